@@ -0,0 +1,54 @@
+//! Benchmark del diff sobre archivos de 50k lineas.
+//! Correr con `cargo bench --bench diff`
+
+use std::time::Instant;
+
+use gitr::diff::Diff;
+
+const LINEAS: usize = 50_000;
+
+// archivo de LINEAS lineas distintas entre si
+fn archivo_base() -> Vec<String> {
+    (0..LINEAS)
+        .map(|i| format!("linea {} del archivo generado: valor = {}", i, i * 7919 % 10007))
+        .collect()
+}
+
+fn medir(nombre: &str, base: &[String], new: &[String]) {
+    let (base, new) = (base.join("\n") + "\n", new.join("\n") + "\n");
+    let inicio = Instant::now();
+    let diff = Diff::new(base, new);
+    println!(
+        "{:<40} {:>8.2?}  (-{} +{})",
+        nombre,
+        inicio.elapsed(),
+        diff.lineas_eliminadas.len(),
+        diff.lineas_agregadas.len()
+    );
+}
+
+fn main() {
+    let base = archivo_base();
+
+    let mut pocos_cambios = base.clone();
+    for i in (0..LINEAS).step_by(5000) {
+        pocos_cambios[i] = format!("linea {} modificada", i);
+    }
+    medir("10 lineas modificadas", &base, &pocos_cambios);
+
+    let mut muchos_cambios = base.clone();
+    for i in (0..LINEAS).step_by(100) {
+        muchos_cambios[i] = format!("linea {} modificada", i);
+    }
+    medir("500 lineas modificadas", &base, &muchos_cambios);
+
+    let mut movido = base[LINEAS / 2..].to_vec();
+    movido.extend_from_slice(&base[..LINEAS / 2]);
+    medir("mitades intercambiadas", &base, &movido);
+
+    let mut insertado = base.clone();
+    for i in (0..LINEAS).step_by(1000).rev() {
+        insertado.insert(i, format!("linea nueva antes de la {}", i));
+    }
+    medir("50 lineas insertadas", &base, &insertado);
+}
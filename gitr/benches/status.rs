@@ -0,0 +1,50 @@
+//! Benchmark del status sobre 3000 archivos, rehasheando todo contra el stat cache del index.
+//! Correr con `cargo bench --bench status`
+
+use std::{fs, path::Path, time::Instant};
+
+use gitr::commands::command_utils::get_working_dir_hashmap;
+use gitr::commands::commands_fn;
+use gitr::file_manager::{self, read_file_bytes, visit_dirs};
+use gitr::objects::blob::Blob;
+
+const CLIENTE: &str = "cliente_bench_status";
+const REPO: &str = "bench_status";
+
+fn main() {
+    let repo_path = format!("{}/{}", CLIENTE, REPO);
+    let _ = fs::remove_dir_all(CLIENTE);
+    fs::create_dir_all(CLIENTE).unwrap();
+    commands_fn::init(vec![REPO.to_string()], CLIENTE.to_string()).unwrap();
+    for dir in 0..30 {
+        fs::create_dir_all(format!("{}/dir{}", repo_path, dir)).unwrap();
+        for file in 0..100 {
+            fs::write(
+                format!("{}/dir{}/file{}", repo_path, dir, file),
+                format!("contenido del archivo {} {}\n", dir, file).repeat(20),
+            )
+            .unwrap();
+        }
+    }
+    commands_fn::add(vec![".".to_string()], CLIENTE.to_string()).unwrap();
+    let repo = file_manager::open_current_repo(CLIENTE.to_string()).unwrap();
+    // la primera pasada refresca las entradas "racy"
+    get_working_dir_hashmap(&repo).unwrap();
+
+    let inicio = Instant::now();
+    for file_path in visit_dirs(Path::new(&repo_path)) {
+        let data = read_file_bytes(file_path).unwrap();
+        Blob::new(data).unwrap();
+    }
+    println!(
+        "{:<40} {:>8.2?}",
+        "rehasheando 3000 archivos",
+        inicio.elapsed()
+    );
+
+    let inicio = Instant::now();
+    get_working_dir_hashmap(&repo).unwrap();
+    println!("{:<40} {:>8.2?}", "con stat cache", inicio.elapsed());
+
+    fs::remove_dir_all(CLIENTE).unwrap();
+}
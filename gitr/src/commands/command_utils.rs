@@ -1,36 +1,38 @@
 use crate::{
-    commands::commands_fn,
-    diff::Diff,
+    diff::{diff_de_lineas, unified_diff, word_diff, Diff},
     file_manager::{
-        self, get_commit, get_current_commit, get_current_repo, get_head, read_index,
+        self, get_commit, get_current_commit, get_head, read_index,
         update_working_directory, visit_dirs, get_tags,
     },
     git_transport::ref_discovery::read_long_stream,
 };
-use crate::{
-    file_manager::get_branches,
-    git_transport::{pack_file::PackFile, ref_discovery},
-    objects::git_object::GitObject,
-};
+use crate::{file_manager::get_branches, git_transport::ref_discovery};
+use crate::git_transport::pack_file::{create_packfile, prepare_contents};
+use crate::object_database::{DiskObjectDatabase, ObjectDatabase, PackObjectDatabase};
 use crate::{
     gitr_errors::GitrError,
+    index::{self, Index, IndexEntry},
     objects::{
         blob::{Blob, TreeEntry},
         commit::Commit,
         tag::Tag,
-        tree::Tree,
+        tree::{self, Tree},
     },
+    repository::Repository,
+    rev_parse::{rev_parse, rev_parse_commit, rev_parse_tree},
 };
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 
 use sha1::{Digest, Sha1};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     fs::{self},
     io::{self, Read, Write},
     net::TcpStream,
+    fmt::Write as FmtWrite,
     path::Path, process::{Command, Stdio},
+    time::{Duration, SystemTime},
 };
 
 /***************************
@@ -88,16 +90,16 @@ pub fn flate2compress(input: String) -> Result<Vec<u8>, GitrError> {
 
 // obtain the hash of an object
 pub fn get_object_hash(
-    cliente: String,
+    repo: &Repository,
     file_path: &mut String,
     write: bool,
 ) -> Result<String, GitrError> {
-    *file_path = file_manager::get_current_repo(cliente.clone())?.to_string() + "/" + file_path;
-    let raw_data = file_manager::read_file(file_path.to_string())?;
+    *file_path = repo.work_path(file_path);
+    let raw_data = file_manager::read_file_bytes(file_path.to_string())?;
     let blob = Blob::new(raw_data)?;
     let res: String = blob.get_hash();
     if write {
-        blob.save(cliente)?;
+        blob.save(repo)?;
     }
     Ok(res)
 }
@@ -105,14 +107,10 @@ pub fn get_object_hash(
 /// returns object hash, output, size and type
 pub fn get_object_properties(
     flags: Vec<String>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(String, String, String, String), GitrError> {
-    let object_hash = &flags[1];
-    let res_output = file_manager::read_object(
-        object_hash,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+    let object_hash = &rev_parse(&flags[1], repo)?;
+    let res_output = file_manager::read_object(object_hash, repo)?;
     let object_type = res_output.split(' ').collect::<Vec<&str>>()[0];
     let _size = res_output.split(' ').collect::<Vec<&str>>()[1];
     let size = _size.split('\0').collect::<Vec<&str>>()[0];
@@ -125,9 +123,9 @@ pub fn get_object_properties(
 }
 
 //Output the contents or other properties such as size, type or delta information of an object
-pub fn _cat_file(flags: Vec<String>, cliente: String) -> Result<String, GitrError> {
+pub fn _cat_file(flags: Vec<String>, repo: &Repository) -> Result<String, GitrError> {
     let (object_hash, res_output, size, object_type) =
-        get_object_properties(flags.clone(), cliente)?;
+        get_object_properties(flags.clone(), repo)?;
     let data_requested = &flags[0];
     if data_requested == "-t" {
         return Ok(object_type);
@@ -175,10 +173,10 @@ pub fn get_tree_data(raw_data: &str) -> String {
         let file_path_hash = file_atributes[1];
         let file_path = file_path_hash.split('\0').collect::<Vec<&str>>()[0];
         let file_hash = file_path_hash.split('\0').collect::<Vec<&str>>()[1];
-        let file_type = if file_mode == "100644" {
-            "blob"
-        } else {
-            "tree"
+        let file_type = match file_mode {
+            "40000" => "tree",
+            "160000" => "commit",
+            _ => "blob",
         };
 
         let entry = format!("{} {} {} {}\n", file_mode, file_type, file_hash, file_path);
@@ -204,23 +202,23 @@ pub fn print_tag_data(raw_data: &str) {
 pub fn create_trees(
     tree_map: HashMap<String, Vec<String>>,
     current_dir: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<Tree, GitrError> {
     let mut tree_entry: Vec<(String, TreeEntry)> = Vec::new();
     if let Some(objs) = tree_map.get(&current_dir) {
         for obj in objs {
             if tree_map.contains_key(obj) {
-                let new_tree = create_trees(tree_map.clone(), obj.to_string(), cliente.clone())?;
+                let new_tree = create_trees(tree_map.clone(), obj.to_string(), repo)?;
                 tree_entry.push((obj.clone(), TreeEntry::Tree(new_tree)));
             } else {
-                let raw_data = file_manager::read_file(obj.clone())?;
+                let raw_data = file_manager::read_blob_content(obj.clone())?;
                 let blob = Blob::new(raw_data)?;
-                tree_entry.push((obj.clone(), TreeEntry::Blob(blob)));
+                tree_entry.push((obj.clone(), TreeEntry::Blob(blob, index::file_mode(obj))));
             }
         }
     };
     let tree = Tree::new(tree_entry)?;
-    tree.save(cliente)?;
+    tree.save(repo)?;
     Ok(tree)
 }
 
@@ -228,12 +226,12 @@ pub fn create_trees(
 pub fn get_tree_entries(
     message: String,
     second_parent: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let (tree_map, tree_order) = get_hashmap_for_checkout(cliente.clone())?;
-    let final_tree = create_trees(tree_map, tree_order[0].clone(), cliente.clone())?;
-    final_tree.save(cliente.clone())?;
-    write_new_commit_and_branch(final_tree, message, second_parent, cliente)?;
+    let (tree_map, tree_order) = get_hashmap_for_checkout(repo)?;
+    let final_tree = create_trees(tree_map, tree_order[0].clone(), repo)?;
+    final_tree.save(repo)?;
+    write_new_commit_and_branch(final_tree, message, second_parent, repo)?;
     Ok(())
 }
 /// write a new commit and the branch if necessary
@@ -241,31 +239,30 @@ pub fn write_new_commit_and_branch(
     final_tree: Tree,
     message: String,
     second_parent: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let head = file_manager::get_head(cliente.clone())?;
-    let repo = file_manager::get_current_repo(cliente.clone())?;
-    let path_complete = repo.clone() + "/gitr/" + head.as_str();
+    let head = file_manager::get_head(repo)?;
+    let path_complete = repo.git_path(&head);
     if fs::metadata(path_complete.clone()).is_err() {
-        let dir = repo + "/gitr/refs/heads/master";
+        let dir = repo.git_path("refs/heads/master");
         file_manager::write_file(path_complete, final_tree.get_hash())?;
         if !Path::new(&dir).exists() {
-            let current_commit = file_manager::get_current_commit(cliente.clone())?;
+            let current_commit = file_manager::get_current_commit(repo)?;
             file_manager::write_file(dir.clone(), current_commit)?;
         }
         let commit = Commit::new(
             final_tree.get_hash(),
             vec!["None".to_string()],
-            cliente.clone(),
-            cliente.clone(),
+            get_current_username(repo),
+            get_current_username(repo),
             message,
-            cliente.clone(),
+            repo,
         )?;
-        commit.save(cliente.clone())?;
+        commit.save(repo)?;
         file_manager::write_file(dir, commit.get_hash())?;
     } else {
-        let dir = repo + "/gitr/" + &head;
-        let current_commit = file_manager::get_current_commit(cliente.clone())?;
+        let dir = repo.git_path(&head);
+        let current_commit = file_manager::get_current_commit(repo)?;
         let mut parents = vec![current_commit];
         if second_parent != "None" {
             parents.push(second_parent);
@@ -273,12 +270,12 @@ pub fn write_new_commit_and_branch(
         let commit = Commit::new(
             final_tree.get_hash(),
             parents,
-            cliente.clone(),
-            cliente.clone(),
+            get_current_username(repo),
+            get_current_username(repo),
             message,
-            cliente.clone(),
+            repo,
         )?;
-        commit.save(cliente)?;
+        commit.save(repo)?;
         file_manager::write_file(dir, commit.get_hash())?;
     }
     Ok(())
@@ -287,14 +284,19 @@ pub fn write_new_commit_and_branch(
 /// returns a hashmap to create trees (using the index)
 type CheckoutHashMap = (HashMap<String, Vec<String>>, Vec<String>);
 
-pub fn get_hashmap_for_checkout(cliente: String) -> Result<CheckoutHashMap, GitrError> {
+pub fn get_hashmap_for_checkout(repo: &Repository) -> Result<CheckoutHashMap, GitrError> {
     let mut tree_map: HashMap<String, Vec<String>> = HashMap::new();
     let mut tree_order: Vec<String> = Vec::new();
-    let index_files = read_index(cliente.clone())?;
-    for file_info in index_files.split('\n') {
-        let file_path = file_info.split(' ').collect::<Vec<&str>>()[3];
+    let index = read_index(repo)?;
+    // el primer tree es el del ultimo directorio del work tree
+    let root = repo.work_tree().split('/').count() - 1;
+    for entry in index.entries() {
+        let file_path = &repo.work_path(&entry.path);
         let splitted_file_path = file_path.split('/').collect::<Vec<&str>>();
         for (i, dir) in (splitted_file_path.clone()).iter().enumerate() {
+            if i < root {
+                continue;
+            }
             if let Some(last_element) = splitted_file_path.last() {
                 if dir == last_element {
                     update_hashmap_tree_entry(
@@ -303,11 +305,11 @@ pub fn get_hashmap_for_checkout(cliente: String) -> Result<CheckoutHashMap, Gitr
                         file_path.to_string(),
                     );
                 } else {
-                    if !tree_map.contains_key(dir as &str) && (dir != &cliente) {
+                    if !tree_map.contains_key(dir as &str) {
                         tree_map.insert(dir.to_string(), vec![]);
                         tree_order.push(dir.to_string());
                     }
-                    if i == 0 {
+                    if i == root {
                         continue;
                     }
                     update_hashmap_tree_entry(
@@ -345,40 +347,133 @@ pub fn update_hashmap_tree_entry(
 // check if the branch exists and if its -b used it creates a branch
 pub fn get_branch_to_checkout(
     args_received: Vec<String>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<String, GitrError> {
     let mut branch_to_checkout: String = args_received[0].clone();
     if args_received.len() == 2 && args_received[0] == "-b" {
         branch_to_checkout = args_received[1].clone();
-        branch_newbranch_flag(branch_to_checkout.clone(), cliente.clone())?;
+        branch_newbranch_flag(branch_to_checkout.clone(), repo)?;
     }
-    if !branch_exists(branch_to_checkout.clone(), cliente.clone()) {
+    if !branch_exists(branch_to_checkout.clone(), repo) {
         return Err(GitrError::BranchNonExistsError(args_received[0].clone()));
     }
     Ok(branch_to_checkout)
 }
 
+/// Lleva el work tree y el index del commit actual al commit destino tocando solo
+/// los archivos que cambian entre los dos trees. Los cambios locales de los demas
+/// archivos (y los archivos sin trackear) se mantienen, como en git.
+/// On Error returns a CheckoutConflict con los archivos que cambian y tienen cambios
+/// locales (en el index o el work tree) que se perderian
+pub fn checkout_commit(destino: &str, repo: &Repository) -> Result<(), GitrError> {
+    let actual = match get_current_commit(repo) {
+        Ok(commit) => index_de_tree(&file_manager::get_main_tree(commit, repo)?, repo)?,
+        Err(_) => Index::new(),
+    };
+    let objetivo = index_de_tree(&file_manager::get_main_tree(destino.to_string(), repo)?, repo)?;
+    // antes de leer el index: puede refrescar los datos de stat
+    let work = get_working_dir_hashmap(repo)?;
+    let mut index = match read_index(repo) {
+        Ok(index) => index,
+        Err(_) => Index::new(),
+    };
+
+    let clave = |entry: Option<&IndexEntry>| entry.map(|entry| (entry.hash.clone(), entry.mode));
+    let hash = |entry: Option<&IndexEntry>| entry.map(|entry| entry.hash.clone());
+    let mut cambian = actual
+        .entries()
+        .iter()
+        .chain(objetivo.entries())
+        .map(|entry| entry.path.clone())
+        .filter(|path| clave(actual.get(path)) != clave(objetivo.get(path)))
+        .collect::<Vec<String>>();
+    cambian.sort();
+    cambian.dedup();
+
+    let mut conflictos = vec![];
+    for path in &cambian {
+        let en_work = work.get(&repo.work_path(path)).cloned();
+        let en_index = hash(index.get(path));
+        let limpio = en_index == hash(actual.get(path)) && en_work == en_index;
+        let ya_actualizado = en_index == hash(objetivo.get(path)) && en_work == en_index;
+        if !limpio && !ya_actualizado {
+            conflictos.push(path.clone());
+        }
+    }
+    // un archivo sin trackear donde el destino tiene un directorio (o al reves)
+    for (archivo, _) in work.iter() {
+        let path = index::relative_path(archivo, repo.work_tree());
+        let pisado = objetivo.entries().iter().any(|e| path.starts_with(&format!("{}/", e.path)));
+        if pisado && index.get(&path).is_none() {
+            conflictos.push(path);
+        }
+    }
+    for entry in objetivo.entries() {
+        let mut directorio = entry.path.as_str();
+        while let Some((padre, _)) = directorio.rsplit_once('/') {
+            directorio = padre;
+            if Path::new(&repo.work_path(directorio)).is_file() && index.get(directorio).is_none() {
+                conflictos.push(directorio.to_string());
+            }
+        }
+    }
+    if !conflictos.is_empty() {
+        conflictos.sort();
+        conflictos.dedup();
+        return Err(GitrError::CheckoutConflict(conflictos));
+    }
+
+    for path in &cambian {
+        let archivo = repo.work_path(path);
+        index.remove(path);
+        match objetivo.get(path) {
+            Some(entry) => {
+                if Path::new(&archivo).is_dir() {
+                    let _ = fs::remove_dir_all(&archivo);
+                }
+                restaurar_archivo(entry, repo)?;
+                let stat = IndexEntry::from_file(&archivo, path.clone(), entry.hash.clone());
+                index.add(stat.unwrap_or(entry.clone()));
+            }
+            None => {
+                let _ = fs::remove_file(&archivo);
+                borrar_directorios_vacios(&archivo, repo);
+            }
+        }
+    }
+    file_manager::write_index(&index, repo)
+}
+
+// borra los directorios que quedaron vacios arriba del archivo, sin salir del work tree
+fn borrar_directorios_vacios(archivo: &str, repo: &Repository) {
+    let raiz = Path::new(repo.work_tree());
+    let mut directorio = Path::new(archivo).parent();
+    while let Some(dir) = directorio {
+        if dir == raiz || !dir.starts_with(raiz) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        directorio = dir.parent();
+    }
+}
+
 /***************************
  ***************************
  *    GET USER DATA
  **************************
  **************************/
 
-/// returns the username
-pub fn get_current_username(cliente: String) -> String {
-    cliente
+/// returns the username: the [user] name of the client's gitrconfig,
+/// or the client name if the config has none (setup_config_file writes the client name there)
+pub fn get_current_username(repo: &Repository) -> String {
+    repo.config().user_name.clone().unwrap_or_default()
 }
 
 /// returns the mail from config
-pub fn get_user_mail_from_config(cliente: String) -> Result<String, GitrError> {
-    let config_data = match file_manager::read_file(cliente + "/gitrconfig") {
-        Ok(config_data) => config_data,
-        Err(e) => return Err(GitrError::FileReadError(e.to_string())),
-    };
-
-    let lines = config_data.split('\n').collect::<Vec<&str>>();
-    let email = lines[1].split('=').collect::<Vec<&str>>()[1].trim_start();
-    Ok(email.to_string())
+pub fn get_user_mail_from_config(repo: &Repository) -> Result<String, GitrError> {
+    match &repo.config().user_email {
+        Some(email) => Ok(email.to_string()),
+        None => Err(GitrError::FileReadError("gitrconfig".to_string())),
+    }
 }
 
 /***************************
@@ -388,28 +483,52 @@ pub fn get_user_mail_from_config(cliente: String) -> Result<String, GitrError> {
  **************************/
 
 /// returns cached ls-files
-pub fn get_ls_files_cached(cliente: String) -> Result<String, GitrError> {
+pub fn get_ls_files_cached(repo: &Repository) -> Result<String, GitrError> {
     let mut string_res = String::new();
-    let index = match read_index(cliente.clone()) {
+    let index = match read_index(repo) {
         Ok(index) => index,
         Err(_) => return Ok(string_res),
     };
-    for file_path in index.lines() {
-        let correct_path = match file_path.split_once('/') {
-            Some((_path, file)) => file,
-            None => file_path,
-        };
-        let line = correct_path.to_string() + "\n";
+    for entry in index.entries() {
+        let line = entry.path.clone() + "\n";
+        string_res.push_str(&line);
+    }
+    Ok(string_res)
+}
+
+/// returns ls-files --stage: mode, hash and stage of every entry of the index
+pub fn get_ls_files_stage(repo: &Repository) -> Result<String, GitrError> {
+    let mut string_res = String::new();
+    let index = match read_index(repo) {
+        Ok(index) => index,
+        Err(_) => return Ok(string_res),
+    };
+    for entry in index.entries() {
+        let line = format!("{:o} {} {}\t{}\n", entry.mode, entry.hash, entry.stage(), entry.path);
+        string_res.push_str(&line);
+    }
+    Ok(string_res)
+}
+
+/// returns ls-files --unmerged: the entries of the index with a conflict stage
+pub fn get_ls_files_unmerged(repo: &Repository) -> Result<String, GitrError> {
+    let mut string_res = String::new();
+    let index = match read_index(repo) {
+        Ok(index) => index,
+        Err(_) => return Ok(string_res),
+    };
+    for entry in index.entries().iter().filter(|entry| entry.stage() != 0) {
+        let line = format!("{:o} {} {}\t{}\n", entry.mode, entry.hash, entry.stage(), entry.path);
         string_res.push_str(&line);
     }
     Ok(string_res)
 }
 
 /// returns deleted files or modified files depending on bool received
-pub fn get_ls_files_deleted_modified(deleted: bool, cliente: String) -> Result<String, GitrError> {
+pub fn get_ls_files_deleted_modified(deleted: bool, repo: &Repository) -> Result<String, GitrError> {
     let mut res = String::new();
-    let (not_staged, _, _) = get_untracked_notstaged_files(cliente.clone())?;
-    let files_not_staged = get_status_files_not_staged(&not_staged, cliente.clone())?;
+    let (not_staged, _, _) = get_untracked_notstaged_files(repo)?;
+    let files_not_staged = get_status_files_not_staged(&not_staged, repo)?;
     for line in files_not_staged.lines() {
         if line.contains("deleted") && deleted {
             let line = line.replace("deleted:   ", "");
@@ -430,12 +549,16 @@ pub fn get_ls_files_deleted_modified(deleted: bool, cliente: String) -> Result<S
  **************************/
 
 /// print all the branches in repo
-pub fn print_branches(cliente: String) -> Result<String, GitrError> {
+pub fn print_branches(repo: &Repository) -> Result<String, GitrError> {
     let mut res = String::new();
-    let head = file_manager::get_head(cliente.clone())?;
+    let head = file_manager::get_head(repo)?;
     let head_vec = head.split('/').collect::<Vec<&str>>();
     let head = head_vec[head_vec.len() - 1];
-    let branches = file_manager::get_branches(cliente.clone())?;
+    if file_manager::get_current_branch(repo)?.is_none() {
+        let commit = get_current_commit(repo)?;
+        res.push_str(&format!("* \x1b[92m(HEAD detached at {})\x1b[0m\n", &commit[..7]));
+    }
+    let branches = file_manager::get_branches(repo)?;
     for branch in branches {
         if head == branch {
             let index_branch = format!("* \x1b[92m{}\x1b[0m", branch);
@@ -448,8 +571,8 @@ pub fn print_branches(cliente: String) -> Result<String, GitrError> {
 }
 
 /// check if a branch exists
-pub fn branch_exists(branch: String, cliente: String) -> bool {
-    let branches = file_manager::get_branches(cliente.clone());
+pub fn branch_exists(branch: String, repo: &Repository) -> bool {
+    let branches = file_manager::get_branches(repo);
     let branches = match branches {
         Ok(branches) => branches,
         Err(_) => return false,
@@ -463,11 +586,11 @@ pub fn branch_exists(branch: String, cliente: String) -> bool {
 }
 
 /// branch -d flag function
-pub fn branch_delete_flag(branch: String, cliente: String) -> Result<(), GitrError> {
-    if !branch_exists(branch.clone(), cliente.clone()) {
+pub fn branch_delete_flag(branch: String, repo: &Repository) -> Result<(), GitrError> {
+    if !branch_exists(branch.clone(), repo) {
         return Err(GitrError::BranchNonExistsError(branch));
     }
-    file_manager::delete_branch(branch, false, cliente.clone())?;
+    file_manager::delete_branch(branch, false, repo)?;
     Ok(())
 }
 
@@ -475,49 +598,46 @@ pub fn branch_delete_flag(branch: String, cliente: String) -> Result<(), GitrErr
 pub fn branch_move_flag(
     branch_origin: String,
     branch_destination: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    if !branch_exists(branch_origin.clone(), cliente.clone()) {
+    if !branch_exists(branch_origin.clone(), repo) {
         return Err(GitrError::BranchNonExistsError(branch_origin));
     }
-    if branch_exists(branch_destination.clone(), cliente.clone()) {
+    if branch_exists(branch_destination.clone(), repo) {
         return Err(GitrError::BranchAlreadyExistsError(branch_destination));
     }
-    let repo = get_current_repo(cliente.clone())?;
-    let old_path = format!("{}/gitr/refs/heads/{}", repo.clone(), branch_origin);
-    let new_path = format!("{}/gitr/refs/heads/{}", repo.clone(), branch_destination);
+    let old_path = repo.git_path(&format!("refs/heads/{}", branch_origin));
+    let new_path = repo.git_path(&format!("refs/heads/{}", branch_destination));
     file_manager::move_branch(old_path.clone(), new_path.clone())?;
-    let head = get_head(cliente.clone())?;
-    if branch_origin == head.split('/').collect::<Vec<&str>>()[2] {
+    if file_manager::get_current_branch(repo)? == Some(branch_origin) {
         let ref_correct = format!("refs/heads/{}", branch_destination);
-        file_manager::update_head(&ref_correct, cliente.clone())?;
+        file_manager::update_head(&ref_correct, repo)?;
     }
     Ok(())
 }
 
 /// branch <newbranch> flag function
-pub fn branch_newbranch_flag(branch: String, cliente: String) -> Result<(), GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    if branch_exists(branch.clone(), cliente.clone()) {
+pub fn branch_newbranch_flag(branch: String, repo: &Repository) -> Result<(), GitrError> {
+    if branch_exists(branch.clone(), repo) {
         return Err(GitrError::BranchAlreadyExistsError(branch));
     }
-    let current_commit = file_manager::get_current_commit(cliente.clone())?;
+    let current_commit = file_manager::get_current_commit(repo)?;
     file_manager::write_file(
-        format!("{}/gitr/refs/heads/{}", repo.clone(), branch),
+        repo.git_path(&format!("refs/heads/{}", branch)),
         current_commit,
     )?;
     Ok(())
 }
 
 /// receives a branch_name and returns the commit hash
-pub fn branch_commits_list(branch_name: String, cliente: String) -> Result<Vec<String>, GitrError> {
+pub fn branch_commits_list(branch_name: String, repo: &Repository) -> Result<Vec<String>, GitrError> {
     let mut commits = Vec::new();
 
-    let mut commit = file_manager::get_commit(branch_name, cliente.clone())?;
+    let mut commit = rev_parse_commit(&branch_name, repo)?;
     
     commits.push(commit.clone());
     loop {
-        let parent = file_manager::get_parent_commit(commit.clone(), cliente.clone())?[0].clone();
+        let parent = file_manager::get_parent_commit(commit.clone(), repo)?[0].clone();
 
         if parent == "None" {
             break;
@@ -535,51 +655,279 @@ pub fn branch_commits_list(branch_name: String, cliente: String) -> Result<Vec<S
  **************************/
 
 /// prints the commit confirmation after commiting
-pub fn print_commit_confirmation(message: String, cliente: String) -> Result<(), GitrError> {
-    let branch = get_head(cliente.clone())?.split('/').collect::<Vec<&str>>()[2].to_string();
-    let hash_recortado = &get_current_commit(cliente.clone())?[0..7];
+pub fn print_commit_confirmation(message: String, repo: &Repository) -> Result<(), GitrError> {
+    let branch = match file_manager::get_current_branch(repo)? {
+        Some(branch) => branch,
+        None => "detached HEAD".to_string(),
+    };
+    let hash_recortado = &get_current_commit(repo)?[0..7];
     println!("[{} {}] {}", branch, hash_recortado, message);
     Ok(())
 }
 /// check if a commit exist
-pub fn commit_existing(cliente: String) -> Result<(), GitrError> {
-    let repo = file_manager::get_current_repo(cliente.clone())?;
-    let head = file_manager::get_head(cliente.clone())?;
-    let branch_name = head.split('/').collect::<Vec<&str>>()[2];
-    if fs::metadata(repo.clone() + "/gitr/" + &head).is_err() {
+pub fn commit_existing(repo: &Repository) -> Result<(), GitrError> {
+    let head = file_manager::get_head(repo)?;
+    let branch_name = head.trim_start_matches("refs/heads/");
+    if fs::metadata(repo.git_path(&head)).is_err() {
         return Err(GitrError::NoCommitExisting(branch_name.to_string()));
     }
     Ok(())
 }
 
+/***************************
+ ***************************
+ *   MERGE-BASE FUNCTIONS
+ **************************
+ **************************/
+
+/// Todos los commits alcanzables desde los recibidos (incluidos ellos), siguiendo
+/// todos los padres. Estan en el orden en que se recorren (BFS)
+pub fn ancestros(commits: Vec<String>, repo: &Repository) -> Result<Vec<String>, GitrError> {
+    let mut visitados = HashSet::new();
+    let mut pendientes = VecDeque::from(commits);
+    let mut res = vec![];
+    while let Some(commit) = pendientes.pop_front() {
+        if commit == "None" || !visitados.insert(commit.clone()) {
+            continue;
+        }
+        pendientes.extend(file_manager::get_parent_commit(commit.clone(), repo)?);
+        res.push(commit);
+    }
+    Ok(res)
+}
+
+/// Ancestros comunes mas cercanos de dos commits: los que son ancestros de los dos
+/// pero no de otro ancestro comun. Hay mas de uno en los merges cruzados (criss-cross)
+/// y ninguno si las historias no estan relacionadas
+pub fn merge_bases(a: &str, b: &str, repo: &Repository) -> Result<Vec<String>, GitrError> {
+    let de_b: HashSet<String> = ancestros(vec![b.to_string()], repo)?.into_iter().collect();
+    let comunes: Vec<String> = ancestros(vec![a.to_string()], repo)?
+        .into_iter()
+        .filter(|commit| de_b.contains(commit))
+        .collect();
+    // los ancestros de un comun tambien son comunes, asi que alcanza con sacar
+    // todo lo alcanzable desde sus padres
+    let mut padres = vec![];
+    for commit in &comunes {
+        padres.extend(file_manager::get_parent_commit(commit.clone(), repo)?);
+    }
+    let no_mejores: HashSet<String> = ancestros(padres, repo)?.into_iter().collect();
+    Ok(comunes
+        .into_iter()
+        .filter(|commit| !no_mejores.contains(commit))
+        .collect())
+}
+
+/// returns merge-base: la merge base de las dos revisiones, o todas con --all, una por linea
+/// On Error returns a UnrelatedHistories si no tienen ningun commit en comun
+pub fn get_merge_base(
+    a: &str,
+    b: &str,
+    todas: bool,
+    repo: &Repository,
+) -> Result<String, GitrError> {
+    let commit_a = rev_parse_commit(a, repo)?;
+    let commit_b = rev_parse_commit(b, repo)?;
+    let bases = merge_bases(&commit_a, &commit_b, repo)?;
+    if bases.is_empty() {
+        return Err(GitrError::UnrelatedHistories(a.to_string(), b.to_string()));
+    }
+    let cantidad = if todas { bases.len() } else { 1 };
+    let mut string_res = String::new();
+    for base in bases.iter().take(cantidad) {
+        string_res.push_str(&(base.clone() + "\n"));
+    }
+    Ok(string_res)
+}
+
+/// Tree a usar como base del three way merge. Si hay varias merge bases se mergean
+/// entre ellas (recursivamente) en un tree virtual, y si no hay ninguna se usa el tree vacio
+pub fn tree_de_merge_bases(bases: &[String], repo: &Repository) -> Result<String, GitrError> {
+    let (primera, resto) = match bases.split_first() {
+        Some(partes) => partes,
+        None => {
+            let tree_vacio = Tree::new_from_packfile(&[])?;
+            tree_vacio.save(repo)?;
+            return Ok(tree_vacio.get_hash());
+        }
+    };
+    let mut tree = file_manager::get_main_tree(primera.clone(), repo)?;
+    for otra in resto {
+        let base = tree_de_merge_bases(&merge_bases(primera, otra, repo)?, repo)?;
+        let otra = file_manager::get_main_tree(otra.clone(), repo)?;
+        tree = merge_de_trees_virtual(&base, &tree, &otra, repo)?;
+    }
+    Ok(tree)
+}
+
+// mergea dos trees sin tocar el work tree ni el index y guarda el resultado.
+// Los conflictos de contenido quedan con sus marcas en el archivo y en los
+// modify/delete se queda la version del base, como hace git con la base virtual
+fn merge_de_trees_virtual(
+    base: &String,
+    origin: &String,
+    branch: &String,
+    repo: &Repository,
+) -> Result<String, GitrError> {
+    let base_tree = index_de_tree(base, repo)?;
+    let origin_tree = index_de_tree(origin, repo)?;
+    let branch_tree = index_de_tree(branch, repo)?;
+    let mut res = Index::new();
+
+    let mut paths: Vec<&String> = [&base_tree, &origin_tree, &branch_tree]
+        .iter()
+        .flat_map(|tree| tree.entries().iter().map(|entry| &entry.path))
+        .collect();
+    paths.sort();
+    paths.dedup();
+    let version = |entry: Option<&IndexEntry>| entry.map(|entry| (entry.hash.clone(), entry.mode));
+    for path in paths {
+        let base = base_tree.get(path);
+        let origin = origin_tree.get(path);
+        let branch = branch_tree.get(path);
+        let elegido = if version(origin) == version(branch) || version(base) == version(branch) {
+            origin.cloned()
+        } else if version(base) == version(origin) {
+            branch.cloned()
+        } else if let (Some(origin), Some(branch)) = (origin, branch) {
+            let (contenido, _) = contenido_mergeado(
+                base,
+                origin,
+                branch,
+                EstiloConflicto::Merge,
+                ResolucionConflicto::Marcar,
+                repo,
+            )?;
+            let blob = Blob::new(contenido.into_bytes())?;
+            blob.save(repo)?;
+            let modo = modo_mergeado(base, origin, branch);
+            Some(IndexEntry::new(path.clone(), blob.get_hash(), modo))
+        } else {
+            base.cloned()
+        };
+        if let Some(entry) = elegido {
+            res.add(entry);
+        }
+    }
+    escribir_tree_de_index(&res, "", repo)
+}
+
+// guarda los trees del directorio (y sus subdirectorios) con las entradas del index
+fn escribir_tree_de_index(
+    index: &Index,
+    directorio: &str,
+    repo: &Repository,
+) -> Result<String, GitrError> {
+    // git ordena los subdirectorios como si terminaran en '/'
+    let mut entradas: Vec<(String, Vec<u8>)> = vec![];
+    let mut subdirectorios = HashSet::new();
+    for entry in index.entries() {
+        let nombre = match entry.path.strip_prefix(directorio) {
+            Some(nombre) => nombre,
+            None => continue,
+        };
+        match nombre.split_once('/') {
+            Some((subdirectorio, _)) => {
+                subdirectorios.insert(subdirectorio.to_string());
+            }
+            None => {
+                let hash = tree::get_formated_hash(entry.hash.clone(), &entry.path)?;
+                let modo = format!("{:o} ", entry.mode);
+                let entrada = [modo.as_bytes(), nombre.as_bytes(), b"\0", &hash].concat();
+                entradas.push((nombre.to_string(), entrada));
+            }
+        }
+    }
+    for subdirectorio in subdirectorios {
+        let prefijo = format!("{}{}/", directorio, subdirectorio);
+        let hash = escribir_tree_de_index(index, &prefijo, repo)?;
+        let hash = tree::get_formated_hash(hash, &prefijo)?;
+        let entrada = [b"40000 ", subdirectorio.as_bytes(), b"\0", &hash].concat();
+        entradas.push((subdirectorio + "/", entrada));
+    }
+    entradas.sort_by(|a, b| a.0.cmp(&b.0));
+    let datos: Vec<u8> = entradas
+        .into_iter()
+        .flat_map(|(_, entrada)| entrada)
+        .collect();
+    let tree = Tree::new_from_packfile(&datos)?;
+    tree.save(repo)?;
+    Ok(tree.get_hash())
+}
+
 /***************************
  ***************************
  *   MERGE FUNCTIONS
  **************************
  **************************/
 
-/// receives a branch name and makes fast forward merge
-pub fn fast_forward_merge(branch_name: String, cliente: String) -> Result<(), GitrError> {
-    let commit: String = file_manager::get_commit(branch_name, cliente.clone())?;
-    let head = get_head(cliente.clone())?;
-    let repo = get_current_repo(cliente.clone())?;
-    let mut path = format!("{}/gitr/{}", repo, head);
+/// Como se escriben los conflictos en el work tree (merge.conflictstyle del config)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EstiloConflicto {
+    /// <<<<<<< ours / ======= / >>>>>>> theirs (por defecto)
+    #[default]
+    Merge,
+    /// como Merge, con la version del ancestro comun despues de ||||||| base
+    Diff3,
+}
 
-    if cliente.contains('/') {
-        path = path.replace("/gitr/", "/");
+impl EstiloConflicto {
+    pub fn from_config(repo: &Repository) -> EstiloConflicto {
+        match repo.config().merge_conflict_style.as_deref() {
+            Some("diff3") => EstiloConflicto::Diff3,
+            _ => EstiloConflicto::Merge,
+        }
     }
+}
 
-    file_manager::write_file(path, commit.clone())?;
-    
-    if cliente.contains('/') {
+/// -X ours / -X theirs: como se resuelven los conflictos de contenido
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResolucionConflicto {
+    /// el conflicto queda marcado en el archivo (por defecto)
+    #[default]
+    Marcar,
+    /// se queda con las lineas de HEAD
+    Ours,
+    /// se queda con las lineas de la branch que se mergea
+    Theirs,
+}
+
+/// --ff, --no-ff y --ff-only
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModoFastForward {
+    /// hace fast-forward si se puede (por defecto)
+    #[default]
+    Permitido,
+    /// siempre crea el commit de merge
+    Nunca,
+    /// falla si no se puede hacer fast-forward
+    Solo,
+}
+
+/// Opciones del merge, ver commands_fn::merge
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OpcionesMerge {
+    pub fast_forward: ModoFastForward,
+    /// deja el resultado en el index y el work tree sin commitear ni mover HEAD
+    pub squash: bool,
+    pub resolucion: ResolucionConflicto,
+}
+
+/// receives a branch name and makes fast forward merge
+pub fn fast_forward_merge(branch_name: String, repo: &Repository) -> Result<(), GitrError> {
+    let commit: String = rev_parse_commit(&branch_name, repo)?;
+    let head = get_head(repo)?;
+    file_manager::write_file(repo.git_path(&head), commit.clone())?;
+
+    if repo.is_bare() {
         return Ok(());
     }
-    update_working_directory(commit, cliente.clone())?;
+    update_working_directory(commit, repo)?;
     Ok(())
 }
 
-pub fn get_blobs_from_commit(commit_hash: String, cliente: String) -> Result<(), GitrError> {
-    let _path_and_hash_hashmap = get_commit_hashmap(commit_hash, cliente.clone())?;
+pub fn get_blobs_from_commit(commit_hash: String, repo: &Repository) -> Result<(), GitrError> {
+    let _path_and_hash_hashmap = get_commit_hashmap(commit_hash, repo)?;
     Ok(())
 }
 
@@ -633,27 +981,53 @@ fn _aplicar_diffs(string_archivo: String, diff: Diff) -> Result<Vec<String>, Git
     Ok(archivo_reconstruido)
 }
 
-fn aplicar_diffs(path: String, diff: Diff) -> Result<(), GitrError> {
-    let string_archivo = file_manager::read_file(path.clone())?;
-    let archivo_reconstruido = _aplicar_diffs(string_archivo.clone(), diff.clone())?;
-    file_manager::write_file(path, archivo_reconstruido.concat().to_string())?;
-
-    Ok(()) 
+// con -X ours/theirs el conflicto se resuelve con las lineas de ese lado, si no se
+// marca. Devuelve las lineas que quedan y si sigue habiendo conflicto
+fn resolver_conflicto(
+    origin_conflicts: String,
+    new_conflicts: String,
+    base: Option<String>,
+    resolucion: ResolucionConflicto,
+) -> (String, bool) {
+    match resolucion {
+        ResolucionConflicto::Ours => (origin_conflicts, false),
+        ResolucionConflicto::Theirs => (new_conflicts, false),
+        ResolucionConflicto::Marcar => {
+            (armar_conflict2(origin_conflicts, new_conflicts, base), true)
+        }
+    }
 }
 
-fn armar_conflict2(origin_conflicts: String, new_conflicts: String) -> String {
-    //armo el conflict y vacío los vectores para "reiniciarlos"
-    let conflict = [
-        "<<<<<<< HEAD\n",
-        origin_conflicts.as_str(),
-        "\n=======\n",
-        new_conflicts.as_str(),
-        "\n>>>>>>> BRANCH",
-    ]
-    .concat();
+// base es la parte del ancestro comun, solo en estilo diff3 (puede no tener lineas)
+fn armar_conflict2(origin_conflicts: String, new_conflicts: String, base: Option<String>) -> String {
+    let mut conflict = ["<<<<<<< ours\n", origin_conflicts.as_str(), "\n"].concat();
+    if let Some(base) = base {
+        conflict.push_str("||||||| base\n");
+        if !base.is_empty() {
+            conflict.push_str(&(base + "\n"));
+        }
+    }
+    conflict.push_str(&["=======\n", new_conflicts.as_str(), "\n>>>>>>> theirs"].concat());
     conflict
 }
 
+// lineas del base que borran los diffs a partir de la linea del conflicto,
+// None si el estilo no muestra el base
+fn base_del_conflicto(
+    borradas: &HashMap<usize, String>,
+    index: usize,
+    estilo: EstiloConflicto,
+) -> Option<String> {
+    if estilo == EstiloConflicto::Merge {
+        return None;
+    }
+    let mut lineas = vec![];
+    while let Some(linea) = borradas.get(&(index + lineas.len())) {
+        lineas.push(linea.clone());
+    }
+    Some(lineas.join("\n"))
+}
+
 fn juntar_consecutivos(diff: Diff) -> Diff {
     let mut diff_juntado = Diff::new("".to_string(), "".to_string());
 
@@ -756,15 +1130,45 @@ fn preparar_lineas_para_comparar(
     result
 }
 
+/// Como se escriben (y con -X ours/theirs se resuelven) los conflictos de contenido
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OpcionesConflicto {
+    pub estilo: EstiloConflicto,
+    pub resolucion: ResolucionConflicto,
+}
+
+// une los diffs con las opciones por defecto: conflictos marcados sin el base
+#[cfg(test)]
 fn comparar_diffs(
     diff_base_origin: Diff,
     diff_base_branch: Diff,
     limite_archivo: usize,
+) -> Result<(Diff, bool), GitrError> {
+    comparar_diffs_con_opciones(
+        diff_base_origin,
+        diff_base_branch,
+        limite_archivo,
+        OpcionesConflicto::default(),
+    )
+}
+
+fn comparar_diffs_con_opciones(
+    diff_base_origin: Diff,
+    diff_base_branch: Diff,
+    limite_archivo: usize,
+    opciones: OpcionesConflicto,
 ) -> Result<(Diff, bool), GitrError> {
     let mut diff_final = Diff::new("".to_string(), "".to_string());
 
     let result = preparar_lineas_para_comparar(diff_base_origin, diff_base_branch);
 
+    // lineas del base que borra alguno de los dos diffs, para el estilo diff3
+    let borradas: HashMap<usize, String> = result
+        .iter()
+        .filter(|(_, flag, _, _)| !flag)
+        .map(|(index, _, string, _)| (*index, string.clone()))
+        .collect();
+
     // dic solo con los de agregar
     let mut map: HashMap<usize, Vec<(String, String)>> = HashMap::new();
     for (index, flag, string, tag) in result.clone() {
@@ -809,8 +1213,10 @@ fn comparar_diffs(
         }
 
         //para este punto hay un conflict
-        hubo_conflict = true;
-        let conflict = armar_conflict2(lineas[0].0.clone(), lineas[1].0.clone());
+        let base = base_del_conflicto(&borradas, index, opciones.estilo);
+        let (conflict, quedo_conflict) =
+            resolver_conflicto(lineas[0].0.clone(), lineas[1].0.clone(), base, opciones.resolucion);
+        hubo_conflict |= quedo_conflict;
         diff_final.lineas.push((index, flag, conflict));
         indices_ya_visitados.insert(index);
     }
@@ -837,7 +1243,9 @@ fn comparar_diffs(
                 new = result[iter_count as usize].clone();
             }
 
-            let conflict = armar_conflict2(origin.2, new.2);
+            let base = base_del_conflicto(&borradas, origin.0, opciones.estilo);
+            let (conflict, quedo_conflict) = resolver_conflicto(origin.2, new.2, base, opciones.resolucion);
+            hubo_conflict |= quedo_conflict;
             diff_final.lineas.push((
                 result[iter_count as usize].0,
                 result[iter_count as usize].1,
@@ -849,162 +1257,410 @@ fn comparar_diffs(
     Ok((diff_final, hubo_conflict))
 }
 
+// the line diff works on text, so blob contents are read lossily for the merge
+fn read_blob_as_text(hash: String, repo: &Repository) -> Result<String, GitrError> {
+    let data = file_manager::read_file_data_from_blob_hash(hash, repo)?;
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+/// Merge de tres vias entre el tree base (ver tree_de_merge_bases) y los commits
+/// origin (HEAD, el del work tree) y branch. Por cada path que este en alguno de los tres:
+/// - si branch no lo cambio, o los dos lados quedaron iguales, queda el de origin
+/// - si solo lo cambio branch se toma su version (agregado, modificado o borrado)
+/// - si lo cambiaron los dos se mergea el contenido (add/add si no estaba en base),
+///   y los conflictos se marcan o se resuelven segun -X ours/theirs
+/// - si un lado lo borro y el otro lo modifico es un conflicto modify/delete
+///
+/// Se compara el blob y el modo: un cambio de modo de branch tambien se toma.
+/// Si un lado renombro un archivo sin cambiarlo y el otro lo modifico en el path
+/// viejo, los cambios se mergean en el path nuevo (ver renombres).
+/// Los paths con conflicto quedan sin mergear en el index (ver registrar_conflicto).
+/// Con verificar_locales no se mergea si eso pisaria cambios locales (ver
+/// verificar_cambios_locales); rebase no lo usa porque su origin no es el del work tree.
+/// Devuelve si hubo conflictos y los nombres de los archivos con conflicto
 pub fn three_way_merge(
-    base_commit: String,
+    base_tree: String,
     origin_commit: String,
     branch_commit: String,
-    cliente: String,
+    resolucion: ResolucionConflicto,
+    verificar_locales: bool,
+    repo: &Repository,
 ) -> Result<(bool, Vec<String>), GitrError> {
-    let branch_hashmap = get_commit_hashmap(branch_commit.clone(), cliente.clone())?;
-    let mut origin_hashmap = get_commit_hashmap(origin_commit.clone(), cliente.clone())?;
-    file_manager::add_new_files_from_merge(
-        origin_hashmap.clone(),
-        branch_hashmap.clone(),
-        cliente.clone(),
-    )?;
-    origin_hashmap = get_commit_hashmap(origin_commit.clone(), cliente.clone())?;
-    let base_hashmap = get_commit_hashmap(base_commit.clone(), cliente.clone())?;
+    let base_tree = index_de_tree(&base_tree, repo)?;
+    let origin_tree = index_de_tree(&file_manager::get_main_tree(origin_commit, repo)?, repo)?;
+    let branch_tree = index_de_tree(&file_manager::get_main_tree(branch_commit, repo)?, repo)?;
+    let estilo = EstiloConflicto::from_config(repo);
     let mut hubo_conflict = false;
     let mut archivos_conflict = Vec::new();
 
+    // los renombres de un lado se aplican al base y al otro lado, asi el
+    // archivo se mergea en el path nuevo
+    let renombres_branch = renombres(&base_tree, &branch_tree, &origin_tree);
+    let renombres_origin = renombres(&base_tree, &origin_tree, &branch_tree);
+    let mut base_vista = base_tree.clone();
+    let mut origin_vista = origin_tree.clone();
+    let mut branch_vista = branch_tree.clone();
+    for (viejo, nuevo) in &renombres_branch {
+        mover_entry(&mut base_vista, viejo, nuevo);
+        mover_entry(&mut origin_vista, viejo, nuevo);
+    }
+    for (viejo, nuevo) in &renombres_origin {
+        mover_entry(&mut base_vista, viejo, nuevo);
+        mover_entry(&mut branch_vista, viejo, nuevo);
+    }
 
-    for (path, origin_file_hash) in origin_hashmap.iter() {
-        let origin_file_data: String =
-            file_manager::read_file_data_from_blob_hash(origin_file_hash.clone(), cliente.clone())?;
-        if branch_hashmap.contains_key(&path.clone()) {
-            let branch_file_hash = branch_hashmap[path].clone(); //aax
-            let branch_file_data = file_manager::read_file_data_from_blob_hash(
-                branch_file_hash.clone(),
-                cliente.clone(),
-            )?;
-
-            if origin_file_hash == &branch_file_hash {
-                continue;
+    let version = |entry: Option<&IndexEntry>| entry.map(|entry| (entry.hash.clone(), entry.mode));
+    // solo se tocan los paths que cambio branch y que no quedaron iguales a origin
+    let mut paths: Vec<&String> = [&base_vista, &origin_vista, &branch_vista]
+        .iter()
+        .flat_map(|tree| tree.entries().iter().map(|entry| &entry.path))
+        .filter(|path| {
+            let branch = version(branch_vista.get(path));
+            branch != version(origin_vista.get(path)) && branch != version(base_vista.get(path))
+        })
+        .collect();
+    // el path nuevo de un renombre de branch no esta en el work tree aunque no haya cambios
+    paths.extend(renombres_branch.iter().map(|(_, nuevo)| nuevo));
+    paths.sort();
+    paths.dedup();
+    if verificar_locales && !repo.is_bare() {
+        // los paths viejos de los renombres de branch tambien se borran del work tree
+        let mut a_verificar = paths.clone();
+        a_verificar.extend(renombres_branch.iter().map(|(viejo, _)| viejo));
+        verificar_cambios_locales(&a_verificar, &origin_tree, repo)?;
+    }
+    for (viejo, _) in &renombres_branch {
+        tomar_version_de_branch(viejo, None, repo)?;
+    }
+    for path in paths {
+        let base = base_vista.get(path);
+        let origin = origin_vista.get(path);
+        let branch = branch_vista.get(path);
+        if version(base) == version(origin) {
+            tomar_version_de_branch(path, branch, repo)?;
+            continue;
+        }
+        let mensaje = match (origin, branch) {
+            (Some(origin), Some(branch)) => {
+                if !merge_de_contenido(path, base, origin, branch, estilo, resolucion, repo)? {
+                    continue;
+                }
+                let tipo = if base.is_some() { "content" } else { "add/add" };
+                format!("CONFLICT ({}): Merge conflict in {}", tipo, path)
+            }
+            (None, Some(branch)) => {
+                // queda la version de branch en el work tree para resolver el conflicto
+                if !repo.is_bare() {
+                    restaurar_archivo(branch, repo)?;
+                }
+                format!(
+                    "CONFLICT (modify/delete): {} deleted in HEAD and modified in theirs",
+                    path
+                )
             }
+            _ => format!(
+                "CONFLICT (modify/delete): {} deleted in theirs and modified in HEAD",
+                path
+            ),
+        };
+        hubo_conflict = true;
+        if let Some(nombre_archivo) = path.split('/').last() {
+            archivos_conflict.push(nombre_archivo.to_string());
+        }
+        if !repo.is_bare() {
+            println!("{}", mensaje);
+            registrar_conflicto(path, base, origin, branch, repo)?;
+        }
+    }
 
-            let mut base_file_hash = "".to_string();
-            let base_file_data;
+    Ok((hubo_conflict, archivos_conflict))
+}
 
-            if !base_hashmap.contains_key(path) {
-                base_file_data = "".to_string();
-            } else {
-                base_file_hash = base_hashmap[path].clone(); // chequear que capaz puede no exisiir en base
-                base_file_data = file_manager::read_file_data_from_blob_hash(
-                    base_file_hash.clone(),
-                    cliente.clone(),
-                )?;
-            }
+// como en checkout_commit, no se mergea si algun path que se va a escribir tiene cambios
+// locales en el index o el work tree, o es un archivo sin trackear que se pisaria.
+// On Error returns a MergeLocalChanges con esos paths
+fn verificar_cambios_locales(
+    paths: &[&String],
+    origin_tree: &Index,
+    repo: &Repository,
+) -> Result<(), GitrError> {
+    // antes de leer el index: puede refrescar los datos de stat
+    let work = get_working_dir_hashmap(repo)?;
+    let index = file_manager::read_index(repo).unwrap_or_default();
+    let hash = |entry: Option<&IndexEntry>| entry.map(|entry| entry.hash.clone());
+    let mut pisados = vec![];
+    for path in paths {
+        let en_head = hash(origin_tree.get(path));
+        let en_index = hash(index.get(path));
+        let en_work = work.get(&repo.work_path(path)).cloned();
+        if en_index != en_head || en_work != en_head {
+            pisados.push(path.to_string());
+        }
+    }
+    if !pisados.is_empty() {
+        return Err(GitrError::MergeLocalChanges(pisados));
+    }
+    Ok(())
+}
 
-            if &base_file_hash == origin_file_hash {
-                let diff_base_branch = Diff::new(base_file_data, branch_file_data);
-                if cliente.contains('/') {
-                    _aplicar_diffs(origin_file_data.clone(), diff_base_branch)?;
-                } else {
-                    aplicar_diffs(path.clone(), diff_base_branch)?;
-                }
-                continue;
-            }
+// renombres exactos de un lado: (path viejo, path nuevo) de los archivos que movio
+// sin cambiarlos, si el otro lado todavia tiene el path viejo y no tiene el nuevo
+fn renombres(base: &Index, lado: &Index, otro: &Index) -> Vec<(String, String)> {
+    let mut res: Vec<(String, String)> = vec![];
+    for nuevo in lado.entries() {
+        if base.get(&nuevo.path).is_some() || otro.get(&nuevo.path).is_some() {
+            continue;
+        }
+        let viejo = base.entries().iter().find(|viejo| {
+            viejo.hash == nuevo.hash
+                && lado.get(&viejo.path).is_none()
+                && otro.get(&viejo.path).is_some()
+                && !res.iter().any(|(usado, _)| *usado == viejo.path)
+        });
+        if let Some(viejo) = viejo {
+            res.push((viejo.path.clone(), nuevo.path.clone()));
+        }
+    }
+    res
+}
 
-            if base_file_hash == branch_file_hash {
-                continue;
-            }
+fn mover_entry(index: &mut Index, viejo: &str, nuevo: &str) {
+    if let Some(mut entry) = index.get(viejo).cloned() {
+        index.remove(viejo);
+        entry.path = nuevo.to_string();
+        index.add(entry);
+    }
+}
 
-            let mut len_archivo = base_file_data.len();
-            if len_archivo == 0 {
-                if branch_file_data.len() > origin_file_data.len() {
-                    len_archivo = branch_file_data.len();
-                } else {
-                    len_archivo = origin_file_data.len();
-                }
+// el modo de origin, salvo que solo branch lo haya cambiado
+fn modo_mergeado(base: Option<&IndexEntry>, origin: &IndexEntry, branch: &IndexEntry) -> u32 {
+    match base {
+        Some(base) if base.mode == origin.mode => branch.mode,
+        _ => origin.mode,
+    }
+}
+
+// solo cambio la branch: el work tree y el index toman su version o se borra el archivo
+fn tomar_version_de_branch(
+    path: &str,
+    branch: Option<&IndexEntry>,
+    repo: &Repository,
+) -> Result<(), GitrError> {
+    if repo.is_bare() {
+        return Ok(());
+    }
+    let mut index = file_manager::read_index(repo).unwrap_or_default();
+    let work_path = repo.work_path(path);
+    match branch {
+        Some(entry) => {
+            restaurar_archivo(entry, repo)?;
+            index.add(IndexEntry::from_file(
+                &work_path,
+                path.to_string(),
+                entry.hash.clone(),
+            )?);
+        }
+        None => {
+            let _ = fs::remove_file(&work_path);
+            borrar_directorios_vacios(&work_path, repo);
+            index.remove(path);
+        }
+    }
+    file_manager::write_index(&index, repo)
+}
+
+// mergea linea por linea un archivo que cambiaron los dos lados y lo escribe en el
+// work tree, con los conflictos marcados. Devuelve si hubo conflicto
+fn merge_de_contenido(
+    path: &str,
+    base: Option<&IndexEntry>,
+    origin: &IndexEntry,
+    branch: &IndexEntry,
+    estilo: EstiloConflicto,
+    resolucion: ResolucionConflicto,
+    repo: &Repository,
+) -> Result<bool, GitrError> {
+    let (contenido, hubo_conflict) =
+        contenido_mergeado(base, origin, branch, estilo, resolucion, repo)?;
+    if !repo.is_bare() {
+        // el path puede ser nuevo si el archivo se renombro
+        let work_path = repo.work_path(path);
+        if let Some(directorio) = Path::new(&work_path).parent() {
+            if fs::create_dir_all(directorio).is_err() {
+                return Err(GitrError::FileCreationError(
+                    directorio.display().to_string(),
+                ));
             }
+        }
+        let modo = modo_mergeado(base, origin, branch);
+        file_manager::write_blob_content(work_path, contenido.as_bytes(), modo)?;
+    }
+    Ok(hubo_conflict)
+}
 
-            let diff_base_origin = Diff::new(base_file_data.clone(), origin_file_data.clone());
-            let diff_base_branch = Diff::new(base_file_data.clone(), branch_file_data.clone());
-            let union_diffs;
+// une los cambios de los dos lados sobre el contenido de origin.
+// Devuelve el archivo mergeado y si hubo conflicto
+fn contenido_mergeado(
+    base: Option<&IndexEntry>,
+    origin: &IndexEntry,
+    branch: &IndexEntry,
+    estilo: EstiloConflicto,
+    resolucion: ResolucionConflicto,
+    repo: &Repository,
+) -> Result<(String, bool), GitrError> {
+    let origin_file_data = read_blob_as_text(origin.hash.clone(), repo)?;
+    let branch_file_data = read_blob_as_text(branch.hash.clone(), repo)?;
+    let base_file_data = match base {
+        Some(base) => read_blob_as_text(base.hash.clone(), repo)?,
+        None => "".to_string(),
+    };
 
-            (union_diffs, hubo_conflict) =
-                comparar_diffs(diff_base_origin, diff_base_branch, len_archivo - 1)?; //une los diffs o da el conflict
+    let mut len_archivo = base_file_data.len();
+    if len_archivo == 0 {
+        len_archivo = branch_file_data.len().max(origin_file_data.len());
+    }
 
-            if hubo_conflict {
-                if let Some(nombre_archivo) = path.split('/').last() {
-                    archivos_conflict.push(nombre_archivo.to_string().clone());
-                }
-            }
+    let diff_base_origin = Diff::new(base_file_data.clone(), origin_file_data.clone());
+    let diff_base_branch = Diff::new(base_file_data.clone(), branch_file_data);
+    let (union_diffs, hubo_conflict) = comparar_diffs_con_opciones(
+        diff_base_origin,
+        diff_base_branch,
+        len_archivo.saturating_sub(1),
+        OpcionesConflicto { estilo, resolucion },
+    )?; //une los diffs o da el conflict
+
+    let archivo_reconstruido = if base_file_data.is_empty() {
+        _aplicar_diffs("".to_string(), union_diffs)?
+    } else {
+        _aplicar_diffs(origin_file_data, union_diffs)?
+    };
+    Ok((archivo_reconstruido.concat(), hubo_conflict))
+}
 
-            if base_file_data.is_empty() || base_file_data.is_empty() {
-                let archivo_reconstruido = _aplicar_diffs("".to_string(), union_diffs)?;
-                file_manager::write_file(
-                    path.to_string() + "_mergeado",
-                    archivo_reconstruido.concat().to_string(),
-                )?;
-            } else if cliente.contains('/') {
-                _aplicar_diffs(origin_file_data.clone(), union_diffs)?;
-            } else {
-                aplicar_diffs(path.clone(), union_diffs)?;
-            }
-        } else {
-            continue;
+/// Deja el path sin mergear en el index: el ancestro comun en el stage 1, la version
+/// de HEAD (ours) en el 2 y la de la branch (theirs) en el 3. Los lados que no
+/// tienen el archivo (agregado o borrado) no tienen stage
+pub fn registrar_conflicto(
+    path: &str,
+    base: Option<&IndexEntry>,
+    ours: Option<&IndexEntry>,
+    theirs: Option<&IndexEntry>,
+    repo: &Repository,
+) -> Result<(), GitrError> {
+    let mut index = file_manager::read_index(repo).unwrap_or_default();
+    index.remove(path);
+    for (stage, entry) in [(1, base), (2, ours), (3, theirs)] {
+        if let Some(entry) = entry {
+            let mut entry = IndexEntry::new(path.to_string(), entry.hash.clone(), entry.mode);
+            entry.set_stage(stage);
+            index.add(entry);
         }
     }
+    file_manager::write_index(&index, repo)
+}
 
-    Ok((hubo_conflict, archivos_conflict))
+/// Paths del index que quedaron sin mergear, relativos al work tree
+pub fn get_unmerged_paths(repo: &Repository) -> Result<Vec<String>, GitrError> {
+    match file_manager::read_index(repo) {
+        Ok(index) => Ok(index.unmerged_paths()),
+        Err(_) => Ok(vec![]),
+    }
 }
 
 pub fn create_merge_commit(
     branch_name: String,
     branch_commit: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let index_path = file_manager::get_current_repo(cliente.clone())?.to_string() + "/gitr/index";
-    if !Path::new(&index_path).exists() {
-        return commands_fn::status(vec![], cliente.clone());
+    if !Path::new(&repo.git_path("index")).exists() {
+        println!("{}", get_status(repo)?);
+        return Ok(());
     }
 
     let message = format!("Merge branch '{}'", branch_name);
-    get_tree_entries(message.to_string(), branch_commit, cliente.clone())?;
-    print_commit_confirmation(message, cliente.clone())?;
+    get_tree_entries(message.to_string(), branch_commit, repo)?;
+    print_commit_confirmation(message, repo)?;
     Ok(())
 }
 
+/// merge --abort: deja HEAD, index y work tree como estaban en ORIG_HEAD y termina el merge
+pub fn merge_abort(repo: &Repository) -> Result<(), GitrError> {
+    if file_manager::read_merge_head(repo)?.is_none() {
+        return Err(GitrError::NoMergeInProgress);
+    }
+    let orig_head = file_manager::read_file(repo.git_path("ORIG_HEAD"))?;
+    reset_(orig_head.trim_end(), ModoReset::Hard, repo)?;
+    file_manager::clear_merge_state(repo)
+}
+
 /***************************
  ***************************
  *   STATUS FUNCTIONS
  **************************
  **************************/
 
-pub fn get_status(cliente: String) -> Result<String, GitrError> {
+pub fn get_status(repo: &Repository) -> Result<String, GitrError> {
     let mut res = String::new();
-    res.push_str(&(status_print_current_branch(cliente.clone())? + "\n"));
-    let (not_staged, untracked_files, hayindex) = get_untracked_notstaged_files(cliente.clone())?;
-    let (new_files, modified_files) = get_tobe_commited_files(&not_staged, cliente.clone())?;
+    res.push_str(&(status_print_current_branch(repo)? + "\n"));
+    let (mut not_staged, untracked_files, hayindex) = get_untracked_notstaged_files(repo)?;
+    let (mut new_files, mut modified_files) = get_tobe_commited_files(&not_staged, repo)?;
+    // los paths sin mergear solo se listan en su seccion
+    let unmerged = get_unmerged_paths(repo)?;
+    for files in [&mut not_staged, &mut new_files, &mut modified_files] {
+        files.retain(|file| !unmerged.contains(&index::relative_path(file, repo.work_tree())));
+    }
     res.push_str(&get_status_files_to_be_comited(
         &new_files,
         &modified_files,
     )?);
-    res.push_str(&get_status_files_not_staged(&not_staged, cliente.clone())?);
+    res.push_str(&get_status_files_unmerged(repo)?);
+    res.push_str(&get_status_files_not_staged(&not_staged, repo)?);
     res.push_str(&get_status_files_untracked(&untracked_files, hayindex));
     if new_files.is_empty()
         && modified_files.is_empty()
         && not_staged.is_empty()
         && untracked_files.is_empty()
+        && unmerged.is_empty()
     {
         res.push_str("nothing to commit, working tree clean\n");
     }
     Ok(res)
 }
-pub fn get_working_dir_hashmap(cliente: String) -> Result<HashMap<String, String>, GitrError> {
+/// returns path -> hash of every file in the working dir.
+/// Files whose stat data matches their index entry aren't read again, and the
+/// entries of files that were rehashed without changes get their stat data refreshed.
+pub fn get_working_dir_hashmap(repo: &Repository) -> Result<HashMap<String, String>, GitrError> {
     let mut working_dir_hashmap = HashMap::new();
-    let repo = file_manager::get_current_repo(cliente.clone())?;
+    let (mut index, index_mtime) = match read_index(repo) {
+        Ok(index) => (Some(index), index::file_mtime(&repo.git_path("index"))),
+        Err(_) => (None, (0, 0)),
+    };
+    let mut index_refreshed = false;
 
-    let path = Path::new(&repo);
+    let path = Path::new(repo.work_tree());
     let files = visit_dirs(path);
     for file_path in files {
-        let file_data = file_manager::read_file(file_path.clone())?;
+        let relative_path = index::relative_path(&file_path, repo.work_tree());
+        if let Some(entry) = index.as_ref().and_then(|index| index.get(&relative_path)) {
+            if entry.stat_matches(&file_path, index_mtime) {
+                working_dir_hashmap.insert(file_path, entry.hash.clone());
+                continue;
+            }
+        }
+        let file_data = file_manager::read_blob_content(file_path.clone())?;
 
-        let blob = Blob::new(file_data.clone())?;
+        let blob = Blob::new(file_data)?;
         let hash = blob.get_hash();
+        if let Some(entry) = index.as_mut().and_then(|index| index.get_mut(&relative_path)) {
+            if entry.hash == hash && entry.update_stat(&file_path).is_ok() {
+                index_refreshed = true;
+            }
+        }
         working_dir_hashmap.insert(file_path, hash);
     }
+    if let (true, Some(index)) = (index_refreshed, index) {
+        file_manager::write_index(&index, repo)?;
+    }
     Ok(working_dir_hashmap)
 }
 
@@ -1038,13 +1694,46 @@ pub fn get_status_files_to_be_comited(
     Ok(res)
 }
 
+/// Seccion "Unmerged paths" del status, con el tipo de conflicto segun los stages del index
+pub fn get_status_files_unmerged(repo: &Repository) -> Result<String, GitrError> {
+    let mut res = String::new();
+    let index = match file_manager::read_index(repo) {
+        Ok(index) => index,
+        Err(_) => return Ok(res),
+    };
+    let unmerged = index.unmerged_paths();
+    if unmerged.is_empty() {
+        return Ok(res);
+    }
+    res.push_str("Unmerged paths:\n");
+    res.push_str("  (use \"add <file>...\" to mark resolution)\n");
+    for path in unmerged {
+        let stages: Vec<u8> = index
+            .entries()
+            .iter()
+            .filter(|entry| entry.path == path)
+            .map(|entry| entry.stage())
+            .collect();
+        let tipo = match (stages.contains(&1), stages.contains(&2), stages.contains(&3)) {
+            (true, true, false) => "deleted by them:",
+            (true, false, true) => "deleted by us:  ",
+            (false, true, true) => "both added:     ",
+            (true, false, false) => "both deleted:   ",
+            _ => "both modified:  ",
+        };
+        res.push_str(&format!("\t\x1b[31m{} {}\x1b[0m\n", tipo, path));
+    }
+    res.push('\n');
+    Ok(res)
+}
+
 pub fn get_status_files_not_staged(
     not_staged: &Vec<String>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<String, GitrError> {
     let mut res = String::new();
-    let (index, hayindex) = get_index_hashmap(cliente.clone())?;
-    let working_dir_hashmap = get_working_dir_hashmap(cliente.clone())?;
+    let (index, hayindex) = get_index_hashmap(repo)?;
+    let working_dir_hashmap = get_working_dir_hashmap(repo)?;
     if !not_staged.is_empty() {
         let header1 = "Changes not staged for commit:\n".to_string();
         let header2 = "  (use \"add <file>...\" to update what will be committed)\n".to_string();
@@ -1100,49 +1789,54 @@ pub fn get_status_files_untracked(untracked_files: &Vec<String>, hayindex: bool)
     res
 }
 
-pub fn status_print_current_branch(cliente: String) -> Result<String, GitrError> {
+pub fn status_print_current_branch(repo: &Repository) -> Result<String, GitrError> {
     let mut res = String::new();
-    let head = file_manager::get_head(cliente.clone())?;
-    let current_branch = head.split('/').collect::<Vec<&str>>()[2];
-    res.push_str(&format!("On branch {}\n\n", current_branch));
-    if commit_existing(cliente).is_err() {
+    match file_manager::get_current_branch(repo)? {
+        Some(current_branch) => res.push_str(&format!("On branch {}\n\n", current_branch)),
+        None => {
+            let commit = get_current_commit(repo)?;
+            res.push_str(&format!("HEAD detached at {}\n\n", &commit[..7]));
+        }
+    }
+    if commit_existing(repo).is_err() {
         res.push_str("No commits yet\n");
     }
+    if file_manager::read_merge_head(repo)?.is_some() {
+        if get_unmerged_paths(repo)?.is_empty() {
+            res.push_str("All conflicts fixed but you are still merging.\n");
+            res.push_str("  (use \"commit\" to conclude merge)\n\n");
+        } else {
+            res.push_str("You have unmerged paths.\n");
+            res.push_str("  (fix conflicts and run \"commit\")\n");
+            res.push_str("  (use \"merge --abort\" to abort the merge)\n\n");
+        }
+    }
     Ok(res)
 }
 
-pub fn get_index_hashmap(cliente: String) -> Result<(HashMap<String, String>, bool), GitrError> {
+pub fn get_index_hashmap(repo: &Repository) -> Result<(HashMap<String, String>, bool), GitrError> {
     let mut index_hashmap = HashMap::new();
-    let mut hayindex = true;
-    let index_data = match file_manager::read_index(cliente.clone()) {
-        Ok(data) => data,
-        Err(_) => {
-            hayindex = false;
-            String::new()
-        }
+    let index = match file_manager::read_index(repo) {
+        Ok(index) => index,
+        Err(_) => return Ok((index_hashmap, false)),
     };
-    if hayindex {
-        for index_entry in index_data.split('\n') {
-            let attributes = index_entry.split(' ').collect::<Vec<&str>>();
-            let path = attributes[3].to_string();
-            let hash = attributes[1].to_string();
-            index_hashmap.insert(path, hash);
+    for entry in index.entries() {
+        // de un path sin mergear se toma la version de HEAD (stage 2)
+        if entry.stage() == 1 || entry.stage() == 3 {
+            continue;
         }
+        index_hashmap.insert(repo.work_path(&entry.path), entry.hash.clone());
     }
-    Ok((index_hashmap, hayindex))
+    Ok((index_hashmap, true))
 }
 
 pub fn get_subtrees_data(
     hash_of_tree_to_read: String,
     file_path: String,
     tree_hashmap: &mut HashMap<String, String>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let tree_data = file_manager::read_object(
-        &hash_of_tree_to_read,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+    let tree_data = file_manager::read_object(&hash_of_tree_to_read, repo)?;
 
     let tree_entries = match tree_data.split_once('\0') {
         Some((_tree_type, tree_entries)) => tree_entries,
@@ -1154,7 +1848,7 @@ pub fn get_subtrees_data(
             let relative_file_path = attributes.split('\0').collect::<Vec<&str>>()[0].to_string();
             let file_path = format!("{}/{}", file_path, relative_file_path);
             let file_hash = attributes.split('\0').collect::<Vec<&str>>()[1].to_string();
-            get_subtrees_data(file_hash, file_path, tree_hashmap, cliente.clone())?;
+            get_subtrees_data(file_hash, file_path, tree_hashmap, repo)?;
         }
         if entry.split(' ').collect::<Vec<&str>>()[0] == "40000" {
             continue;
@@ -1172,13 +1866,12 @@ pub fn get_subtrees_data(
 
 pub fn get_commit_hashmap(
     commit: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<HashMap<String, String>, GitrError> {
     let mut tree_hashmap = HashMap::new();
     if !commit.is_empty() {
-        let repo = file_manager::get_current_repo(cliente.clone())?;
-        let tree = file_manager::get_main_tree(commit, cliente.clone())?;
-        let tree_data = file_manager::read_object(&tree, repo.clone(), true)?;
+        let tree = file_manager::get_main_tree(commit, repo)?;
+        let tree_data = file_manager::read_object(&tree, repo)?;
         let tree_entries = match tree_data.split_once('\0') {
             Some((_tree_type, tree_entries)) => tree_entries,
             None => "",
@@ -1187,9 +1880,9 @@ pub fn get_commit_hashmap(
             if entry.split(' ').collect::<Vec<&str>>()[0] == "40000" {
                 let attributes = entry.split(' ').collect::<Vec<&str>>()[1];
                 let _file_path = attributes.split('\0').collect::<Vec<&str>>()[0].to_string();
-                let file_path = format!("{}/{}", repo, _file_path);
+                let file_path = repo.work_path(&_file_path);
                 let file_hash = attributes.split('\0').collect::<Vec<&str>>()[1].to_string();
-                get_subtrees_data(file_hash, file_path, &mut tree_hashmap, cliente.clone())?;
+                get_subtrees_data(file_hash, file_path, &mut tree_hashmap, repo)?;
             }
 
             if entry.split(' ').collect::<Vec<&str>>()[0] == "40000" {
@@ -1198,7 +1891,7 @@ pub fn get_commit_hashmap(
 
             let attributes = entry.split(' ').collect::<Vec<&str>>()[1];
             let _file_path = attributes.split('\0').collect::<Vec<&str>>()[0].to_string();
-            let file_path = format!("{}/{}", repo, _file_path);
+            let file_path = repo.work_path(&_file_path);
             let file_hash = attributes.split('\0').collect::<Vec<&str>>()[1].to_string();
 
             tree_hashmap.insert(file_path, file_hash);
@@ -1209,11 +1902,11 @@ pub fn get_commit_hashmap(
 }
 
 pub fn get_untracked_notstaged_files(
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(Vec<String>, Vec<String>, bool), GitrError> {
-    let working_dir_hashmap = get_working_dir_hashmap(cliente.clone())?;
-    let (index_hashmap, hayindex) = get_index_hashmap(cliente.clone())?;
-    let current_commit_hashmap = get_current_commit_hashmap(cliente.clone())?;
+    let working_dir_hashmap = get_working_dir_hashmap(repo)?;
+    let (index_hashmap, hayindex) = get_index_hashmap(repo)?;
+    let current_commit_hashmap = get_current_commit_hashmap(repo)?;
     let mut not_staged = Vec::new();
     let mut untracked_files = Vec::new();
     for (path, _) in index_hashmap.clone().into_iter() {
@@ -1245,10 +1938,10 @@ pub fn get_untracked_notstaged_files(
     Ok((not_staged, untracked_files, hayindex))
 }
 
-pub fn get_current_commit_hashmap(cliente: String) -> Result<HashMap<String, String>, GitrError> {
+pub fn get_current_commit_hashmap(repo: &Repository) -> Result<HashMap<String, String>, GitrError> {
     let mut tree_hashmap = HashMap::new();
     let mut haycommitshechos = true;
-    let current_commit = match file_manager::get_current_commit(cliente.clone()) {
+    let current_commit = match file_manager::get_current_commit(repo) {
         Ok(commit) => commit,
         Err(_) => {
             haycommitshechos = false;
@@ -1257,9 +1950,8 @@ pub fn get_current_commit_hashmap(cliente: String) -> Result<HashMap<String, Str
     };
 
     if haycommitshechos {
-        let repo = file_manager::get_current_repo(cliente.clone())?;
-        let tree = file_manager::get_main_tree(current_commit, cliente.clone())?;
-        let tree_data = file_manager::read_object(&tree, repo.clone(), true)?;
+        let tree = file_manager::get_main_tree(current_commit, repo)?;
+        let tree_data = file_manager::read_object(&tree, repo)?;
         let tree_entries = match tree_data.split_once('\0') {
             Some((_tree_type, tree_entries)) => tree_entries,
             None => "",
@@ -1267,7 +1959,7 @@ pub fn get_current_commit_hashmap(cliente: String) -> Result<HashMap<String, Str
         for entry in tree_entries.split('\n') {
             let attributes = entry.split(' ').collect::<Vec<&str>>()[1];
             let _file_path = attributes.split('\0').collect::<Vec<&str>>()[0].to_string();
-            let file_path = format!("{}/{}", repo, _file_path);
+            let file_path = repo.work_path(&_file_path);
             let file_hash = attributes.split('\0').collect::<Vec<&str>>()[1].to_string();
             tree_hashmap.insert(file_path, file_hash);
         }
@@ -1276,10 +1968,10 @@ pub fn get_current_commit_hashmap(cliente: String) -> Result<HashMap<String, Str
 }
 pub fn get_tobe_commited_files(
     not_staged: &[String],
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(Vec<String>, Vec<String>), GitrError> {
-    let (index_hashmap, _) = get_index_hashmap(cliente.clone())?;
-    let current_commit_hashmap = get_current_commit_hashmap(cliente.clone())?;
+    let (index_hashmap, _) = get_index_hashmap(repo)?;
+    let current_commit_hashmap = get_current_commit_hashmap(repo)?;
     let mut new_files_to_be_commited = Vec::new();
     let mut modified_files_to_be_commited = Vec::new();
     for (path, hash) in index_hashmap.clone().into_iter() {
@@ -1300,8 +1992,12 @@ pub fn get_tobe_commited_files(
  **************************
  **************************/
 
-pub fn save_and_add_blob_to_index(file_path: String, cliente: String) -> Result<(), GitrError> {
-    match path_is_ignored(vec![file_path.clone()], cliente.clone()) {
+pub fn save_and_add_blob_to_index(
+    file_path: String,
+    index: &mut Index,
+    repo: &Repository,
+) -> Result<(), GitrError> {
+    match path_is_ignored(vec![file_path.clone()], repo) {
         Ok(r) => {
             if r {
                 return Ok(());
@@ -1309,58 +2005,53 @@ pub fn save_and_add_blob_to_index(file_path: String, cliente: String) -> Result<
         }
         Err(e) => return Err(e),
     }
-    let raw_data = file_manager::read_file(file_path.clone())?;
+    let raw_data = file_manager::read_blob_content(file_path.clone())?;
     let blob = Blob::new(raw_data)?;
-    blob.save(cliente.clone())?;
+    blob.save(repo)?;
     let hash = blob.get_hash();
-    file_manager::add_to_index(&file_path, &hash, cliente.clone())?;
+    index.add_file(&file_path, repo.work_tree(), hash);
     Ok(())
 }
 
-pub fn update_index_before_add(cliente: String) -> Result<(), GitrError> {
-    let repo = file_manager::get_current_repo(cliente.clone())?;
-    let index_path = &(repo.clone() + "/gitr/index");
+/// drops the deleted files from the index and restages the tracked files that changed
+/// (the ones whose stat data still matches are left as they are)
+pub fn update_index_before_add(repo: &Repository) -> Result<(), GitrError> {
+    let index_path = &repo.git_path("index");
     if Path::new(index_path).is_file() {
-        let index_data = file_manager::read_index(cliente.clone())?;
-        let mut index_vector: Vec<&str> = Vec::new();
-        if !index_data.is_empty() {
-            index_vector = index_data.split('\n').collect::<Vec<&str>>();
-        }
-        let mut i: i32 = 0;
-        while i != index_vector.len() as i32 {
-            let entry = index_vector[i as usize];
-            let path_to_check = entry.split(' ').collect::<Vec<&str>>()[3];
-            if !Path::new(path_to_check).exists() {
-                index_vector.remove(i as usize);
-                i -= 1;
-            }
-            i += 1;
-        }
-        file_manager::remove_file(index_path.to_string())?;
-        for entry in index_vector {
-            let path = entry.split(' ').collect::<Vec<&str>>()[3];
-            save_and_add_blob_to_index(path.to_string(), cliente.clone())?;
+        let mut index = file_manager::read_index(repo)?;
+        let index_mtime = index::file_mtime(index_path);
+        index.retain(|entry| Path::new(&repo.work_path(&entry.path)).exists());
+        let changed: Vec<String> = index
+            .entries()
+            .iter()
+            .map(|entry| (repo.work_path(&entry.path), entry))
+            .filter(|(path, entry)| !entry.stat_matches(path, index_mtime))
+            .map(|(path, _)| path)
+            .collect();
+        for path in changed {
+            save_and_add_blob_to_index(path, &mut index, repo)?;
         }
+        file_manager::write_index(&index, repo)?;
     }
     Ok(())
 }
 
-pub fn add_files_command(file_path: String, cliente: String) -> Result<(), GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
+pub fn add_files_command(file_path: String, repo: &Repository) -> Result<(), GitrError> {
+    let mut index = file_manager::read_index(repo).unwrap_or_default();
     if file_path == "." {
-        let files = visit_dirs(std::path::Path::new(&repo));
+        let files = visit_dirs(std::path::Path::new(repo.work_tree()));
         for file in files {
             if file.contains("gitr") || file.contains("gitrignore") {
                 continue;
             }
-            save_and_add_blob_to_index(file.clone(), cliente.clone())?;
+            save_and_add_blob_to_index(file.clone(), &mut index, repo)?;
 
         }
     } else {
-        let full_file_path = repo + "/" + &file_path;
-        save_and_add_blob_to_index(full_file_path, cliente)?;
+        let full_file_path = repo.work_path(&file_path);
+        save_and_add_blob_to_index(full_file_path, &mut index, repo)?;
     }
-    Ok(())
+    file_manager::write_index(&index, repo)
 }
 
 /***************************
@@ -1369,45 +2060,240 @@ pub fn add_files_command(file_path: String, cliente: String) -> Result<(), GitrE
  **************************
  **************************/
 
-pub fn rm_from_index(file_to_delete: &str, cliente: String) -> Result<bool, GitrError> {
-    let mut removed: bool = false;
-    let mut index = file_manager::read_index(cliente.clone())?;
-    index += "\n";
-    let current_repo = file_manager::get_current_repo(cliente)?;
-    let file_to_rm_path = format!("{}/{}", current_repo, file_to_delete);
-    for line in index.lines() {
-        let attributes = line.split(' ').collect::<Vec<&str>>();
-        if attributes[3] == file_to_rm_path {
-            let complete_line = format!("{}\n", line);
-            index = index.replace(&complete_line, "");
-            let res = index.trim_end().to_string();
-            removed = true;
-            let compressed_index = flate2compress(res)?;
-            let _ = file_manager::write_compressed_data(
-                &(current_repo + "/gitr/index"),
-                compressed_index.as_slice(),
-            );
-            break;
-        }
+pub fn rm_from_index(file_to_delete: &str, repo: &Repository) -> Result<bool, GitrError> {
+    let mut index = file_manager::read_index(repo)?;
+    let removed = index.remove(file_to_delete);
+    if removed {
+        file_manager::write_index(&index, repo)?;
     }
     Ok(removed)
 }
 
+/***************************
+ ***************************
+ *   RESET FUNCTIONS
+ **************************
+ **************************/
+
+/// Que rehace reset ademas de mover la branch actual
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModoReset {
+    /// solo mueve la branch
+    Soft,
+    /// rehace el index con el tree del commit (por defecto)
+    Mixed,
+    /// rehace el index y el work tree
+    Hard,
+}
+
+/// Mueve la branch actual al commit de la revision y, segun el modo,
+/// rehace el index (mixed) o el index y el work tree (hard)
+pub fn reset_(revision: &str, modo: ModoReset, repo: &Repository) -> Result<(), GitrError> {
+    let commit = rev_parse_commit(revision, repo)?;
+    let head = get_head(repo)?;
+    if head == "None" {
+        return Err(GitrError::NoHead);
+    }
+    let actual = get_current_commit(repo)?;
+    match modo {
+        ModoReset::Soft => (),
+        ModoReset::Mixed => {
+            let tree = file_manager::get_main_tree(commit.clone(), repo)?;
+            file_manager::write_index(&index_de_tree(&tree, repo)?, repo)?;
+        }
+        ModoReset::Hard => reset_hard(&commit, repo)?,
+    }
+    // la branch se mueve al final: si falla antes queda donde estaba
+    file_manager::write_orig_head(&actual, repo)?;
+    file_manager::write_file(repo.git_path(&head), commit)
+}
+
+// rehace el index con el tree del commit y reescribe solo los archivos que no coinciden con
+// el, pisando los cambios locales. Los archivos trackeados que el commit no tiene se borran
+// y los que no estan trackeados quedan como estan
+fn reset_hard(commit: &str, repo: &Repository) -> Result<(), GitrError> {
+    let tree = file_manager::get_main_tree(commit.to_string(), repo)?;
+    let objetivo = index_de_tree(&tree, repo)?;
+    // antes de leer el index: puede refrescar los datos de stat
+    let work = get_working_dir_hashmap(repo)?;
+    let mut index = match read_index(repo) {
+        Ok(index) => index,
+        Err(_) => Index::new(),
+    };
+
+    let mut paths = index
+        .entries()
+        .iter()
+        .chain(objetivo.entries())
+        .map(|entry| entry.path.clone())
+        .collect::<Vec<String>>();
+    paths.sort();
+    paths.dedup();
+
+    for path in &paths {
+        let archivo = repo.work_path(path);
+        match objetivo.get(path) {
+            Some(entry) => {
+                let en_index = index.get(path).filter(|actual| actual.stage() == 0);
+                let igual = en_index
+                    .is_some_and(|actual| actual.hash == entry.hash && actual.mode == entry.mode)
+                    && work.get(&archivo) == Some(&entry.hash);
+                if igual {
+                    continue;
+                }
+                index.remove(path);
+                if Path::new(&archivo).is_dir() {
+                    let _ = fs::remove_dir_all(&archivo);
+                }
+                restaurar_archivo(entry, repo)?;
+                let stat = IndexEntry::from_file(&archivo, path.clone(), entry.hash.clone());
+                index.add(stat.unwrap_or(entry.clone()));
+            }
+            None => {
+                index.remove(path);
+                let _ = fs::remove_file(&archivo);
+                borrar_directorios_vacios(&archivo, repo);
+            }
+        }
+    }
+    file_manager::write_index(&index, repo)
+}
+
+/// reset [<revision>] -- <path>: vuelve las entradas del index de cada path (o de los
+/// archivos dentro si es un directorio) a las del commit, sin tocar la branch ni el work tree.
+/// Los paths que el commit no tiene se sacan del index.
+pub fn reset_paths(revision: &str, paths: &[String], repo: &Repository) -> Result<(), GitrError> {
+    let commit = rev_parse_commit(revision, repo)?;
+    let origen = index_de_tree(&file_manager::get_main_tree(commit, repo)?, repo)?;
+    let mut index = match file_manager::read_index(repo) {
+        Ok(index) => index,
+        Err(_) => Index::new(),
+    };
+    for path in paths {
+        index.retain(|entry| !path_incluido(path, &entry.path));
+        for entry in origen.entries() {
+            if path_incluido(path, &entry.path) {
+                index.add(entry.clone());
+            }
+        }
+    }
+    file_manager::write_index(&index, repo)
+}
+
+// si el path (relativo al work tree) del index esta en el pathspec: el mismo archivo,
+// un archivo dentro del directorio o "." para todos
+fn path_incluido(pathspec: &str, path: &str) -> bool {
+    let pathspec = pathspec.trim_start_matches("./").trim_end_matches('/');
+    pathspec == "." || path == pathspec || path.starts_with(&format!("{}/", pathspec))
+}
+
+/// Index con todos los blobs del tree. Las entradas no tienen datos de stat,
+/// asi que status vuelve a hashear esos archivos hasta el proximo add.
+pub fn index_de_tree(tree: &String, repo: &Repository) -> Result<Index, GitrError> {
+    let mut index = Index::new();
+    agregar_tree_al_index(tree, "", &mut index, repo)?;
+    Ok(index)
+}
+
+fn agregar_tree_al_index(
+    tree: &String,
+    directorio: &str,
+    index: &mut Index,
+    repo: &Repository,
+) -> Result<(), GitrError> {
+    let tree_data = file_manager::read_object(tree, repo)?;
+    let entradas = match tree_data.split_once('\0') {
+        Some((_, entradas)) => entradas,
+        None => return Err(GitrError::InvalidTreeError),
+    };
+    for entrada in entradas.split('\n').filter(|e| !e.is_empty()) {
+        let (modo, nombre_hash) = entrada.split_once(' ').ok_or(GitrError::InvalidTreeError)?;
+        let (nombre, hash) = nombre_hash.split_once('\0').ok_or(GitrError::InvalidTreeError)?;
+        let path = match directorio {
+            "" => nombre.to_string(),
+            _ => format!("{}/{}", directorio, nombre),
+        };
+        if modo == "40000" {
+            agregar_tree_al_index(&hash.to_string(), &path, index, repo)?;
+            continue;
+        }
+        let modo = u32::from_str_radix(modo, 8).map_err(|_| GitrError::InvalidTreeError)?;
+        index.add(IndexEntry::new(path, hash.to_string(), modo));
+    }
+    Ok(())
+}
+
+/***************************
+ ***************************
+ *   RESTORE FUNCTIONS
+ **************************
+ **************************/
+
+/// Reescribe en el work tree los archivos de cada path con su version del index o,
+/// con source, con la del commit/tree de esa revision. No mueve HEAD ni toca el index
+/// ni el resto del work tree. Con source, los archivos trackeados del pathspec que
+/// la revision no tiene se borran.
+/// On Error returns a PathspecError si un path no coincide con ningun archivo
+pub fn restore_(paths: &[String], source: Option<&str>, repo: &Repository) -> Result<(), GitrError> {
+    let index = match file_manager::read_index(repo) {
+        Ok(index) => index,
+        Err(_) => Index::new(),
+    };
+    let origen = match source {
+        Some(revision) => index_de_tree(&rev_parse_tree(revision, repo)?, repo)?,
+        None => index.clone(),
+    };
+    for path in paths {
+        let entradas = origen
+            .entries()
+            .iter()
+            .filter(|entry| path_incluido(path, &entry.path))
+            .collect::<Vec<&IndexEntry>>();
+        let sobrantes = index
+            .entries()
+            .iter()
+            .filter(|entry| path_incluido(path, &entry.path) && origen.get(&entry.path).is_none())
+            .collect::<Vec<&IndexEntry>>();
+        if entradas.is_empty() && sobrantes.is_empty() {
+            return Err(GitrError::PathspecError(path.clone()));
+        }
+        for entry in entradas {
+            restaurar_archivo(entry, repo)?;
+        }
+        for entry in sobrantes {
+            let _ = fs::remove_file(repo.work_path(&entry.path));
+        }
+    }
+    Ok(())
+}
+
+// escribe el blob de la entrada en su path del work tree, creando los directorios
+fn restaurar_archivo(entry: &IndexEntry, repo: &Repository) -> Result<(), GitrError> {
+    let path = repo.work_path(&entry.path);
+    if let Some(directorio) = Path::new(&path).parent() {
+        if fs::create_dir_all(directorio).is_err() {
+            return Err(GitrError::FileCreationError(directorio.display().to_string()));
+        }
+    }
+    let data = file_manager::read_file_data_from_blob_hash(entry.hash.clone(), repo)?;
+    file_manager::write_blob_content(path, &data, entry.mode)
+}
+
 /***************************
  ***************************
  *    TAG FUNCTIONS
  **************************
  **************************/
 
-pub fn create_lightweight_tag(tag_name: String, cliente: String) -> Result<(), GitrError> {
-    let current_commit = match get_current_commit(cliente.clone()) {
+pub fn create_lightweight_tag(tag_name: String, repo: &Repository) -> Result<(), GitrError> {
+    let current_commit = match get_current_commit(repo) {
         Ok(commit) => commit,
         Err(_) => {
             println!("fatal: Failed to resolve 'HEAD' as a valid ref.");
             return Ok(());
         }
     };
-    let tag_path = get_current_repo(cliente.clone())? + "/gitr/refs/tags/" + &tag_name;
+    let tag_path = repo.git_path(&format!("refs/tags/{}", tag_name));
     if Path::new(&tag_path).exists() {
         return Err(GitrError::TagAlreadyExistsError(tag_name.clone()));
     }
@@ -1418,27 +2304,27 @@ pub fn create_lightweight_tag(tag_name: String, cliente: String) -> Result<(), G
 pub fn create_annotated_tag(
     tag_name: String,
     tag_message: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let current_commit = match get_current_commit(cliente.clone()) {
+    let current_commit = match get_current_commit(repo) {
         Ok(commit) => commit,
         Err(_) => {
             println!("fatal: Failed to resolve 'HEAD' as a valid ref.");
             return Ok(());
         }
     };
-    let tag_path = get_current_repo(cliente.clone())? + "/gitr/refs/tags/" + &tag_name;
+    let tag_path = repo.git_path(&format!("refs/tags/{}", tag_name));
     if Path::new(&tag_path).exists() {
         return Err(GitrError::TagAlreadyExistsError(tag_name.clone()));
     }
-    let tag = Tag::new(tag_name, tag_message, current_commit, cliente.clone())?;
-    tag.save(cliente.clone())?;
+    let tag = Tag::new(tag_name, tag_message, current_commit, repo)?;
+    tag.save(repo)?;
     file_manager::write_file(tag_path, tag.get_hash())?;
     Ok(())
 }
 
-pub fn get_tags_str(cliente: String) -> Result<String, GitrError> {
-    let tags = file_manager::get_tags(cliente)?;
+pub fn get_tags_str(repo: &Repository) -> Result<String, GitrError> {
+    let tags = file_manager::get_tags(repo)?;
     let mut tag_str = String::new();
     for t in tags {
         tag_str.push_str(&(t + "\n"))
@@ -1454,13 +2340,12 @@ pub fn get_tags_str(cliente: String) -> Result<String, GitrError> {
 pub fn write_reference_from_cloning(
     references: Vec<(String, String)>,
     _ref_disc: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let repo = file_manager::get_current_repo(cliente.clone())?;
     for reference in &references[1..] {
-        let path_str = repo.clone() + "/gitr/" + &reference.1.clone();
+        let path_str = repo.git_path(&reference.1);
         if references[0].0 == reference.0 {
-            file_manager::update_head(&reference.1.clone(), cliente.clone())?;
+            file_manager::update_head(&reference.1.clone(), repo)?;
         }
         let into_hash = reference.0.clone();
         file_manager::write_file(path_str, into_hash)?;
@@ -1544,9 +2429,9 @@ pub fn read_socket(socket: &mut TcpStream, buffer: &mut [u8]) -> Result<(), Gitr
  **************************
  **************************/
 
-pub fn handshake(orden: String, cliente: String) -> Result<TcpStream, GitrError> {
+pub fn handshake(orden: String, repo: &Repository) -> Result<TcpStream, GitrError> {
 
-    let remote = file_manager::get_remote(cliente.clone())?;
+    let remote = file_manager::get_remote(repo)?;
     let url_n_name = remote.split('/').collect::<Vec<&str>>();
     if url_n_name.len() != 2 {
         return Err(GitrError::InvalidArgumentError(
@@ -1599,12 +2484,12 @@ pub fn protocol_reference_discovery(
 pub fn protocol_wants_n_haves(
     hash_n_references: Vec<(String, String)>,
     stream: &mut TcpStream,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<bool, GitrError> {
-    let mut refs_ids = file_manager::get_refs_ids("heads", cliente.clone())?;
-    refs_ids.append(file_manager::get_refs_ids("tags", cliente.clone())?.as_mut());
+    let mut refs_ids = file_manager::get_refs_ids("heads", repo)?;
+    refs_ids.append(file_manager::get_refs_ids("tags", repo)?.as_mut());
     let want_message =
-        ref_discovery::assemble_want_message(&hash_n_references, refs_ids, cliente.clone())?;
+        ref_discovery::assemble_want_message(&hash_n_references, refs_ids, repo)?;
     match stream.write(want_message.as_bytes()) {
         Ok(_) => (),
         Err(e) => {
@@ -1633,8 +2518,8 @@ pub fn protocol_wants_n_haves(
     Ok(true)
 }
 
-pub fn pull_packfile(stream: &mut TcpStream, cliente: String) -> Result<(), GitrError> {
-    let mut buf = match ref_discovery::read_long_stream(stream) {
+pub fn pull_packfile(stream: &mut TcpStream, repo: &Repository) -> Result<(), GitrError> {
+    let buf = match ref_discovery::read_long_stream(stream) {
         Ok(buf) => buf,
         Err(e) => {
             println!("Error: {}", e);
@@ -1645,15 +2530,7 @@ pub fn pull_packfile(stream: &mut TcpStream, cliente: String) -> Result<(), Gitr
         println!("Error: packfile vacío");
         return Ok(());
     }
-    let pack_file_struct = PackFile::new_from_server_packfile(&mut buf)?;
-    for object in pack_file_struct.objects.iter() {
-        match object {
-            GitObject::Blob(blob) => blob.save(cliente.clone())?,
-            GitObject::Commit(commit) => commit.save(cliente.clone())?,
-            GitObject::Tree(tree) => tree.save(cliente.clone())?,
-            GitObject::Tag(tag) => tag.save(cliente.clone())?,
-        }
-    }
+    repo.objects().write_pack(&buf)?;
     Ok(())
 }
 
@@ -1666,15 +2543,15 @@ pub fn pull_packfile(stream: &mut TcpStream, cliente: String) -> Result<(), Gitr
 pub fn reference_update_request(
     stream: &mut TcpStream,
     hash_n_references: Vec<(String, String)>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(bool, Vec<String>), GitrError> {
     let ids_propios = (
-        file_manager::get_refs_ids("heads", cliente.clone())?,
-        file_manager::get_refs_ids("tags", cliente.clone())?,
+        file_manager::get_refs_ids("heads", repo)?,
+        file_manager::get_refs_ids("tags", repo)?,
     ); // esta sacando de gitr/refs/heads y tags
     let refs_propios = (
-        get_branches(cliente.clone())?,
-        get_tags(cliente.clone())?,
+        get_branches(repo)?,
+        get_tags(repo)?,
     ); // tambien de gitr/refs/heads y tags
     let (ref_upd, pkt_needed, pkt_ids) = match ref_discovery::reference_update_request(
         hash_n_references.clone(),
@@ -1706,18 +2583,17 @@ pub fn push_packfile(
     stream: &mut TcpStream,
     pkt_ids: Vec<String>,
     hash_n_references: Vec<(String, String)>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let repo = file_manager::get_current_repo(cliente.clone())? + "/gitr";
     let all_pkt_commits = Commit::get_parents(
         pkt_ids.clone(),
         hash_n_references.iter().map(|t| t.0.clone()).collect(),
-        repo.clone(),
+        repo,
     )?;
-    let ids = Commit::get_objects_from_commits(all_pkt_commits, vec![], repo.clone())?;
+    let ids = Commit::get_objects_from_commits(all_pkt_commits, vec![], repo)?;
     let mut contents: Vec<Vec<u8>> = Vec::new();
     for id in ids {
-        contents.push(file_manager::get_object_bytes(id, repo.clone())?)
+        contents.push(file_manager::get_object_bytes(id, repo)?)
     }
     let cont: Vec<(String, String, Vec<u8>)> =
         crate::git_transport::pack_file::prepare_contents(contents.clone());
@@ -1735,11 +2611,17 @@ pub fn push_packfile(
 fn check_conflicts_and_get_tree(
     origin_commit: String,
     branch_commit: String,
-    base_commit: String,
-    cliente: String,
+    base_tree: String,
+    repo: &Repository,
 ) -> Result<String, GitrError> {
-    let (hubo_conflict, _) =
-        three_way_merge(base_commit, branch_commit, origin_commit, cliente.clone())?;
+    let (hubo_conflict, _) = three_way_merge(
+        base_tree,
+        branch_commit,
+        origin_commit,
+        ResolucionConflicto::Marcar,
+        false,
+        repo,
+    )?;
     if hubo_conflict {
         loop {
             println!("conflicts detected, please resolve them and then run '--continue'");
@@ -1754,40 +2636,40 @@ fn check_conflicts_and_get_tree(
             }
         }
     }
-    commands_fn::add(vec![".".to_string()], cliente.clone())?;
-    let (tree_map, tree_order) = get_hashmap_for_checkout(cliente.clone())?;
-    let final_tree = create_trees(tree_map, tree_order[0].clone(), cliente.clone())?;
+    update_index_before_add(repo)?;
+    add_files_command(".".to_string(), repo)?;
+    let (tree_map, tree_order) = get_hashmap_for_checkout(repo)?;
+    let final_tree = create_trees(tree_map, tree_order[0].clone(), repo)?;
     Ok(final_tree.get_hash())
 }
 
 pub fn create_rebase_commits(
     to_rebase_commits: Vec<String>,
     origin_name: String,
-    cliente: String,
-    commit_base: String,
+    repo: &Repository,
+    base_tree: String,
 ) -> Result<(), GitrError> {
-    let mut last_commit: String = get_commit(origin_name, cliente.clone())?;
-    let head = get_head(cliente.clone())?;
-    let path = get_current_repo(cliente.clone())? + "/gitr/" + &head;
-    file_manager::write_file(path, last_commit.clone())?;
+    let mut last_commit: String = get_commit(origin_name, repo)?;
+    let head = get_head(repo)?;
+    file_manager::write_file(repo.git_path(&head), last_commit.clone())?;
     for commit_old in to_rebase_commits.iter().rev() {
         let main_tree = check_conflicts_and_get_tree(
             last_commit.clone(),
             commit_old.to_string(),
-            commit_base.clone(),
-            cliente.clone(),
+            base_tree.clone(),
+            repo,
         )?;
-        let message = file_manager::get_commit_message(commit_old.clone(), cliente.clone())?;
+        let message = file_manager::get_commit_message(commit_old.clone(), repo)?;
         let commit = Commit::new(
             main_tree.clone(),
             vec![last_commit.clone()],
-            cliente.clone(),
-            cliente.clone(),
+            get_current_username(repo),
+            get_current_username(repo),
             message.clone(),
-            cliente.clone(),
+            repo,
         )?;
-        commit.save(cliente.clone())?;
-        let dir = get_current_repo(cliente.clone())? + "/gitr/" + &get_head(cliente.clone())?;
+        commit.save(repo)?;
+        let dir = repo.git_path(&get_head(repo)?);
         file_manager::write_file(dir, commit.get_hash())?;
         last_commit = commit.get_hash();
     }
@@ -1797,8 +2679,8 @@ pub fn create_rebase_commits(
 /*******************
  * CHECK-IGNORE FUNCTIONS
  * *****************/
-fn path_is_ignored(paths: Vec<String>, client: String) -> Result<bool, GitrError> {
-    let ignored_paths = match check_ignore_(paths, client) {
+fn path_is_ignored(paths: Vec<String>, repo: &Repository) -> Result<bool, GitrError> {
+    let ignored_paths = match check_ignore_(paths, repo) {
         Ok(paths) => paths,
         Err(e) => return Err(e),
     };
@@ -1808,17 +2690,15 @@ fn path_is_ignored(paths: Vec<String>, client: String) -> Result<bool, GitrError
     Ok(true)
 }
 
-pub fn check_ignore_(paths: Vec<String>, client: String) -> Result<Vec<String>, GitrError> {
-    let path = armar_path("gitrignore".to_string(), client.clone())?;
-    let gitignore = match file_manager::read_file(path){
+pub fn check_ignore_(paths: Vec<String>, repo: &Repository) -> Result<Vec<String>, GitrError> {
+    let gitignore = match file_manager::read_file(repo.work_path("gitrignore")){
         Ok(data) => data,
         Err(_) => return Ok(vec![]),
     };
     let lineas_ignore: Vec<&str> = gitignore.split('\n').collect();
     let mut lineas_full: Vec<String> = vec![];
-    let repo = file_manager::get_current_repo(client.clone())?;
     for linea in lineas_ignore {
-        lineas_full.push(repo.to_owned() + linea);
+        lineas_full.push(repo.work_tree().to_owned() + linea);
     }
     let mut ignored_paths = vec![];
     for path in paths {
@@ -1830,15 +2710,6 @@ pub fn check_ignore_(paths: Vec<String>, client: String) -> Result<Vec<String>,
     Ok(ignored_paths)
 }
 
-pub fn armar_path(path: String, cliente: String) -> Result<String, GitrError> {
-    let full_path = vec![
-        file_manager::get_current_repo(cliente)?,
-        "/".to_string(),
-        path,
-    ];
-    Ok(full_path.concat())
-}
-
 /*******************
  *   LS-TREE FUNCTIONS
  * *****************/
@@ -1846,10 +2717,10 @@ pub fn armar_path(path: String, cliente: String) -> Result<String, GitrError> {
 pub fn _ls_tree(
     flags: Vec<String>,
     father_dir: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<String, GitrError> {
-    let tree_hash = flags[flags.len() - 1].clone();
-    let data = _cat_file(vec!["-p".to_string(), tree_hash.clone()], cliente.clone())?;
+    let tree_hash = rev_parse_tree(&flags[flags.len() - 1], repo)?;
+    let data = _cat_file(vec!["-p".to_string(), tree_hash.clone()], repo)?;
 
     if flags.len() == 1 {
         print!("{}", data);
@@ -1875,7 +2746,7 @@ pub fn _ls_tree(
             _ls_tree(
                 vec![flags[0].clone(), entry[2].to_string().clone()],
                 new_father.clone(),
-                cliente.clone(),
+                repo,
             )?;
             if !flags[0].contains('t') {
                 continue;
@@ -1893,7 +2764,7 @@ pub fn _ls_tree(
         if flags[0].contains('l') {
             let size = _cat_file(
                 vec!["-s".to_string(), entry[2].to_string().clone()],
-                cliente.clone(),
+                repo,
             )?;
             res_entry.push(size);
         }
@@ -1919,12 +2790,408 @@ pub fn _ls_tree(
     Ok(result)
 }
 
+/*******************
+ *   DIFF FUNCTIONS
+ * *****************/
+
+/// Lineas de contexto por defecto alrededor de cada cambio (como git diff)
+pub const DIFF_CONTEXT: usize = 3;
+
+/// Como se muestra el contenido de cada archivo en un diff
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatoDiff {
+    /// hunks con lineas - y + (por defecto)
+    Unificado,
+    /// --word-diff: [-eliminado-]{+agregado+} dentro de cada linea
+    Palabras,
+    /// --stat: "path | N ++--" por archivo y una linea de totales
+    Stat,
+    /// --numstat: "agregadas<TAB>eliminadas<TAB>path" por archivo
+    Numstat,
+}
+
+/// Ancho maximo de la barra de +/- de --stat
+const DIFF_STAT_WIDTH: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpcionesDiff {
+    pub contexto: usize,
+    pub formato: FormatoDiff,
+}
+
+impl Default for OpcionesDiff {
+    fn default() -> Self {
+        OpcionesDiff {
+            contexto: DIFF_CONTEXT,
+            formato: FormatoDiff::Unificado,
+        }
+    }
+}
+
+/// diff entre el index y el work tree (solo archivos trackeados)
+pub fn diff_worktree(opciones: &OpcionesDiff, repo: &Repository) -> Result<String, GitrError> {
+    let (index_hashmap, _) = get_index_hashmap(repo)?;
+    let working_dir_hashmap = get_working_dir_hashmap(repo)?
+        .into_iter()
+        .filter(|(path, _)| index_hashmap.contains_key(path))
+        .collect();
+    let modos = modos_por_path(&[file_manager::read_index(repo).unwrap_or_default()], repo);
+    diff_hashmaps(
+        &index_hashmap,
+        &working_dir_hashmap,
+        &modos,
+        true,
+        opciones,
+        repo,
+    )
+}
+
+/// diff --cached: entre el commit de HEAD y el index
+pub fn diff_cached(opciones: &OpcionesDiff, repo: &Repository) -> Result<String, GitrError> {
+    let (head_hashmap, head_tree) = match file_manager::get_current_commit(repo) {
+        Ok(commit) => (
+            get_commit_hashmap(commit.clone(), repo)?,
+            index_de_commit(commit, repo)?,
+        ),
+        Err(_) => (HashMap::new(), Index::new()),
+    };
+    let (index_hashmap, _) = get_index_hashmap(repo)?;
+    let index = file_manager::read_index(repo).unwrap_or_default();
+    let modos = modos_por_path(&[head_tree, index], repo);
+    diff_hashmaps(&head_hashmap, &index_hashmap, &modos, false, opciones, repo)
+}
+
+/// diff entre los trees de dos commits (o branches)
+pub fn diff_commits(
+    viejo: &str,
+    nuevo: &str,
+    opciones: &OpcionesDiff,
+    repo: &Repository,
+) -> Result<String, GitrError> {
+    let viejo = rev_parse_commit(viejo, repo)?;
+    let nuevo = rev_parse_commit(nuevo, repo)?;
+    let viejo_hashmap = get_commit_hashmap(viejo.clone(), repo)?;
+    let nuevo_hashmap = get_commit_hashmap(nuevo.clone(), repo)?;
+    let modos = modos_por_path(
+        &[index_de_commit(viejo, repo)?, index_de_commit(nuevo, repo)?],
+        repo,
+    );
+    diff_hashmaps(
+        &viejo_hashmap,
+        &nuevo_hashmap,
+        &modos,
+        false,
+        opciones,
+        repo,
+    )
+}
+
+// un archivo que cambio entre los dos lados de un diff
+struct ArchivoDiff {
+    path: String,
+    hash_viejo: Option<String>,
+    hash_nuevo: Option<String>,
+    // el modo del lado que tiene el archivo, para los nuevos y los borrados
+    modo: u32,
+    data_vieja: Vec<u8>,
+    data_nueva: Vec<u8>,
+}
+
+impl ArchivoDiff {
+    fn es_binario(&self) -> bool {
+        es_binario(&self.data_vieja) || es_binario(&self.data_nueva)
+    }
+
+    fn textos(&self) -> (String, String) {
+        (
+            String::from_utf8_lossy(&self.data_vieja).to_string(),
+            String::from_utf8_lossy(&self.data_nueva).to_string(),
+        )
+    }
+
+    // (lineas agregadas, lineas eliminadas)
+    fn lineas_cambiadas(&self) -> (usize, usize) {
+        let (viejo, nuevo) = self.textos();
+        let diff = diff_de_lineas(&viejo, &nuevo);
+        (diff.lineas_agregadas.len(), diff.lineas_eliminadas.len())
+    }
+}
+
+/// Compara dos hashmaps path -> hash de blob (como los de get_commit_hashmap) e
+/// imprime cada archivo distinto segun el formato de las opciones, ordenados por path.
+/// modos tiene el modo de los archivos (ver modos_por_path), para los headers.
+/// Si nuevo_en_work_tree, el contenido nuevo se lee del work tree y no de los objetos.
+pub fn diff_hashmaps(
+    viejo: &HashMap<String, String>,
+    nuevo: &HashMap<String, String>,
+    modos: &HashMap<String, u32>,
+    nuevo_en_work_tree: bool,
+    opciones: &OpcionesDiff,
+    repo: &Repository,
+) -> Result<String, GitrError> {
+    let mut paths = viejo.keys().chain(nuevo.keys()).collect::<Vec<&String>>();
+    paths.sort();
+    paths.dedup();
+    let mut archivos = vec![];
+    for path in paths {
+        let (hash_viejo, hash_nuevo) = (viejo.get(path), nuevo.get(path));
+        if hash_viejo == hash_nuevo {
+            continue;
+        }
+        let data_vieja = match hash_viejo {
+            Some(hash) => file_manager::read_file_data_from_blob_hash(hash.clone(), repo)?,
+            None => vec![],
+        };
+        let data_nueva = match hash_nuevo {
+            Some(_) if nuevo_en_work_tree => file_manager::read_blob_content(path.clone())?,
+            Some(hash) => file_manager::read_file_data_from_blob_hash(hash.clone(), repo)?,
+            None => vec![],
+        };
+        archivos.push(ArchivoDiff {
+            path: index::relative_path(path, repo.work_tree()),
+            hash_viejo: hash_viejo.cloned(),
+            hash_nuevo: hash_nuevo.cloned(),
+            modo: modos.get(path).copied().unwrap_or(index::REGULAR_FILE_MODE),
+            data_vieja,
+            data_nueva,
+        });
+    }
+    Ok(match opciones.formato {
+        FormatoDiff::Stat => diff_stat(&archivos),
+        FormatoDiff::Numstat => diff_numstat(&archivos),
+        _ => archivos.iter().map(|archivo| diff_parche(archivo, opciones)).collect(),
+    })
+}
+
+/// Modo de cada archivo de los indices, por su path en el work tree (como las
+/// claves de get_commit_hashmap). Si un path esta en varios queda el del ultimo
+pub fn modos_por_path(indices: &[Index], repo: &Repository) -> HashMap<String, u32> {
+    indices
+        .iter()
+        .flat_map(|index| index.entries())
+        .map(|entry| (repo.work_path(&entry.path), entry.mode))
+        .collect()
+}
+
+fn index_de_commit(commit: String, repo: &Repository) -> Result<Index, GitrError> {
+    index_de_tree(&file_manager::get_main_tree(commit, repo)?, repo)
+}
+
+// headers de git y el diff unificado o por palabras del archivo
+fn diff_parche(archivo: &ArchivoDiff, opciones: &OpcionesDiff) -> String {
+    let path = &archivo.path;
+    let mut res = format!("diff --git a/{0} b/{0}\n", path);
+    match (&archivo.hash_viejo, &archivo.hash_nuevo) {
+        (None, Some(_)) => res.push_str(&format!("new file mode {:o}\n", archivo.modo)),
+        (Some(_), None) => res.push_str(&format!("deleted file mode {:o}\n", archivo.modo)),
+        _ => (),
+    }
+    res.push_str(&format!(
+        "index {}..{}\n",
+        hash_corto(archivo.hash_viejo.as_ref()),
+        hash_corto(archivo.hash_nuevo.as_ref())
+    ));
+    let origen = match archivo.hash_viejo {
+        Some(_) => format!("a/{}", path),
+        None => "/dev/null".to_string(),
+    };
+    let destino = match archivo.hash_nuevo {
+        Some(_) => format!("b/{}", path),
+        None => "/dev/null".to_string(),
+    };
+    if archivo.es_binario() {
+        res.push_str(&format!("Binary files {} and {} differ\n", origen, destino));
+        return res;
+    }
+    res.push_str(&format!("--- {}\n+++ {}\n", origen, destino));
+    let (viejo, nuevo) = archivo.textos();
+    res.push_str(&match opciones.formato {
+        FormatoDiff::Palabras => word_diff(&viejo, &nuevo, opciones.contexto),
+        _ => unified_diff(&viejo, &nuevo, opciones.contexto),
+    });
+    res
+}
+
+// " path | N ++--" por archivo (la barra se escala si no entra) y los totales
+fn diff_stat(archivos: &[ArchivoDiff]) -> String {
+    if archivos.is_empty() {
+        return String::new();
+    }
+    let cambios = archivos
+        .iter()
+        .map(|archivo| match archivo.es_binario() {
+            true => None,
+            false => Some(archivo.lineas_cambiadas()),
+        })
+        .collect::<Vec<Option<(usize, usize)>>>();
+    let ancho_path = archivos.iter().map(|a| a.path.chars().count()).max().unwrap_or(0);
+    let max_cambios = cambios.iter().flatten().map(|(a, e)| a + e).max().unwrap_or(0);
+    let ancho_numero = max_cambios.to_string().len();
+    let escalar = |n: usize| match max_cambios > DIFF_STAT_WIDTH && n > 0 {
+        true => (n * (DIFF_STAT_WIDTH - 1)) / max_cambios + 1,
+        false => n,
+    };
+
+    let mut res = String::new();
+    let (mut total_agregadas, mut total_eliminadas) = (0, 0);
+    for (archivo, cambio) in archivos.iter().zip(cambios) {
+        let path = format!("{:<ancho$}", archivo.path, ancho = ancho_path);
+        let (agregadas, eliminadas) = match cambio {
+            Some(cambio) => cambio,
+            None => {
+                res.push_str(&format!(
+                    " {} | Bin {} -> {} bytes\n",
+                    path,
+                    archivo.data_vieja.len(),
+                    archivo.data_nueva.len()
+                ));
+                continue;
+            }
+        };
+        total_agregadas += agregadas;
+        total_eliminadas += eliminadas;
+        let barra_agregadas = escalar(agregadas);
+        let barra_eliminadas = escalar(agregadas + eliminadas) - barra_agregadas;
+        let linea = format!(
+            " {} | {:>ancho$} {}{}",
+            path,
+            agregadas + eliminadas,
+            "+".repeat(barra_agregadas),
+            "-".repeat(barra_eliminadas),
+            ancho = ancho_numero
+        );
+        res.push_str(&format!("{}\n", linea.trim_end()));
+    }
+    let plural = |n: usize, singular: &str, plural: &str| match n {
+        1 => format!("{} {}", n, singular),
+        _ => format!("{} {}", n, plural),
+    };
+    res.push_str(&format!(" {}", plural(archivos.len(), "file changed", "files changed")));
+    if total_agregadas > 0 || total_eliminadas == 0 {
+        res.push_str(&format!(", {}", plural(total_agregadas, "insertion(+)", "insertions(+)")));
+    }
+    if total_eliminadas > 0 || total_agregadas == 0 {
+        res.push_str(&format!(", {}", plural(total_eliminadas, "deletion(-)", "deletions(-)")));
+    }
+    res.push('\n');
+    res
+}
+
+// "agregadas<TAB>eliminadas<TAB>path" por archivo, "-" en los binarios
+fn diff_numstat(archivos: &[ArchivoDiff]) -> String {
+    let mut res = String::new();
+    for archivo in archivos {
+        if archivo.es_binario() {
+            res.push_str(&format!("-\t-\t{}\n", archivo.path));
+            continue;
+        }
+        let (agregadas, eliminadas) = archivo.lineas_cambiadas();
+        res.push_str(&format!("{}\t{}\t{}\n", agregadas, eliminadas, archivo.path));
+    }
+    res
+}
+
+fn hash_corto(hash: Option<&String>) -> String {
+    match hash {
+        Some(hash) => hash.chars().take(7).collect(),
+        None => "0000000".to_string(),
+    }
+}
+
+// como git, un archivo con un byte nulo en los primeros 8000 bytes es binario
+fn es_binario(data: &[u8]) -> bool {
+    data.iter().take(8000).any(|b| *b == 0)
+}
+
+/*******************
+ *   SHOW FUNCTIONS
+ * *****************/
+
+/// Muestra un objeto: un commit con su header de log y el diff contra su primer padre,
+/// una tag anotada con su header y el objeto al que apunta, un tree como ls-tree
+/// y un blob con su contenido. Acepta cualquier revision de rev_parse.
+pub fn show_(revision: &str, opciones: &OpcionesDiff, repo: &Repository) -> Result<String, GitrError> {
+    let hash = rev_parse(revision, repo)?;
+    show_objeto(&hash, opciones, repo)
+}
+
+fn show_objeto(hash: &String, opciones: &OpcionesDiff, repo: &Repository) -> Result<String, GitrError> {
+    let data = file_manager::read_object(hash, repo)?;
+    let (header, contenido) = match data.split_once('\0') {
+        Some(partes) => partes,
+        None => return Err(GitrError::FileReadError(hash.to_string())),
+    };
+    match header.split(' ').next() {
+        Some("commit") => show_commit(hash, opciones, repo),
+        Some("tag") => show_tag(contenido, opciones, repo),
+        Some("tree") => Ok(format!("tree {}\n\n{}", hash, get_tree_data(contenido))),
+        Some("blob") => Ok(contenido.to_string()),
+        _ => Err(GitrError::FileReadError(hash.to_string())),
+    }
+}
+
+fn show_commit(commit: &str, opciones: &OpcionesDiff, repo: &Repository) -> Result<String, GitrError> {
+    let mut res = file_manager::commit_header(commit.to_string(), repo)?;
+    let padres = file_manager::get_parent_commit(commit.to_string(), repo)?;
+    let (padre_hashmap, padre_tree) = match padres[0].as_str() {
+        "None" => (HashMap::new(), Index::new()),
+        padre => (
+            get_commit_hashmap(padre.to_string(), repo)?,
+            index_de_commit(padre.to_string(), repo)?,
+        ),
+    };
+    let commit_hashmap = get_commit_hashmap(commit.to_string(), repo)?;
+    let modos = modos_por_path(
+        &[padre_tree, index_de_commit(commit.to_string(), repo)?],
+        repo,
+    );
+    res.push_str(&diff_hashmaps(
+        &padre_hashmap,
+        &commit_hashmap,
+        &modos,
+        false,
+        opciones,
+        repo,
+    )?);
+    Ok(res)
+}
+
+// header de la tag (nombre, tagger, fecha y mensaje) seguido del objeto apuntado
+fn show_tag(contenido: &str, opciones: &OpcionesDiff, repo: &Repository) -> Result<String, GitrError> {
+    let (header, mensaje) = contenido.split_once("\n\n").unwrap_or((contenido, ""));
+    let (mut objeto, mut nombre, mut tagger, mut fecha) =
+        (String::new(), String::new(), String::new(), String::new());
+    for linea in header.lines() {
+        match linea.split_once(' ') {
+            Some(("object", hash)) => objeto = hash.to_string(),
+            Some(("tag", tag)) => nombre = tag.to_string(),
+            Some(("tagger", datos)) => {
+                // "nombre <mail> timestamp zona"
+                let partes = datos.rsplitn(3, ' ').collect::<Vec<&str>>();
+                if partes.len() != 3 {
+                    return Err(GitrError::InvalidTagError);
+                }
+                tagger = partes[2].to_string();
+                fecha = file_manager::format_timestamp(partes[1])?;
+            }
+            _ => (),
+        }
+    }
+    if objeto.is_empty() {
+        return Err(GitrError::InvalidTagError);
+    }
+    let mut res = format!("tag {}\nTagger: {}\nDate: {}\n\n", nombre, tagger, fecha);
+    res.push_str(&format!("{}\n\n", mensaje.trim_end()));
+    res.push_str(&show_objeto(&objeto, opciones, repo)?);
+    Ok(res)
+}
+
 /*****************
  * PULL REQUESTS *
  *****************/
 
-pub fn _create_pr(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
-    let remote = file_manager::get_remote(cliente.clone())?;
+pub fn _create_pr(flags: Vec<String>, repo: &Repository) -> Result<(), GitrError> {
+    let remote = file_manager::get_remote(repo)?;
     let sv_url = remote.split('/').collect::<Vec<&str>>()[0];
     let sv_name = remote.split('/').collect::<Vec<&str>>()[1];
     let title = flags[0].clone();
@@ -1950,6 +3217,361 @@ pub fn _create_pr(flags: Vec<String>, cliente: String) -> Result<(), GitrError>
     Ok(())
 }
 
+/*******************
+ *   FSCK FUNCTIONS
+ * *****************/
+
+/// Resultado de fsck. Los objetos colgados (dangling) no se consideran errores.
+#[derive(Debug, Default, PartialEq)]
+pub struct FsckReport {
+    pub checked: usize,
+    /// "<hash>: <motivo>"
+    pub corrupt: Vec<String>,
+    /// "<tipo> <hash>"
+    pub missing: Vec<String>,
+    /// "<tipo> <hash>"
+    pub dangling: Vec<String>,
+    /// "<ref>: <motivo>"
+    pub bad_refs: Vec<String>,
+}
+
+impl FsckReport {
+    pub fn is_ok(&self) -> bool {
+        self.corrupt.is_empty() && self.missing.is_empty() && self.bad_refs.is_empty()
+    }
+}
+
+/// Valida el header y la estructura de un objeto descomprimido.
+/// Devuelve su tipo y los (tipo esperado, hash) de los objetos a los que apunta.
+fn fsck_object(data: &[u8]) -> Result<(String, Vec<(String, String)>), String> {
+    let fin_header = match data.iter().position(|b| *b == 0) {
+        Some(pos) => pos,
+        None => return Err("falta el header".to_string()),
+    };
+    let header = String::from_utf8_lossy(&data[..fin_header]).to_string();
+    let (tipo, largo) = match header.split_once(' ') {
+        Some((tipo, largo)) => (tipo.to_string(), largo),
+        None => return Err(format!("header invalido '{}'", header)),
+    };
+    let body = &data[fin_header + 1..];
+    if largo.parse::<usize>() != Ok(body.len()) {
+        return Err(format!("el tamaño del header ({}) no coincide con el contenido ({})", largo, body.len()));
+    }
+    let mut referencias = vec![];
+    match tipo.as_str() {
+        "blob" => {}
+        "tree" => {
+            let entries = match Tree::entries_from_data(body) {
+                Ok(entries) => entries,
+                Err(_) => return Err("tree invalido".to_string()),
+            };
+            for (mode, _, hash) in entries {
+                match mode.as_str() {
+                    "40000" => referencias.push(("tree".to_string(), hash)),
+                    "160000" => {} // submodulo: el commit esta en otro repositorio
+                    _ => referencias.push(("blob".to_string(), hash)),
+                }
+            }
+        }
+        "commit" => {
+            let texto = match std::str::from_utf8(body) {
+                Ok(texto) => texto.to_string(),
+                Err(_) => return Err("commit con contenido no UTF-8".to_string()),
+            };
+            let headers = texto.split("\n\n").next().unwrap_or("");
+            for campo in ["author ", "committer "] {
+                if !headers.lines().any(|l| l.starts_with(campo)) {
+                    return Err(format!("commit sin '{}'", campo.trim()));
+                }
+            }
+            let commit = match Commit::new_commit_from_string(texto) {
+                Ok(commit) => commit,
+                Err(_) => return Err("commit invalido".to_string()),
+            };
+            if !is_hex_hash(&commit.get_tree()) {
+                return Err("commit sin tree valido".to_string());
+            }
+            referencias.push(("tree".to_string(), commit.get_tree()));
+            for parent in commit.parents.iter().filter(|p| *p != "None") {
+                if !is_hex_hash(parent) {
+                    return Err(format!("parent invalido '{}'", parent));
+                }
+                referencias.push(("commit".to_string(), parent.clone()));
+            }
+        }
+        "tag" => {
+            let texto = match std::str::from_utf8(body) {
+                Ok(texto) => texto.to_string(),
+                Err(_) => return Err("tag con contenido no UTF-8".to_string()),
+            };
+            let tipo_objeto = texto
+                .lines()
+                .find_map(|l| l.strip_prefix("type "))
+                .unwrap_or("")
+                .to_string();
+            if tipo_objeto.is_empty() || !texto.lines().any(|l| l.starts_with("tag ")) {
+                return Err("tag sin 'type' o sin 'tag'".to_string());
+            }
+            let tag = match Tag::new_tag_from_string(texto) {
+                Ok(tag) => tag,
+                Err(_) => return Err("tag invalida".to_string()),
+            };
+            if !is_hex_hash(&tag.get_commit_hash()) {
+                return Err("tag sin 'object' valido".to_string());
+            }
+            referencias.push((tipo_objeto, tag.get_commit_hash()));
+        }
+        _ => return Err(format!("tipo de objeto desconocido '{}'", tipo)),
+    }
+    Ok((tipo, referencias))
+}
+
+/// Verifica todos los objetos del repositorio (hash, header y estructura), que
+/// existan los objetos a los que apuntan commits, trees, tags y el index, y que
+/// las refs de refs/heads y refs/tags apunten a objetos validos. Tambien reporta
+/// los objetos a los que no apunta nadie (dangling).
+pub fn fsck_(repo: &Repository) -> Result<FsckReport, GitrError> {
+    let mut report = FsckReport::default();
+    let mut hashes = file_manager::get_all_objects_hashes(repo)?;
+    hashes.sort();
+    let mut tipos: HashMap<String, String> = HashMap::new();
+    let mut referenciados: HashMap<String, String> = HashMap::new();
+    for hash in &hashes {
+        report.checked += 1;
+        let data = match repo.objects().read(hash) {
+            Ok(data) => data,
+            Err(e) => {
+                report.corrupt.push(format!("{}: no se pudo leer ({})", hash, e));
+                continue;
+            }
+        };
+        let calculado = crate::object_database::hash_object_data(&data);
+        if calculado != *hash {
+            report.corrupt.push(format!("{}: hash incorrecto (el contenido es {})", hash, calculado));
+            continue;
+        }
+        match fsck_object(&data) {
+            Ok((tipo, referencias)) => {
+                tipos.insert(hash.clone(), tipo);
+                for (tipo, referencia) in referencias {
+                    referenciados.entry(referencia).or_insert(tipo);
+                }
+            }
+            Err(motivo) => report.corrupt.push(format!("{}: {}", hash, motivo)),
+        }
+    }
+
+    // ###### REFS ######
+    let mut refs = vec![];
+    collect_refs("refs/heads", repo, &mut refs);
+    collect_refs("refs/tags", repo, &mut refs);
+    for (nombre, id) in refs {
+        if !is_hex_hash(&id) {
+            report.bad_refs.push(format!("{}: contenido invalido '{}'", nombre, id));
+            continue;
+        }
+        match tipos.get(&id).map(|t| t.as_str()) {
+            None => report.bad_refs.push(format!("{}: apunta a un objeto inexistente {}", nombre, id)),
+            Some("commit") => {}
+            Some("tag") if nombre.starts_with("refs/tags/") => {}
+            Some(tipo) => report.bad_refs.push(format!("{}: apunta a un {} ({})", nombre, tipo, id)),
+        }
+    }
+    let raices = root_ids(repo);
+    for id in &raices {
+        referenciados.entry(id.clone()).or_insert("commit".to_string());
+    }
+    if let Ok(index) = read_index(repo) {
+        for entry in index.entries() {
+            referenciados.entry(entry.hash.clone()).or_insert("blob".to_string());
+        }
+    }
+
+    // ###### MISSING Y DANGLING ######
+    let mut faltantes: Vec<(&String, &String)> = referenciados
+        .iter()
+        .filter(|(hash, _)| hashes.binary_search(hash).is_err())
+        .collect();
+    faltantes.sort();
+    for (hash, tipo) in faltantes {
+        report.missing.push(format!("{} {}", tipo, hash));
+    }
+    for hash in &hashes {
+        if let Some(tipo) = tipos.get(hash) {
+            if !referenciados.contains_key(hash) {
+                report.dangling.push(format!("{} {}", tipo, hash));
+            }
+        }
+    }
+    Ok(report)
+}
+
+/*******************
+ *   GC FUNCTIONS
+ * *****************/
+
+/// Tiempo que se conservan los objetos sueltos inalcanzables antes de borrarlos (2 semanas)
+pub const GC_PRUNE_GRACE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Cantidades antes y despues de correr gc/repack
+#[derive(Debug, Default, PartialEq)]
+pub struct GcStats {
+    pub loose_before: usize,
+    pub loose_after: usize,
+    pub packs_before: usize,
+    pub packs_after: usize,
+    pub packed: usize,
+    pub pruned: usize,
+}
+
+/// Junta (nombre, contenido) de todas las refs que hay dentro de git_dir/carpeta
+/// (recursivo), por ejemplo ("refs/heads/master", "<hash>")
+fn collect_refs(carpeta: &str, repo: &Repository, refs: &mut Vec<(String, String)>) {
+    let entradas = match fs::read_dir(repo.git_path(carpeta)) {
+        Ok(entradas) => entradas,
+        Err(_) => return,
+    };
+    for entrada in entradas.flatten() {
+        let nombre = format!("{}/{}", carpeta, entrada.file_name().to_string_lossy());
+        if entrada.path().is_dir() {
+            collect_refs(&nombre, repo, refs);
+        } else if let Ok(id) = file_manager::read_file(repo.git_path(&nombre)) {
+            refs.push((nombre, id.trim().to_string()));
+        }
+    }
+}
+
+fn is_hex_hash(id: &str) -> bool {
+    id.len() == 40 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Commits alcanzables desde commit que no se alcanzan desde ninguna ref ni desde
+/// destino: los que quedan perdidos al sacar un HEAD detached de commit.
+/// Del mas nuevo al mas viejo.
+pub fn commits_sin_referencia(
+    commit: &str,
+    destino: &str,
+    repo: &Repository,
+) -> Result<Vec<String>, GitrError> {
+    let mut refs = vec![];
+    collect_refs("refs", repo, &mut refs);
+    let mut raices = refs.into_iter().map(|(_, id)| id).collect::<Vec<String>>();
+    raices.push(destino.to_string());
+    raices.retain(|id| is_hex_hash(id) && repo.objects().contains(id));
+    let alcanzables = Commit::get_parents(raices, vec![], repo)?;
+    Commit::get_parents(vec![commit.to_string()], alcanzables, repo)
+}
+
+/// Ids a los que apuntan las refs (heads, tags y remotes) y HEAD, sin repetir
+fn root_ids(repo: &Repository) -> Vec<String> {
+    let mut refs = vec![];
+    collect_refs("refs", repo, &mut refs);
+    if let Ok(head) = file_manager::read_file(repo.git_path("HEAD")) {
+        refs.push(("HEAD".to_string(), head.trim().to_string()));
+    }
+    let mut ids: Vec<String> = vec![];
+    for (_, id) in refs {
+        if is_hex_hash(&id) && !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// Devuelve los ids de todos los objetos alcanzables desde las refs (heads, tags
+/// y remotes), HEAD y el index: commits, tags, trees y blobs.
+pub fn reachable_objects(repo: &Repository) -> Result<HashSet<String>, GitrError> {
+    let mut roots = root_ids(repo);
+    roots.retain(|id| repo.objects().contains(id));
+
+    let mut objects: HashSet<String> = HashSet::new();
+    for id in Commit::get_parents(roots, vec![], repo)? {
+        if !objects.insert(id.clone()) {
+            continue;
+        }
+        if let Ok(commit) = Commit::new_commit_from_data(file_manager::get_object(id, repo)?) {
+            objects.insert(commit.get_tree());
+            Tree::get_all_tree_objects(commit.get_tree(), repo, &mut objects)?;
+        }
+    }
+    if let Ok(index) = read_index(repo) {
+        for entry in index.entries() {
+            objects.insert(entry.hash.clone());
+        }
+    }
+    objects.retain(|id| repo.objects().contains(id));
+    Ok(objects)
+}
+
+/// Junta todos los objetos alcanzables en un unico pack (con deltas) y borra los
+/// objetos sueltos que quedaron empaquetados y los packs viejos. Los objetos
+/// inalcanzables de los packs viejos pasan a ser sueltos.
+/// Si prune es Some, ademas borra los sueltos inalcanzables mas viejos que ese tiempo.
+pub fn gc_(repo: &Repository, prune: Option<Duration>) -> Result<GcStats, GitrError> {
+    let disk = DiskObjectDatabase::new(&repo.git_path("objects"));
+    let old_packs = disk.pack_files();
+    let mut stats = GcStats {
+        loose_before: disk.loose().iter().count(),
+        packs_before: old_packs.len(),
+        ..Default::default()
+    };
+
+    let mut reachable: Vec<String> = reachable_objects(repo)?.into_iter().collect();
+    reachable.sort();
+    let mut new_pack = String::new();
+    if !reachable.is_empty() {
+        let mut contents = vec![];
+        for hash in &reachable {
+            contents.push(repo.objects().read(hash)?);
+        }
+        let pack = create_packfile(prepare_contents(contents))?;
+        repo.objects().write_pack(&pack)?;
+        let checksum = pack[pack.len() - 20..]
+            .iter()
+            .fold(String::new(), |mut output, b| {
+                let _ = write!(output, "{b:02x}");
+                output
+            });
+        new_pack = repo.git_path(&format!("objects/pack/pack-{}.pack", checksum));
+    }
+    stats.packed = reachable.len();
+    let reachable: HashSet<String> = reachable.into_iter().collect();
+
+    for old_pack in old_packs {
+        if old_pack == new_pack {
+            continue;
+        }
+        let pack = PackObjectDatabase::open(&old_pack)?;
+        for hash in pack.iter() {
+            if !reachable.contains(&hash) && !disk.loose().contains(&hash) {
+                disk.loose().write(&pack.read(&hash)?)?;
+            }
+        }
+        let _ = fs::remove_file(&old_pack);
+        let _ = fs::remove_file(format!("{}.idx", old_pack.trim_end_matches(".pack")));
+    }
+
+    let limite = prune.and_then(|grace| SystemTime::now().checked_sub(grace));
+    for hash in disk.loose().iter().collect::<Vec<String>>() {
+        if reachable.contains(&hash) {
+            disk.loose().remove(&hash)?;
+            continue;
+        }
+        let vencido = match (limite, disk.loose().modified(&hash)) {
+            (Some(limite), Some(modificado)) => modificado <= limite,
+            _ => false,
+        };
+        if vencido {
+            disk.loose().remove(&hash)?;
+            stats.pruned += 1;
+        }
+    }
+
+    stats.loose_after = disk.loose().iter().count();
+    stats.packs_after = disk.pack_files().len();
+    Ok(stats)
+}
+
 
 
 
@@ -2056,7 +3678,7 @@ mod comparar_diffs_tests {
             (
                 1,
                 true,
-                "<<<<<<< HEAD\nque\ntal\n=======\nque\ntal\nbien\ny\nvos\n>>>>>>> BRANCH"
+                "<<<<<<< ours\nque\ntal\n=======\nque\ntal\nbien\ny\nvos\n>>>>>>> theirs"
                     .to_string(),
             ),
             (2, false, "estas".to_string()),
@@ -2078,7 +3700,7 @@ mod comparar_diffs_tests {
             (
                 0,
                 true,
-                "<<<<<<< HEAD\nbuenas\n=======\nnihao\n>>>>>>> BRANCH".to_string(),
+                "<<<<<<< ours\nbuenas\n=======\nnihao\n>>>>>>> theirs".to_string(),
             ),
         ];
         assert_eq!(diff_final.unwrap().0.lineas, lineas_esperadas);
@@ -2098,7 +3720,7 @@ mod comparar_diffs_tests {
             (
                 2,
                 true,
-                "<<<<<<< HEAD\nandas\n=======\ntas\n>>>>>>> BRANCH".to_string(),
+                "<<<<<<< ours\nandas\n=======\ntas\n>>>>>>> theirs".to_string(),
             ),
         ];
         assert_eq!(diff_final.unwrap().0.lineas, lineas_esperadas);
@@ -2118,7 +3740,7 @@ mod comparar_diffs_tests {
             (
                 1,
                 true,
-                "<<<<<<< HEAD\nromo\n=======\nfomo\n>>>>>>> BRANCH".to_string(),
+                "<<<<<<< ours\nromo\n=======\nfomo\n>>>>>>> theirs".to_string(),
             ),
         ];
         assert_eq!(diff_final.unwrap().0.lineas, lineas_esperadas);
@@ -2138,7 +3760,7 @@ mod comparar_diffs_tests {
             (
                 0,
                 true,
-                "<<<<<<< HEAD\norigin\n=======\nnew\n>>>>>>> BRANCH".to_string(),
+                "<<<<<<< ours\norigin\n=======\nnew\n>>>>>>> theirs".to_string(),
             ),
         ];
         assert_eq!(diff_final.unwrap().0.lineas, lineas_esperadas);
@@ -2158,7 +3780,7 @@ mod comparar_diffs_tests {
             (
                 0,
                 true,
-                "<<<<<<< HEAD\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> BRANCH".to_string(),
+                "<<<<<<< ours\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> theirs".to_string(),
             ),
             (1, false, "como".to_string()),
         ];
@@ -2176,7 +3798,7 @@ mod comparar_diffs_tests {
         let diff_final = comparar_diffs(diff_base_origin, diff_base_branch, 4);
         let lineas_esperadas = vec![
             (0,false,"hola".to_string()),
-            (0,true,"<<<<<<< HEAD\norigin1\norigin2\norigin3\norigin4\norigin5\n=======\nnew1\nnew2\nnew3\nnew4\nnew5\n>>>>>>> BRANCH".to_string()),
+            (0,true,"<<<<<<< ours\norigin1\norigin2\norigin3\norigin4\norigin5\n=======\nnew1\nnew2\nnew3\nnew4\nnew5\n>>>>>>> theirs".to_string()),
             (1,false,"como".to_string()),
             (2,false,"estas".to_string()),
             (3,false,"pepe".to_string()),
@@ -2197,7 +3819,7 @@ mod comparar_diffs_tests {
         let lineas_esperadas = vec![(
             1,
             true,
-            "<<<<<<< HEAD\norigin1\n=======\ncomo\nnew3\n>>>>>>> BRANCH".to_string(),
+            "<<<<<<< ours\norigin1\n=======\ncomo\nnew3\n>>>>>>> theirs".to_string(),
         )];
 
         assert_eq!(diff_final.lineas, lineas_esperadas);
@@ -2214,7 +3836,7 @@ mod comparar_diffs_tests {
         let lineas_esperadas = vec![(
             1,
             true,
-            "<<<<<<< HEAD\ncomo\norigin\n=======\nnew\n>>>>>>> BRANCH".to_string(),
+            "<<<<<<< ours\ncomo\norigin\n=======\nnew\n>>>>>>> theirs".to_string(),
         )];
 
         assert_eq!(diff_final.0.lineas, lineas_esperadas);
@@ -2234,8 +3856,8 @@ mod comparar_diffs_tests {
 
         let diff_final = comparar_diffs(diff_base_origin, diff_base_branch, 2).unwrap();
         let lineas_esperadas = vec![
-            (2,true,"<<<<<<< HEAD\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> BRANCH".to_string()),
-            (4,true,"<<<<<<< HEAD\nori4\niguales\nori5\niguales para cerrar\n=======\nnew2\nnew3\niguales\nnew4\niguales para cerrar\n>>>>>>> BRANCH".to_string()),
+            (2,true,"<<<<<<< ours\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> theirs".to_string()),
+            (4,true,"<<<<<<< ours\nori4\niguales\nori5\niguales para cerrar\n=======\nnew2\nnew3\niguales\nnew4\niguales para cerrar\n>>>>>>> theirs".to_string()),
         ];
         assert_eq!(diff_final.0.lineas, lineas_esperadas);
     }
@@ -2253,11 +3875,74 @@ mod comparar_diffs_tests {
         let diff_esperado = vec![(
             2,
             true,
-            "<<<<<<< HEAD\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> BRANCH".to_string(),
+            "<<<<<<< ours\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> theirs".to_string(),
         )];
 
         assert_eq!(diff_final.0.lineas, diff_esperado);
     }
+
+    #[test]
+    fn comparar_diffs_test_14_conflict_estilo_diff3_con_el_base() {
+        let str_base = "hola\ncomo\nestas\n".to_string();
+        let str_origin = "hola\nque\ntal\n".to_string();
+        let str_new = "hola\nque\ntal\nbien\n".to_string();
+        let diff_base_origin = Diff::new(str_base.clone(), str_origin);
+        let diff_base_branch = Diff::new(str_base.clone(), str_new);
+
+        let (diff_final, hubo_conflict) =
+            comparar_diffs_con_opciones(
+                diff_base_origin,
+                diff_base_branch,
+                2,
+                OpcionesConflicto {
+                    estilo: EstiloConflicto::Diff3,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(hubo_conflict);
+        let archivo = _aplicar_diffs(str_base, diff_final).unwrap().concat();
+        assert_eq!(
+            archivo,
+            "hola\n<<<<<<< ours\nque\ntal\n||||||| base\ncomo\nestas\n=======\nque\ntal\nbien\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    fn comparar_diffs_test_15_conflict_resuelto_con_ours_o_theirs() {
+        let str_base = "hola\ncomo\nestas\nchau\n".to_string();
+        let str_origin = "hola\nque\nestas\nchau\n".to_string();
+        let str_new = "hola\ncuando\nestas\nadios\n".to_string();
+        let resolver = |resolucion| {
+            let diff_base_origin = Diff::new(str_base.clone(), str_origin.clone());
+            let diff_base_branch = Diff::new(str_base.clone(), str_new.clone());
+            let (diff_final, hubo_conflict) = comparar_diffs_con_opciones(
+                diff_base_origin,
+                diff_base_branch,
+                3,
+                OpcionesConflicto {
+                    resolucion,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let archivo = _aplicar_diffs(str_base.clone(), diff_final).unwrap().concat();
+            (archivo, hubo_conflict)
+        };
+
+        // solo se resuelve el conflicto, el cambio que no choca se toma igual
+        assert_eq!(
+            resolver(ResolucionConflicto::Ours),
+            ("hola\nque\nestas\nadios\n".to_string(), false)
+        );
+        assert_eq!(
+            resolver(ResolucionConflicto::Theirs),
+            ("hola\ncuando\nestas\nadios\n".to_string(), false)
+        );
+        let (archivo, hubo_conflict) = resolver(ResolucionConflicto::Marcar);
+        assert!(hubo_conflict);
+        assert!(archivo.contains("<<<<<<< ours\nque\n=======\ncuando\n>>>>>>> theirs\n"));
+    }
 }
 
 #[cfg(test)]
@@ -2313,7 +3998,7 @@ mod aplicar_diffs_tests {
         let diff_final = comparar_diffs(diff_base_origin, diff_base_branch, 2).unwrap();
         let _archivo_reconstruido = _aplicar_diffs(str_base, diff_final.0).unwrap();
         let archivo_esperado = vec![
-            "<<<<<<< HEAD\norigin1\n=======\nnew1\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\norigin1\n=======\nnew1\n>>>>>>> theirs\n",
             "como\n",
             "estas\n",
         ];
@@ -2334,7 +4019,7 @@ mod aplicar_diffs_tests {
         let _archivo_reconstruido = _aplicar_diffs(str_base, diff_final.0).unwrap();
         let archivo_esperado = vec![
             "hola\n",
-            "<<<<<<< HEAD\norigin1\n=======\nnew1\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\norigin1\n=======\nnew1\n>>>>>>> theirs\n",
             "estas\n",
         ];
 
@@ -2355,7 +4040,7 @@ mod aplicar_diffs_tests {
         let archivo_esperado = vec![
             "hola\n",
             "como\n",
-            "<<<<<<< HEAD\norigin1\n=======\nnew1\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\norigin1\n=======\nnew1\n>>>>>>> theirs\n",
         ];
 
         assert_eq!(_archivo_reconstruido, archivo_esperado);
@@ -2373,7 +4058,7 @@ mod aplicar_diffs_tests {
         let diff_final = comparar_diffs(diff_base_origin, diff_base_branch, 2).unwrap();
         let _archivo_reconstruido = _aplicar_diffs(str_base, diff_final.0).unwrap();
         let archivo_esperado = vec![
-            "<<<<<<< HEAD\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> theirs\n",
             "como\n",
             "estas\n",
         ];
@@ -2394,7 +4079,7 @@ mod aplicar_diffs_tests {
         let _archivo_reconstruido = _aplicar_diffs(str_base, diff_final.0).unwrap();
         let archivo_esperado = vec![
             "hola\n",
-            "<<<<<<< HEAD\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> theirs\n",
             "estas\n",
         ];
 
@@ -2415,7 +4100,7 @@ mod aplicar_diffs_tests {
         let archivo_esperado = vec![
             "hola\n",
             "como\n",
-            "<<<<<<< HEAD\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\norigin1\norigin2\n=======\nnew1\nnew2\n>>>>>>> theirs\n",
         ];
 
         assert_eq!(_archivo_reconstruido, archivo_esperado);
@@ -2438,9 +4123,9 @@ mod aplicar_diffs_tests {
         let _archivo_reconstruido = _aplicar_diffs(str_base, diff_final.0).unwrap();
         let archivo_esperado = vec!["hola\n",
         "como\n",
-        "<<<<<<< HEAD\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> BRANCH\n",
+        "<<<<<<< ours\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> theirs\n",
         "estas\n",
-        "<<<<<<< HEAD\nori4\niguales\nori5\niguales para cerrar\n=======\nnew2\nnew3\niguales\nnew4\niguales para cerrar\n>>>>>>> BRANCH\n",
+        "<<<<<<< ours\nori4\niguales\nori5\niguales para cerrar\n=======\nnew2\nnew3\niguales\nnew4\niguales para cerrar\n>>>>>>> theirs\n",
         ];
 
         for i in 0..archivo_esperado.len() {
@@ -2463,7 +4148,7 @@ mod aplicar_diffs_tests {
         let archivo_esperado = vec![
             "hola\n",
             "como\n",
-            "<<<<<<< HEAD\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> BRANCH\n",
+            "<<<<<<< ours\nori1\nori2\nori3\n=======\nnew1\n>>>>>>> theirs\n",
             "estas\n",
         ];
 
@@ -2585,7 +4270,8 @@ mod check_ignore_tests {
         .unwrap();
         let paths = vec!["cliente/repo_ignore/target".to_string()];
         let vec_match = vec!["cliente/repo_ignore/target".to_string()];
-        assert_eq!(check_ignore_(paths, cliente).unwrap(), vec_match);
+        let repo = file_manager::open_current_repo(cliente).unwrap();
+        assert_eq!(check_ignore_(paths, &repo).unwrap(), vec_match);
         fs::remove_dir_all(path).unwrap();
     }
 
@@ -2613,7 +4299,8 @@ mod check_ignore_tests {
             "cliente/repo_ignore/target".to_string(),
             "cliente/repo_ignore/target2".to_string(),
         ];
-        assert_eq!(check_ignore_(paths, cliente).unwrap(), vec_match);
+        let repo = file_manager::open_current_repo(cliente).unwrap();
+        assert_eq!(check_ignore_(paths, &repo).unwrap(), vec_match);
         fs::remove_dir_all(path).unwrap();
     }
 
@@ -2652,9 +4339,11 @@ mod check_ignore_tests {
         .unwrap();
 
         commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
-        let index = file_manager::read_index(cliente.clone()).unwrap();
-        assert!(!index.contains(&"cliente/repo_ignore/file1".to_string()));
-        assert!(!index.contains(&"cliente/repo_ignore/folder/file3".to_string()));
+        let repo = file_manager::open_current_repo(cliente).unwrap();
+        let index = file_manager::read_index(&repo).unwrap();
+        assert!(index.get("file1").is_none());
+        assert!(index.get("folder/file3").is_none());
+        assert!(index.get("folder/file4").is_some());
         fs::remove_dir_all(path).unwrap();
     }
 }
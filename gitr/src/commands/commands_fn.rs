@@ -1,10 +1,14 @@
-use crate::file_manager::{commit_log, get_branches, get_refs_ids};
+use crate::file_manager::{commit_log, get_branches, get_refs_ids, open_current_repo};
 use crate::file_manager::{
-    delete_tag, get_current_commit, get_current_repo, update_working_directory,
+    delete_tag, get_current_commit, update_working_directory,
 };
 use crate::git_transport::ref_discovery::{self, check_push};
+use crate::repository::Repository;
+use crate::rev_parse;
 use crate::{file_manager, gitr_errors::GitrError};
+use std::collections::HashSet;
 use std::path::Path;
+use std::time::Duration;
 
 use super::command_utils::{self, *};
 
@@ -23,7 +27,7 @@ pub fn init(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "init <new_repo_name>".to_string(),
         ));
     }
-    file_manager::init_repository(&(cliente.clone() + "/" + &flags[0]))?;
+    Repository::init(&(cliente.clone() + "/" + &flags[0]))?;
     file_manager::update_current_repo(&flags[0], cliente)?;
     println!("Initialized empty Gitr repository");
     Ok(())
@@ -48,7 +52,8 @@ pub fn hash_object(flags: Vec<String>, cliente: String) -> Result<(), GitrError>
         file_path = flags[1].clone();
         write = true;
     }
-    println!("{}", get_object_hash(cliente, &mut file_path, write)?);
+    let repo = open_current_repo(cliente)?;
+    println!("{}", get_object_hash(&repo, &mut file_path, write)?);
     Ok(())
 }
 
@@ -63,7 +68,8 @@ pub fn cat_file(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "cat-file <[-t/-s/-p]> <object hash>".to_string(),
         ));
     }
-    let data_to_print = _cat_file(flags, cliente)?;
+    let repo = open_current_repo(cliente)?;
+    let data_to_print = _cat_file(flags, &repo)?;
     println!("{}", data_to_print);
 
     Ok(())
@@ -79,8 +85,9 @@ pub fn add(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "add <[file/.]>".to_string(),
         ));
     }
-    update_index_before_add(cliente.clone())?;
-    add_files_command(flags[0].clone(), cliente)?;
+    let repo = open_current_repo(cliente)?;
+    update_index_before_add(&repo)?;
+    add_files_command(flags[0].clone(), &repo)?;
 
     Ok(())
 }
@@ -93,7 +100,8 @@ pub fn rm(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "rm <file>".to_string(),
         ));
     }
-    let removed = rm_from_index(&flags[0], cliente)?;
+    let repo = open_current_repo(cliente)?;
+    let removed = rm_from_index(&flags[0], &repo)?;
     if removed {
         println!("rm '{}'", flags[0]);
     } else {
@@ -102,28 +110,79 @@ pub fn rm(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
     Ok(())
 }
 
+// Reset current HEAD to the specified state
+pub fn reset(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    // reset [--soft | --mixed | --hard] [<revision>]
+    // reset [<revision>] -- <path>...
+    let usage = "reset [--soft | --mixed | --hard] [<revision>] | reset [<revision>] -- <path>...";
+    let invalido = || GitrError::InvalidArgumentError(flags.join(" "), usage.to_string());
+    let repo = open_current_repo(cliente)?;
+    if let Some(separador) = flags.iter().position(|flag| flag == "--") {
+        let paths = &flags[separador + 1..];
+        let revision = match &flags[..separador] {
+            [] => "HEAD",
+            [revision] if !revision.starts_with('-') => revision.as_str(),
+            _ => return Err(invalido()),
+        };
+        if paths.is_empty() {
+            return Err(invalido());
+        }
+        return reset_paths(revision, paths, &repo);
+    }
+    let mut modo = ModoReset::Mixed;
+    let mut revisiones = vec![];
+    for flag in &flags {
+        match flag.as_str() {
+            "--soft" => modo = ModoReset::Soft,
+            "--mixed" => modo = ModoReset::Mixed,
+            "--hard" => modo = ModoReset::Hard,
+            flag if flag.starts_with('-') => return Err(invalido()),
+            revision => revisiones.push(revision),
+        }
+    }
+    match revisiones.as_slice() {
+        [] => reset_("HEAD", modo, &repo),
+        [revision] => reset_(revision, modo, &repo),
+        _ => Err(invalido()),
+    }
+}
+
 //Record changes to the repository
 pub fn commit(flags: Vec<String>, second_parent: String, cliente: String) -> Result<(), GitrError> {
-    //commit -m <message-of-commit>
-    if flags[0] != "-m" || flags.len() < 2 {
+    //commit -m <message-of-commit>, durante un merge sin -m usa MERGE_MSG
+    if !flags.is_empty() && (flags[0] != "-m" || flags.len() < 2) {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "commit -m <commit_message>".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente.clone())?;
+    let merge_head = file_manager::read_merge_head(&repo)?;
+    if flags.is_empty() && merge_head.is_none() {
         return Err(GitrError::InvalidArgumentError(
             flags.join(" "),
             "commit -m <commit_message>".to_string(),
         ));
     }
-    let index_path = file_manager::get_current_repo(cliente.clone())?.to_string() + "/gitr/index";
-    if !Path::new(&index_path).exists() {
-        return status(flags, cliente.clone());
+    if !Path::new(&repo.git_path("index")).exists() {
+        return status(flags, cliente);
+    }
+    let unmerged = get_unmerged_paths(&repo)?;
+    if !unmerged.is_empty() {
+        return Err(GitrError::UnmergedPaths(unmerged));
     }
-    let (not_staged, _, _) = get_untracked_notstaged_files(cliente.clone())?;
-    let (new, mut modified) = get_tobe_commited_files(&not_staged, cliente.clone())?;
+    let (not_staged, _, _) = get_untracked_notstaged_files(&repo)?;
+    let (new, mut modified) = get_tobe_commited_files(&not_staged, &repo)?;
     let mut to_be_commited = new;
     to_be_commited.append(&mut modified);
-    if to_be_commited.is_empty() {
+    // el commit de un merge se hace aunque el resultado quede igual a HEAD
+    if to_be_commited.is_empty() && merge_head.is_none() {
         println!("nothing to commit, working tree clean");
         return Ok(());
     }
-    if flags[1].starts_with('\"') {
+    let message = if flags.is_empty() {
+        file_manager::read_file(repo.git_path("MERGE_MSG"))?
+    } else if flags[1].starts_with('\"') {
         let message = &flags[1..];
         let message = message.join(" ");
         if !message.chars().any(|c| c != ' ' && c != '\"') {
@@ -132,45 +191,144 @@ pub fn commit(flags: Vec<String>, second_parent: String, cliente: String) -> Res
                 "commit -m \"commit_message\"".to_string(),
             ));
         }
-        get_tree_entries(message.to_string(), second_parent, cliente.clone())?;
-        print_commit_confirmation(message, cliente.clone())?;
-        Ok(())
+        message
     } else {
-        Err(GitrError::InvalidArgumentError(
+        return Err(GitrError::InvalidArgumentError(
             flags.join(" "),
             "commit -m \"commit_message\"".to_string(),
-        ))
-    }
+        ));
+    };
+    let second_parent = match merge_head {
+        Some(merge_head) if second_parent == "None" => merge_head,
+        _ => second_parent,
+    };
+    get_tree_entries(message.to_string(), second_parent, &repo)?;
+    file_manager::clear_merge_state(&repo)?;
+    print_commit_confirmation(message, &repo)?;
+    Ok(())
 }
 
 // Switch branches or restore working tree files
 pub fn checkout(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    // checkout [<revision>] -- <path>...
+    if let Some(separador) = flags.iter().position(|flag| flag == "--") {
+        return checkout_paths(&flags, separador, cliente);
+    }
     if flags.is_empty() || flags.len() > 2 || (flags.len() == 2 && flags[0] != "-b") {
         return Err(GitrError::InvalidArgumentError(
             flags.join(" "),
             "checkout <branch>".to_string(),
         ));
     }
-    commit_existing(cliente.clone())?;
-    let branch_to_checkout = get_branch_to_checkout(flags.clone(), cliente.clone())?;
-    let current_commit = file_manager::get_commit(branch_to_checkout.clone(), cliente.clone())?;
-    file_manager::update_working_directory(current_commit, cliente.clone())?;
+    let repo = open_current_repo(cliente)?;
+    commit_existing(&repo)?;
+    let previous_commit = get_current_commit(&repo)?;
+    let was_detached = file_manager::get_current_branch(&repo)?.is_none();
+    // checkout <commit | tag>: HEAD queda detached en el commit
+    if flags.len() == 1 && !branch_exists(flags[0].clone(), &repo) {
+        let commit = match rev_parse::rev_parse_commit(&flags[0], &repo) {
+            Ok(commit) => commit,
+            Err(_) => return Err(GitrError::BranchNonExistsError(flags[0].clone())),
+        };
+        checkout_commit(&commit, &repo)?;
+        file_manager::detach_head(&commit, &repo)?;
+        if was_detached {
+            warn_lost_commits(&previous_commit, &commit, &repo)?;
+        }
+        println!("Note: switching to '{}'. You are in 'detached HEAD' state.", flags[0]);
+        let message = file_manager::get_commit_message(commit.clone(), &repo)?;
+        println!("HEAD is now at {} {}", &commit[..7], message.trim_end());
+        return Ok(());
+    }
+    let branch_to_checkout = get_branch_to_checkout(flags.clone(), &repo)?;
+    let current_commit = file_manager::get_commit(branch_to_checkout.clone(), &repo)?;
+    checkout_commit(&current_commit, &repo)?;
     let path_head = format!("refs/heads/{}", branch_to_checkout);
-    file_manager::update_head(&path_head, cliente.clone())?;
+    file_manager::update_head(&path_head, &repo)?;
+    if was_detached {
+        warn_lost_commits(&previous_commit, &current_commit, &repo)?;
+    }
+
+    Ok(())
+}
 
+// al salir de un HEAD detached avisa que commits quedaron sin ninguna branch que los alcance
+fn warn_lost_commits(previous: &str, new: &str, repo: &Repository) -> Result<(), GitrError> {
+    let lost = commits_sin_referencia(previous, new, repo)?;
+    if lost.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "Warning: you are leaving {} commit(s) behind, not connected to any of your branches:\n",
+        lost.len()
+    );
+    for commit in lost {
+        let message = file_manager::get_commit_message(commit.clone(), repo)?;
+        println!("  {} {}", &commit[..7], message.trim_end());
+    }
     Ok(())
 }
 
+// checkout -- <path> restaura los archivos del index, checkout <revision> -- <path>
+// los restaura de la revision y tambien los actualiza en el index
+fn checkout_paths(flags: &[String], separador: usize, cliente: String) -> Result<(), GitrError> {
+    let paths = &flags[separador + 1..];
+    if paths.is_empty() || separador > 1 || flags[..separador].iter().any(|f| f.starts_with('-')) {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "checkout [<revision>] -- <path>...".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente)?;
+    if separador == 0 {
+        return restore_(paths, None, &repo);
+    }
+    restore_(paths, Some(&flags[0]), &repo)?;
+    reset_paths(&flags[0], paths, &repo)
+}
+
+// Restore working tree files
+pub fn restore(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    // restore <path>...
+    // restore --source <revision> <path>...
+    let usage = "restore [--source <revision>] <path>...".to_string();
+    let mut source = None;
+    let mut paths = vec![];
+    let mut i = 0;
+    while i < flags.len() {
+        if let Some(revision) = flags[i].strip_prefix("--source=") {
+            source = Some(revision.to_string());
+        } else if flags[i] == "--source" || flags[i] == "-s" {
+            i += 1;
+            match flags.get(i) {
+                Some(revision) => source = Some(revision.clone()),
+                None => return Err(GitrError::InvalidArgumentError(flags.join(" "), usage)),
+            }
+        } else if flags[i].starts_with('-') {
+            return Err(GitrError::InvalidArgumentError(flags.join(" "), usage));
+        } else {
+            paths.push(flags[i].clone());
+        }
+        i += 1;
+    }
+    if paths.is_empty() {
+        return Err(GitrError::InvalidArgumentError(flags.join(" "), usage));
+    }
+    let repo = open_current_repo(cliente)?;
+    restore_(&paths, source.as_deref(), &repo)
+}
+
 //Show commit logs
 pub fn log(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
     // log
-    commit_existing(cliente.clone())?;
+    let repo = open_current_repo(cliente)?;
+    commit_existing(&repo)?;
     if flags.is_empty() {
-        let log_res = commit_log("-1".to_string(), cliente.clone())?;
+        let log_res = commit_log("-1".to_string(), &repo)?;
         print!("{}", log_res);
     }
     if flags.len() == 2 && flags[0] == "-n" && flags[1].parse::<usize>().is_ok() {
-        let log_res = commit_log(flags[1].to_string(), cliente.clone())?;
+        let log_res = commit_log(flags[1].to_string(), &repo)?;
         print!("{}", log_res);
     }
     Ok(())
@@ -183,51 +341,58 @@ pub fn branch(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
     //branch -d <branch-to-delete>
     //branch -l
     //branch <new-branch-name>
+    let repo = open_current_repo(cliente)?;
     if flags.is_empty()
         || (flags.len() == 1 && flags[0] == "-l")
         || (flags.len() == 1 && flags[0] == "--list")
     {
-        println!("{}", print_branches(cliente.clone())?);
+        println!("{}", print_branches(&repo)?);
         return Ok(());
     }
-    commit_existing(cliente.clone())?;
+    commit_existing(&repo)?;
     if flags.len() == 2 && flags[0] == "-d" {
-        branch_delete_flag(flags[1].clone(), cliente.clone())?;
+        branch_delete_flag(flags[1].clone(), &repo)?;
     }
     if flags.len() == 3 && flags[0] == "-m" {
-        branch_move_flag(flags[1].clone(), flags[2].clone(), cliente.clone())?;
+        branch_move_flag(flags[1].clone(), flags[2].clone(), &repo)?;
     }
     if flags.len() == 1 && flags[0] != "-l" && flags[0] != "--list" {
-        branch_newbranch_flag(flags[0].clone(), cliente.clone())?;
+        branch_newbranch_flag(flags[0].clone(), &repo)?;
     }
     Ok(())
 }
 
 pub fn ls_files(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
     //ls-files --stage
+    let repo = open_current_repo(cliente)?;
     if flags.is_empty() || flags[0] == "--cached" || flags[0] == "-c" {
-        let ls_files_res = get_ls_files_cached(cliente.clone())?;
+        let ls_files_res = get_ls_files_cached(&repo)?;
         print!("{}", ls_files_res);
         return Ok(());
     }
     if flags[0] == "--stage" {
-        let res_output = file_manager::read_index(cliente.clone())?;
-        println!("{}", res_output);
+        let res_output = get_ls_files_stage(&repo)?;
+        print!("{}", res_output);
+        return Ok(());
+    }
+    if flags[0] == "--unmerged" || flags[0] == "-u" {
+        let res_output = get_ls_files_unmerged(&repo)?;
+        print!("{}", res_output);
         return Ok(());
     }
     if flags[0] == "--deleted" {
-        let res_output = get_ls_files_deleted_modified(true, cliente.clone())?;
+        let res_output = get_ls_files_deleted_modified(true, &repo)?;
         print!("{}", res_output);
         return Ok(());
     }
     if flags[0] == "--modified" {
-        let res_output = get_ls_files_deleted_modified(false, cliente.clone())?;
+        let res_output = get_ls_files_deleted_modified(false, &repo)?;
         print!("{}", res_output);
         return Ok(());
     }
     Err(GitrError::InvalidArgumentError(
         flags.join(" "),
-        "ls-files --stage".to_string(),
+        "ls-files [--cached | --stage | --unmerged | --deleted | --modified]".to_string(),
     ))
 }
 
@@ -241,14 +406,16 @@ pub fn clone(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
 
 // Show the working tree status
 pub fn status(_flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
-    let status = get_status(cliente.clone())?;
+    let repo = open_current_repo(cliente)?;
+    let status = get_status(&repo)?;
     println!("{}", status);
     Ok(())
 }
 
 pub fn tag(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    let repo = open_current_repo(cliente)?;
     if flags.is_empty() || (flags.len() == 1 && flags[0] == "-l") {
-        println!("{}", get_tags_str(cliente.clone())?);
+        println!("{}", get_tags_str(&repo)?);
         return Ok(());
     }
     if flags.len() >= 4 && flags[0] == "-a" && flags[2] == "-m" {
@@ -263,15 +430,15 @@ pub fn tag(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
                 ));
             }
         }
-        create_annotated_tag(flags[1].clone(), message.clone(), cliente.clone())?;
+        create_annotated_tag(flags[1].clone(), message.clone(), &repo)?;
         return Ok(());
     }
     if flags.len() == 1 && flags[0] != "-l" {
-        create_lightweight_tag(flags[0].clone(), cliente.clone())?;
+        create_lightweight_tag(flags[0].clone(), &repo)?;
         return Ok(());
     }
     if flags.len() == 2 && flags[0] == "-d" {
-        let res = delete_tag(flags[1].clone(), cliente)?;
+        let res = delete_tag(flags[1].clone(), &repo)?;
         println!("{}", res);
         return Ok(());
     }
@@ -286,74 +453,142 @@ pub fn fetch(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
 }
 
 pub fn merge(_flags: Vec<String>, cliente: String) -> Result<(bool, String, Vec<String>), GitrError> {
+    let usage = "merge [--no-ff | --ff-only] [--squash] [-X ours | -X theirs] <branch-name> | merge --continue | merge --abort".to_string();
     if _flags.is_empty() {
-        return Err(GitrError::InvalidArgumentError(
-            _flags.join(" "),
-            "merge <branch-name>".to_string(),
-        ));
+        return Err(GitrError::InvalidArgumentError(_flags.join(" "), usage));
     }
     
-    let branch_name = _flags[0].clone();
+    let repo = open_current_repo(cliente.clone())?;
+
+    // merge --continue commitea con MERGE_MSG, merge --abort vuelve a ORIG_HEAD
+    match _flags[0].as_str() {
+        "--continue" => {
+            if file_manager::read_merge_head(&repo)?.is_none() {
+                return Err(GitrError::NoMergeInProgress);
+            }
+            commit(vec![], "None".to_string(), cliente)?;
+            return Ok((false, "".to_string(), vec![]));
+        }
+        "--abort" => {
+            merge_abort(&repo)?;
+            return Ok((false, "".to_string(), vec![]));
+        }
+        _ => (),
+    }
+
+    let mut opciones = OpcionesMerge::default();
+    let mut branch_name = None;
+    let mut flags = _flags.iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--ff" => opciones.fast_forward = ModoFastForward::Permitido,
+            "--no-ff" => opciones.fast_forward = ModoFastForward::Nunca,
+            "--ff-only" => opciones.fast_forward = ModoFastForward::Solo,
+            "--squash" => opciones.squash = true,
+            _ if flag.starts_with("-X") => {
+                let valor = match flag.strip_prefix("-X") {
+                    Some("") => flags.next().map(|valor| valor.as_str()),
+                    valor => valor,
+                };
+                opciones.resolucion = match valor {
+                    Some("ours") => ResolucionConflicto::Ours,
+                    Some("theirs") => ResolucionConflicto::Theirs,
+                    _ => return Err(GitrError::InvalidArgumentError(_flags.join(" "), usage)),
+                };
+            }
+            _ if !flag.starts_with('-') && branch_name.is_none() => {
+                branch_name = Some(flag.clone());
+            }
+            _ => return Err(GitrError::InvalidArgumentError(_flags.join(" "), usage)),
+        }
+    }
+    let branch_name = match branch_name {
+        Some(branch_name) => branch_name,
+        None => return Err(GitrError::InvalidArgumentError(_flags.join(" "), usage)),
+    };
+    // --squash no crea commit de merge, asi que no tiene sentido con --no-ff
+    if opciones.squash && opciones.fast_forward == ModoFastForward::Nunca {
+        return Err(GitrError::InvalidArgumentError(_flags.join(" "), usage));
+    }
 
-    let origin_name = file_manager::get_head(cliente.clone())?
-        .split('/')
-        .collect::<Vec<&str>>()[2]
-        .to_string();
+    let origin_name = file_manager::get_current_branch(&repo)?.unwrap_or("HEAD".to_string());
 
-    match merge_(origin_name, branch_name, cliente.clone()) {
+    match merge_(origin_name, branch_name, &opciones, &repo) {
         Ok((hubo_conflict_res, branch_hash_res, archivos_conflict_res)) => Ok((hubo_conflict_res, branch_hash_res, archivos_conflict_res)),
         Err(e) => Err(e),
     }
 }
 
-pub fn merge_(origin_name: String, branch_name: String, cliente: String) -> Result<(bool, String, Vec<String>), GitrError> {
+pub fn merge_(origin_name: String, branch_name: String, opciones: &OpcionesMerge, repo: &Repository) -> Result<(bool, String, Vec<String>), GitrError> {
     let mut hubo_conflict = false;
     let mut archivos_conflict = vec![];
-    
-    let branch_commits = branch_commits_list(branch_name.clone(), cliente.clone())?;
-    let origin_commits = branch_commits_list(origin_name.clone(), cliente.clone())?;
-
-    for commit in branch_commits.clone() {
-        if origin_commits.contains(&commit) {
-            if commit == origin_commits[0] {
-                println!(
-                    "Updating {}..{}",
-                    &origin_commits[0][..7],
-                    &branch_commits[0][..7]
-                );
-                println!("Fast-forward");
-                fast_forward_merge(branch_name.clone(), cliente.clone())?;
-                break;
-            }
-            (hubo_conflict, archivos_conflict) = command_utils::three_way_merge(
-                commit,
-                origin_commits[0].clone(),
-                branch_commits[0].clone(),
-                cliente.clone(),
-            )?;
-            if !hubo_conflict {
-                println!("no hubo conflictos");
-                if cliente.contains('/'){
-                    return Ok((hubo_conflict, branch_commits[0].clone(), archivos_conflict));
-                }
-                add(vec![".".to_string()], cliente.clone())?;
-                println!("no fallo en el add.");
-
-                command_utils::create_merge_commit(
-                    branch_name.clone(),
-                    branch_commits[0].clone(),
-                    cliente,
-                )?;
-            }
-            break;
+
+    let branch_commit = rev_parse::rev_parse_commit(&branch_name, repo)?;
+    let origin_commit = rev_parse::rev_parse_commit(&origin_name, repo)?;
+    if !repo.is_bare() && file_manager::read_merge_head(repo)?.is_some() {
+        return Err(GitrError::MergeInProgress);
+    }
+
+    let bases = command_utils::merge_bases(&origin_commit, &branch_commit, repo)?;
+    if bases.is_empty() {
+        return Err(GitrError::UnrelatedHistories(origin_name, branch_name));
+    }
+    if bases.contains(&branch_commit) {
+        println!("Already up to date.");
+        return Ok((hubo_conflict, branch_commit, archivos_conflict));
+    }
+    let es_fast_forward = bases.contains(&origin_commit);
+    if !es_fast_forward && opciones.fast_forward == ModoFastForward::Solo {
+        return Err(GitrError::NonFastForward(branch_name));
+    }
+    if !repo.is_bare() {
+        file_manager::write_orig_head(&origin_commit, repo)?;
+    }
+    if es_fast_forward && !opciones.squash && opciones.fast_forward != ModoFastForward::Nunca {
+        println!("Updating {}..{}", &origin_commit[..7], &branch_commit[..7]);
+        println!("Fast-forward");
+        fast_forward_merge(branch_name.clone(), repo)?;
+        return Ok((hubo_conflict, branch_commit, archivos_conflict));
+    }
+
+    let base_tree = command_utils::tree_de_merge_bases(&bases, repo)?;
+    (hubo_conflict, archivos_conflict) = command_utils::three_way_merge(
+        base_tree,
+        origin_commit,
+        branch_commit.clone(),
+        opciones.resolucion,
+        true,
+        repo,
+    )?;
+    if opciones.squash {
+        // el resultado queda en el index para commitearlo a mano, sin MERGE_HEAD
+        if !hubo_conflict && !repo.is_bare() {
+            update_index_before_add(repo)?;
+            add_files_command(".".to_string(), repo)?;
+        }
+        println!("Squash commit -- not updating HEAD");
+        return Ok((hubo_conflict, branch_commit, archivos_conflict));
+    }
+    if !hubo_conflict {
+        if repo.is_bare() {
+            return Ok((hubo_conflict, branch_commit, archivos_conflict));
         }
+        update_index_before_add(repo)?;
+        add_files_command(".".to_string(), repo)?;
+
+        command_utils::create_merge_commit(branch_name.clone(), branch_commit.clone(), repo)?;
+    } else if !repo.is_bare() {
+        // el merge queda en curso hasta el commit o merge --abort
+        let message = format!("Merge branch '{}'", branch_name);
+        file_manager::write_merge_state(&branch_commit, &message, repo)?;
     }
-    Ok((hubo_conflict, branch_commits[0].clone(), archivos_conflict))
+    Ok((hubo_conflict, branch_commit, archivos_conflict))
 }
 
 pub fn remote(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    let repo = open_current_repo(cliente)?;
     if flags.is_empty() {
-        let remote = file_manager::read_file(get_current_repo(cliente.clone())? + "/gitr/remote")?;
+        let remote = file_manager::read_file(repo.git_path("remote"))?;
         println!("remote: {}", remote);
     } else {
         let parts: Vec<&str> = flags[0].split('/').collect();
@@ -363,10 +598,7 @@ pub fn remote(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
                 "remote <remote-url>/<remote-name>".to_string(),
             ));
         }
-        file_manager::write_file(
-            get_current_repo(cliente.clone())? + "/gitr/remote",
-            flags[0].clone(),
-        )?;
+        file_manager::write_file(repo.git_path("remote"), flags[0].clone())?;
     }
     Ok(())
 }
@@ -383,26 +615,24 @@ fn pullear(
         ));
     }
 
+    let repo = open_current_repo(cliente)?;
+
     // ########## HANDSHAKE ##########
-    let mut stream = handshake("git-upload-pack".to_string(), cliente.clone())?;
+    let mut stream = handshake("git-upload-pack".to_string(), &repo)?;
 
     //  ########## REFERENCE DISCOVERY ##########
     let hash_n_references = protocol_reference_discovery(&mut stream)?;
 
     // ########## WANTS N HAVES ##########
     let pkt_needed =
-        protocol_wants_n_haves(hash_n_references.clone(), &mut stream, cliente.clone())?;
+        protocol_wants_n_haves(hash_n_references.clone(), &mut stream, &repo)?;
     // ########## PACKFILE ##########
     if pkt_needed {
-        pull_packfile(&mut stream, cliente.clone())?;
+        pull_packfile(&mut stream, &repo)?;
     }
     if actualizar_work_dir {
-        file_manager::update_client_refs(
-            hash_n_references.clone(),
-            file_manager::get_current_repo(cliente.clone())?,
-            cliente.clone(),
-        )?;
-        update_working_directory(get_current_commit(cliente.clone())?, cliente.clone())?;
+        file_manager::update_client_refs(hash_n_references.clone(), &repo)?;
+        update_working_directory(get_current_commit(&repo)?, &repo)?;
     }
 
     Ok(())
@@ -419,13 +649,15 @@ pub fn push(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "push <no-args>".to_string(),
         ));
     }
+    let repo = open_current_repo(cliente)?;
+
     // ########## HANDSHAKE ##########
-    let mut stream = handshake("git-receive-pack".to_string(), cliente.clone())?;
+    let mut stream = handshake("git-receive-pack".to_string(), &repo)?;
 
     //  ########## REFERENCE DISCOVERY ##########
     let hash_n_references = protocol_reference_discovery(&mut stream)?;
     // ########## REFERENCE UPDATE REQUEST ##########
-    match check_push(hash_n_references.clone(), get_refs_ids("heads", cliente.clone())?,get_branches(cliente.clone())?, cliente.clone()) {
+    match check_push(hash_n_references.clone(), get_refs_ids("heads", &repo)?,get_branches(&repo)?, &repo) {
         Ok(_) => {}
         Err(e) => {
             match std::io::Write::write(&mut stream, "0000".as_bytes()) {
@@ -438,12 +670,12 @@ pub fn push(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
         }
     }
     let (pkt_needed, pkt_ids) =
-        reference_update_request(&mut stream, hash_n_references.clone(), cliente.clone())?;
+        reference_update_request(&mut stream, hash_n_references.clone(), &repo)?;
 
     // ########## PACKFILE ##########
     if pkt_needed {
         println!("pushing packfile");
-        push_packfile(&mut stream, pkt_ids, hash_n_references, cliente)?;
+        push_packfile(&mut stream, pkt_ids, hash_n_references, &repo)?;
     }
     Ok(())
 }
@@ -455,7 +687,8 @@ pub fn show_ref(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "show-ref [--head]".to_string(),
         ));
     }
-    let refs = match ref_discovery::ref_discovery(&(get_current_repo(cliente.clone())? + "/gitr")) {
+    let repo = open_current_repo(cliente)?;
+    let refs = match ref_discovery::ref_discovery(&repo) {
         Ok(refs) => refs.0,
         Err(e) => {
             println!("Error: {}", e);
@@ -485,7 +718,8 @@ pub fn ls_tree(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
             "ls-tree [options] <tree-hash>".to_string(),
         ));
     }
-    command_utils::_ls_tree(flags, "".to_string(), cliente)?;
+    let repo = open_current_repo(cliente)?;
+    command_utils::_ls_tree(flags, "".to_string(), &repo)?;
     Ok(())
 }
 
@@ -543,36 +777,209 @@ pub fn echo(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
         ));
     }
 
-    let repo_path = file_manager::get_current_repo(cliente.clone())?.to_string();
+    let repo = open_current_repo(cliente)?;
 
-    let file_path = format!("{}/{}", repo_path, flags[flags.len() - 1]);
+    let file_path = repo.work_path(&flags[flags.len() - 1]);
     println!("escribo \"{}\" en {}", texto, file_path);
     file_manager::write_file(file_path, texto)
 }
 
 pub fn rebase(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
     let origin_name = flags[0].clone();
-    let branch_name = file_manager::get_head(cliente.clone())?
-        .split('/')
-        .collect::<Vec<&str>>()[2]
-        .to_string();
-    let branch_commits = command_utils::branch_commits_list(branch_name.clone(), cliente.clone())?;
-    let origin_commits = command_utils::branch_commits_list(origin_name.clone(), cliente.clone())?;
-    let mut to_rebase_commits: Vec<String> = vec![];
-    for commit in branch_commits.clone() {
-        if origin_commits.contains(&commit) {
-            if commit == origin_commits[0] {
-                println!("nothing to rebase");
-                return Ok(());
-            }
-            create_rebase_commits(to_rebase_commits, origin_name, cliente.clone(), commit)?;
-            break;
+    let repo = open_current_repo(cliente)?;
+    let branch_name = file_manager::get_current_branch(&repo)?.unwrap_or("HEAD".to_string());
+    let branch_commit = rev_parse::rev_parse_commit(&branch_name, &repo)?;
+    let origin_commit = rev_parse::rev_parse_commit(&origin_name, &repo)?;
+    let bases = command_utils::merge_bases(&branch_commit, &origin_commit, &repo)?;
+    if bases.is_empty() {
+        return Err(GitrError::UnrelatedHistories(branch_name, origin_name));
+    }
+    if bases.contains(&origin_commit) {
+        println!("nothing to rebase");
+        return Ok(());
+    }
+    // se rebasean los commits de la branch que no estan en origin
+    let en_origin: HashSet<String> = command_utils::ancestros(vec![origin_commit], &repo)?
+        .into_iter()
+        .collect();
+    let to_rebase_commits: Vec<String> = command_utils::branch_commits_list(branch_name, &repo)?
+        .into_iter()
+        .take_while(|commit| !en_origin.contains(commit))
+        .collect();
+    let base_tree = command_utils::tree_de_merge_bases(&bases, &repo)?;
+    create_rebase_commits(to_rebase_commits, origin_name, &repo, base_tree)
+}
+
+/// Muestra los cambios entre el work tree y el index, el index y HEAD o dos commits
+pub fn diff(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    // diff
+    // diff --cached
+    // diff <commit> <commit>
+    // -U<n> / --unified=<n> cambia las lineas de contexto
+    // --word-diff muestra los cambios por palabra
+    // --stat / --numstat muestran solo cuantas lineas cambiaron en cada archivo
+    let usage =
+        "diff [-U<n>] [--word-diff | --stat | --numstat] [--cached | <commit> <commit>]".to_string();
+    let mut opciones = OpcionesDiff::default();
+    let mut cached = false;
+    let mut revisiones = vec![];
+    for flag in &flags {
+        if flag == "--cached" || flag == "--staged" {
+            cached = true;
+        } else if flag == "--word-diff" {
+            opciones.formato = FormatoDiff::Palabras;
+        } else if flag == "--stat" {
+            opciones.formato = FormatoDiff::Stat;
+        } else if flag == "--numstat" {
+            opciones.formato = FormatoDiff::Numstat;
+        } else if let Some(n) = flag.strip_prefix("--unified=").or(flag.strip_prefix("-U")) {
+            opciones.contexto = match n.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) => return Err(GitrError::InvalidArgumentError(flags.join(" "), usage)),
+            };
+        } else if flag.starts_with('-') {
+            return Err(GitrError::InvalidArgumentError(flags.join(" "), usage));
+        } else {
+            revisiones.push(flag.clone());
         }
-        to_rebase_commits.push(commit);
     }
+    let repo = open_current_repo(cliente)?;
+    let res = match (cached, revisiones.as_slice()) {
+        (false, []) => diff_worktree(&opciones, &repo)?,
+        (true, []) => diff_cached(&opciones, &repo)?,
+        (false, [viejo, nuevo]) => diff_commits(viejo, nuevo, &opciones, &repo)?,
+        _ => return Err(GitrError::InvalidArgumentError(flags.join(" "), usage)),
+    };
+    print!("{}", res);
+    Ok(())
+}
+
+/// Muestra un commit con su diff, una tag anotada, un tree o un blob
+pub fn show(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    // show
+    // show <commit | branch | tag | objeto>
+    if flags.len() > 1 {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "show [<revision>]".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente)?;
+    let revision = flags.first().map(|r| r.as_str()).unwrap_or("HEAD");
+    let res = show_(revision, &OpcionesDiff::default(), &repo)?;
+    print!("{}", res);
+    Ok(())
+}
+
+/// Imprime el hash al que resuelve cada revision (HEAD~2, master^2, v1^{tree}, hashes cortos...)
+pub fn rev_parse(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    if flags.is_empty() {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "rev-parse <revision>...".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente)?;
+    for revision in flags {
+        println!("{}", rev_parse::rev_parse(&revision, &repo)?);
+    }
+    Ok(())
+}
+
+/// Imprime el mejor ancestro comun de dos commits, o todos con --all (merges cruzados)
+pub fn merge_base(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    let todas = flags.first().is_some_and(|flag| flag == "--all");
+    let revisiones = if todas { &flags[1..] } else { &flags[..] };
+    if revisiones.len() != 2 || revisiones.iter().any(|r| r.starts_with('-')) {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "merge-base [--all] <commit> <commit>".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente)?;
+    let res_output = command_utils::get_merge_base(&revisiones[0], &revisiones[1], todas, &repo)?;
+    print!("{}", res_output);
+    Ok(())
+}
+
+/// Verifica la integridad de los objetos y las refs del repositorio
+pub fn fsck(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    if !flags.is_empty() {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "fsck".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente)?;
+    let report = fsck_(&repo)?;
+    for corrupto in &report.corrupt {
+        println!("error: {}", corrupto);
+    }
+    for referencia in &report.bad_refs {
+        println!("error: {}", referencia);
+    }
+    for faltante in &report.missing {
+        println!("missing {}", faltante);
+    }
+    for colgado in &report.dangling {
+        println!("dangling {}", colgado);
+    }
+    if !report.is_ok() {
+        return Err(GitrError::FsckError(
+            report.checked,
+            report.corrupt.len() + report.bad_refs.len() + report.missing.len(),
+        ));
+    }
+    println!("fsck: {} objetos verificados, sin errores", report.checked);
+    Ok(())
+}
+
+/// Junta los objetos alcanzables en un pack y borra los sueltos inalcanzables viejos
+pub fn gc(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    // gc
+    // gc --prune=now
+    // gc --prune=<dias>
+    // gc --no-prune
+    let usage = "gc [--prune=<now/dias> | --no-prune]".to_string();
+    if flags.len() > 1 {
+        return Err(GitrError::InvalidArgumentError(flags.join(" "), usage));
+    }
+    let prune = match flags.first().map(|f| f.as_str()) {
+        None => Some(GC_PRUNE_GRACE),
+        Some("--no-prune") => None,
+        Some("--prune=now") => Some(Duration::ZERO),
+        Some(flag) => match flag.strip_prefix("--prune=").map(|d| d.parse::<u64>()) {
+            Some(Ok(dias)) => Some(Duration::from_secs(dias * 24 * 60 * 60)),
+            _ => return Err(GitrError::InvalidArgumentError(flags.join(" "), usage)),
+        },
+    };
+    let repo = open_current_repo(cliente)?;
+    print_gc_stats(gc_(&repo, prune)?);
+    Ok(())
+}
+
+/// Junta los objetos alcanzables en un pack, sin borrar objetos inalcanzables
+pub fn repack(flags: Vec<String>, cliente: String) -> Result<(), GitrError> {
+    if !flags.is_empty() {
+        return Err(GitrError::InvalidArgumentError(
+            flags.join(" "),
+            "repack".to_string(),
+        ));
+    }
+    let repo = open_current_repo(cliente)?;
+    print_gc_stats(gc_(&repo, None)?);
     Ok(())
 }
 
+fn print_gc_stats(stats: GcStats) {
+    println!("Objetos sueltos: {} -> {}", stats.loose_before, stats.loose_after);
+    println!(
+        "Packs: {} -> {} ({} objetos empaquetados)",
+        stats.packs_before, stats.packs_after, stats.packed
+    );
+    println!("Objetos inalcanzables eliminados: {}", stats.pruned);
+}
+
 pub fn check_ignore(paths: Vec<String>, client: String) -> Result<(), GitrError> {
     if paths.is_empty() {
         return Err(GitrError::InvalidArgumentError(
@@ -580,12 +987,12 @@ pub fn check_ignore(paths: Vec<String>, client: String) -> Result<(), GitrError>
             "check-ignore <path>".to_string(),
         ));
     }
-    let repo = file_manager::get_current_repo(client.clone())?;
+    let repo = open_current_repo(client)?;
     let mut full_paths = vec![];
     for linea in paths {
-        full_paths.push(repo.to_owned() + linea.as_str());
+        full_paths.push(repo.work_tree().to_owned() + linea.as_str());
     }
-    match command_utils::check_ignore_(full_paths, client) {
+    match command_utils::check_ignore_(full_paths, &repo) {
         Ok(_) => Ok(()),
         Err(e) => Err(e),
     }
@@ -6,14 +6,9 @@ pub fn parse_input(input: String) -> Vec<String> {
 }
 
 /// ["command", "flag1", "flag2", ...]
-pub fn command_handler(
-    argv: Vec<String>,
-    hubo_conflict: bool,
-    branch_hash: String,
-    client: String,
-) -> Result<(bool, String), GitrError> {
+pub fn command_handler(argv: Vec<String>, client: String) -> Result<(), GitrError> {
     if argv.is_empty() {
-        return Ok((false, "".to_string()));
+        return Ok(());
     }
 
     let command = argv[0].clone();
@@ -31,31 +26,22 @@ pub fn command_handler(
         "cat-file" | "c" => commands_fn::cat_file(flags, client)?,
         "init" => commands_fn::init(flags, client)?,
         "status" => commands_fn::status(flags, client)?,
-        "add" => {
-            commands_fn::add(flags, client)?;
-            return Ok((hubo_conflict, branch_hash));
-        }
+        "add" => commands_fn::add(flags, client)?,
         "rm" => commands_fn::rm(flags, client)?,
-        "commit" => {
-            if hubo_conflict {
-                commands_fn::commit(flags, branch_hash.clone(), client)?;
-                return Ok((false, "".to_string()));
-            } else {
-                commands_fn::commit(flags, "None".to_string(), client)?;
-            }
-        }
+        "reset" => commands_fn::reset(flags, client)?,
+        "commit" => commands_fn::commit(flags, "None".to_string(), client)?,
         "checkout" => commands_fn::checkout(flags, client)?,
+        "restore" => commands_fn::restore(flags, client)?,
         "log" => commands_fn::log(flags, client)?,
         "clone" => commands_fn::clone(flags, client)?,
         "fetch" => commands_fn::fetch(flags, client)?,
         "merge" => {
-            let (hubo_conflict_res, branch_hash_res, _) = commands_fn::merge(flags, client)?;
+            let (hubo_conflict_res, _, _) = commands_fn::merge(flags, client)?;
             if hubo_conflict_res {
                 println!(
                     "\x1b[33mHubo un conflicto, por favor resuelvalo antes de continuar\x1b[0m"
                 );
             }
-            return Ok((true, branch_hash_res));
         }
         "remote" => commands_fn::remote(flags, client)?,
         "pull" => commands_fn::pull(flags, client)?,
@@ -67,7 +53,14 @@ pub fn command_handler(
         "ls-tree" => commands_fn::ls_tree(flags, client)?,
         "rebase" => commands_fn::rebase(flags, client)?,
         "check-ignore" => commands_fn::check_ignore(flags, client)?,
-        "q" => return Ok((false, "".to_string())),
+        "diff" => commands_fn::diff(flags, client)?,
+        "show" => commands_fn::show(flags, client)?,
+        "rev-parse" => commands_fn::rev_parse(flags, client)?,
+        "merge-base" => commands_fn::merge_base(flags, client)?,
+        "gc" => commands_fn::gc(flags, client)?,
+        "fsck" => commands_fn::fsck(flags, client)?,
+        "repack" => commands_fn::repack(flags, client)?,
+        "q" => return Ok(()),
         "l" => logger::log(flags)?,
         "list-repos" | "lr" => commands_fn::list_repos(client),
         "go-to-repo" | "gtr" => commands_fn::go_to_repo(flags, client)?,
@@ -82,5 +75,5 @@ pub fn command_handler(
         }
     }
 
-    Ok((false, "".to_string()))
+    Ok(())
 }
@@ -1,4 +1,5 @@
-use std::cmp::max;
+use std::collections::HashSet;
+
 #[derive(Clone, Debug)]
 pub struct Diff {
     pub lineas_eliminadas: Vec<(usize, String)>,
@@ -7,14 +8,6 @@ pub struct Diff {
     pub lineas_extra: usize,
 }
 
-#[derive(Clone, Debug)]
-struct Celda {
-    valor: usize,
-    es_match: bool,
-    fila: usize,
-    columna: usize,
-}
-
 fn empty_diff() -> Diff {
     Diff {
         lineas_eliminadas: vec![],
@@ -24,133 +17,267 @@ fn empty_diff() -> Diff {
     }
 }
 
-fn valor_match(matriz: &[Vec<Celda>], i: usize, j: usize) -> usize {
-    if i == 0 || j == 0 || (i, j) == (0, 0) {
-        1
-    } else {
-        matriz[i - 1][j - 1].valor + 1
-    }
+/*******************
+ *   MYERS
+ * *****************/
+
+// valor de los diagonales que todavia no se alcanzaron
+const INALCANZABLE: isize = isize::MIN / 2;
+// minimo de cambios que se buscan antes de cortar por el punto mas avanzado (como xdiff)
+const COSTO_MINIMO: isize = 256;
+
+/// Lineas eliminadas de base y agregadas en new (indices ascendentes) segun el
+/// algoritmo de Myers en espacio lineal: O((N+M)·D) en tiempo y O(N+M) en memoria,
+/// con D la cantidad de lineas eliminadas y agregadas. Para archivos muy distintos la
+/// busqueda se corta a los sqrt(N+M) cambios (minimo 256) y el diff puede no ser minimo.
+pub fn myers(base: &[&str], new: &[&str]) -> (Vec<usize>, Vec<usize>) {
+    let mut eliminadas = vec![false; base.len()];
+    let mut agregadas = vec![false; new.len()];
+    comparar(base, new, (0, 0), &mut eliminadas, &mut agregadas);
+    deslizar(base, &mut eliminadas);
+    deslizar(new, &mut agregadas);
+    alinear(base, &mut eliminadas, &agregadas);
+    alinear(new, &mut agregadas, &eliminadas);
+    let marcadas = |v: Vec<bool>| {
+        v.iter()
+            .enumerate()
+            .filter(|(_, marcada)| **marcada)
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>()
+    };
+    (marcadas(eliminadas), marcadas(agregadas))
 }
 
-fn valor_unmatch(matriz: &[Vec<Celda>], i: usize, j: usize) -> usize {
-    if i == 0 && j == 0 {
-        0
-    } else if i == 0 {
-        return matriz[i][j - 1].valor;
-    } else if j == 0 {
-        return matriz[i - 1][j].valor;
-    } else {
-        return max(matriz[i - 1][j].valor, matriz[i][j - 1].valor);
+// divide y conquista: se saca el prefijo y el sufijo comun, se busca la snake del
+// medio del camino optimo y se resuelven por separado las dos mitades que quedan
+fn comparar(
+    mut a: &[&str],
+    mut b: &[&str],
+    (mut a0, mut b0): (usize, usize),
+    eliminadas: &mut [bool],
+    agregadas: &mut [bool],
+) {
+    while !a.is_empty() && !b.is_empty() && a[0] == b[0] {
+        a = &a[1..];
+        b = &b[1..];
+        a0 += 1;
+        b0 += 1;
+    }
+    while !a.is_empty() && !b.is_empty() && a[a.len() - 1] == b[b.len() - 1] {
+        a = &a[..a.len() - 1];
+        b = &b[..b.len() - 1];
+    }
+    if a.is_empty() {
+        agregadas[b0..b0 + b.len()].iter_mut().for_each(|l| *l = true);
+        return;
+    }
+    if b.is_empty() {
+        eliminadas[a0..a0 + a.len()].iter_mut().for_each(|l| *l = true);
+        return;
     }
+    let ((x1, y1), (x2, y2)) = snake_del_medio(a, b);
+    comparar(&a[..x1], &b[..y1], (a0, b0), eliminadas, agregadas);
+    comparar(&a[x2..], &b[y2..], (a0 + x2, b0 + y2), eliminadas, agregadas);
 }
 
-fn get_diff(
-    matriz_coincidencias: Vec<Vec<Celda>>,
-    len_columna: usize,
-    len_fila: usize,
-) -> (Vec<usize>, Vec<usize>) {
-    let mut stack = Vec::new();
-
-    let mut j = len_columna;
-    let mut i = len_fila;
-
-    let mut num_bloque_actual = matriz_coincidencias[j][i].valor;
-    loop {
-        if i == 0 && j == 0 {
-            if matriz_coincidencias[j][i].es_match {
-                stack.push(matriz_coincidencias[j][i].clone());
-            }
-            break;
+// Entre lineas repetidas hay varios diffs minimos posibles. Como git, cada grupo de
+// lineas marcadas se corre hacia abajo mientras la primera linea del grupo sea igual a
+// la siguiente sin marcar, asi los cambios quedan despues de las lineas comunes
+fn deslizar(lineas: &[&str], marcadas: &mut [bool]) {
+    let mut inicio = 0;
+    while inicio < lineas.len() {
+        if !marcadas[inicio] {
+            inicio += 1;
+            continue;
         }
-
-        if matriz_coincidencias[j][i].es_match {
-            stack.push(matriz_coincidencias[j][i].clone());
-            num_bloque_actual -= 1;
-            i = i.saturating_sub(1);
-            j = j.saturating_sub(1);
-        } else {
-            if j == 0 && matriz_coincidencias[j][i - 1].valor == num_bloque_actual {
-                i = i.saturating_sub(1);
-                j = j.saturating_sub(1);
-                continue;
-            }
-
-            if i == 0 && matriz_coincidencias[j - 1][i].valor == num_bloque_actual {
-                i = i.saturating_sub(1);
-                j = j.saturating_sub(1);
-                continue;
+        let mut fin = inicio;
+        while fin < lineas.len() && marcadas[fin] {
+            fin += 1;
+        }
+        while fin < lineas.len() && lineas[inicio] == lineas[fin] {
+            marcadas[inicio] = false;
+            marcadas[fin] = true;
+            inicio += 1;
+            fin += 1;
+            // si se junta con el grupo siguiente se siguen corriendo los dos
+            while fin < lineas.len() && marcadas[fin] {
+                fin += 1;
             }
+        }
+        inicio = fin;
+    }
+}
 
-            if matriz_coincidencias[j - 1][i - 1].valor == num_bloque_actual {
-                i = i.saturating_sub(1);
-                j = j.saturating_sub(1);
-                continue;
-            } else {
-                let mut k = j;
-                let mut la_encontre_yendo_arriba = false;
-
-                loop {
-                    if matriz_coincidencias[k][i].es_match {
-                        la_encontre_yendo_arriba = true;
-                        j = k;
-
-                        stack.push(matriz_coincidencias[j][i].clone());
-                        i = i.saturating_sub(1);
-                        j = j.saturating_sub(1);
-
-                        num_bloque_actual -= 1;
-
-                        break;
-                    }
+// Una linea marcada se puede mover a otra igual sin marcar si entre las dos solo hay
+// lineas marcadas (las lineas comunes no cambian). Si asi llega al mismo indice que un
+// cambio del otro archivo se mueve, para que quede como un reemplazo linea por linea
+fn alinear(lineas: &[&str], marcadas: &mut [bool], otras: &[bool]) {
+    // grupo de lineas marcadas donde esta x: [inicio, fin)
+    let (mut inicio, mut fin) = (0, 0);
+    for x in 0..lineas.len() {
+        if !marcadas[x] {
+            inicio = x + 1;
+            continue;
+        }
+        while fin < lineas.len() && (fin <= x || marcadas[fin]) {
+            fin += 1;
+        }
+        if otras.get(x) == Some(&true) {
+            continue;
+        }
+        let abajo = destino_alineado(lineas, marcadas, otras, x, fin..lineas.len());
+        let arriba = destino_alineado(lineas, marcadas, otras, x, (0..inicio).rev());
+        let destino = match (abajo, arriba) {
+            (Some(abajo), Some(arriba)) if x - arriba < abajo - x => Some(arriba),
+            (Some(abajo), _) => Some(abajo),
+            (None, arriba) => arriba,
+        };
+        if let Some(destino) = destino {
+            marcadas[x] = false;
+            marcadas[destino] = true;
+            inicio = x + 1;
+        }
+    }
+}
 
-                    if matriz_coincidencias[k - 1][i].valor != num_bloque_actual {
-                        break;
-                    }
+// recorre las posiciones en orden saltando las marcadas, mientras las lineas sin
+// marcar sean iguales a la de x, hasta encontrar una alineada con el otro archivo
+fn destino_alineado(
+    lineas: &[&str],
+    marcadas: &[bool],
+    otras: &[bool],
+    x: usize,
+    posiciones: impl Iterator<Item = usize>,
+) -> Option<usize> {
+    for y in posiciones {
+        if marcadas[y] {
+            continue;
+        }
+        if lineas[y] != lineas[x] {
+            return None;
+        }
+        if otras.get(y) == Some(&true) {
+            return Some(y);
+        }
+    }
+    None
+}
 
-                    k = k.saturating_sub(1);
-                }
-                if !la_encontre_yendo_arriba {
-                    let mut k = i;
-
-                    loop {
-                        if matriz_coincidencias[j][k].es_match {
-                            i = k;
-                            stack.push(matriz_coincidencias[j][i].clone());
-                            i = i.saturating_sub(1);
-                            j = j.saturating_sub(1);
-
-                            num_bloque_actual -= 1;
-
-                            break;
-                        }
-
-                        if matriz_coincidencias[j][k - 1].valor != num_bloque_actual {
-                            break;
-                        }
-                        k = k.saturating_sub(1);
-                    }
-                }
+// Busca a la vez desde el principio y desde el final (sobre a y b invertidos) hasta que
+// los caminos se cruzan en un mismo diagonal k = x - y. Devuelve el inicio y el fin
+// de la snake (tramo de lineas iguales) donde se cruzaron.
+fn snake_del_medio(a: &[&str], b: &[&str]) -> ((usize, usize), (usize, usize)) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let impar = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let off = max + 1;
+    let mut vf = vec![INALCANZABLE; (2 * max + 3) as usize];
+    let mut vb = vf.clone();
+    let iguales_f = |x: isize, y: isize| a[x as usize] == b[y as usize];
+    let iguales_b = |x: isize, y: isize| a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize];
+    let limite = COSTO_MINIMO.max(((n + m) as f64).sqrt() as isize);
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let (xi, xf) = match avanzar(&mut vf, off, k, d, (n, m), iguales_f) {
+                Some(snake) => snake,
+                None => continue,
+            };
+            // el diagonal k hacia adelante es el delta - k hacia atras
+            let kb = delta - k;
+            if impar
+                && kb.abs() < d
+                && vb[(off + kb) as usize] != INALCANZABLE
+                && xf + vb[(off + kb) as usize] >= n
+            {
+                return punto((xi, xi - k), (xf, xf - k));
             }
         }
+        for kb in (-d..=d).step_by(2) {
+            let (xi, xf) = match avanzar(&mut vb, off, kb, d, (n, m), iguales_b) {
+                Some(snake) => snake,
+                None => continue,
+            };
+            let k = delta - kb;
+            if !impar
+                && k.abs() <= d
+                && vf[(off + k) as usize] != INALCANZABLE
+                && vf[(off + k) as usize] + xf >= n
+            {
+                return punto((n - xf, m - (xf - kb)), (n - xi, m - (xi - kb)));
+            }
+        }
+        if d >= limite {
+            return corte_por_costo(&vf, off, d, (n, m));
+        }
     }
-    let base_numbers = stack.iter().map(|x| x.fila).collect::<Vec<usize>>();
-    let new_numbers = stack.iter().map(|x| x.columna).collect::<Vec<usize>>();
+    // no deberia pasar: con d = max los caminos siempre se cruzan.
+    // Si pasa, se toma todo a como eliminado y todo b como agregado
+    punto((n, 0), (n, 0))
+}
 
-    let mut lineas_eliminadas = Vec::new();
-    for i in 0..(len_columna + 1) {
-        if !base_numbers.contains(&i) {
-            lineas_eliminadas.push(i);
+// Corta en el punto hacia adelante que mas avanzo (mayor x + y). Las dos mitades
+// quedan mas chicas que el problema original, asi que la recursion termina igual
+fn corte_por_costo(
+    vf: &[isize],
+    off: isize,
+    d: isize,
+    (n, m): (isize, isize),
+) -> ((usize, usize), (usize, usize)) {
+    let mut mejor = (0, 0);
+    for k in (-d..=d).step_by(2) {
+        let x = vf[(off + k) as usize];
+        if x != INALCANZABLE && 2 * x - k > mejor.0 + mejor.1 {
+            mejor = (x, x - k);
         }
     }
+    if mejor.0 + mejor.1 == 0 || mejor == (n, m) {
+        return punto((n, 0), (n, 0));
+    }
+    punto(mejor, mejor)
+}
+
+fn punto(inicio: (isize, isize), fin: (isize, isize)) -> ((usize, usize), (usize, usize)) {
+    (
+        (inicio.0 as usize, inicio.1 as usize),
+        (fin.0 as usize, fin.1 as usize),
+    )
+}
 
-    let mut lineas_agregadas = Vec::new();
-    for i in 0..(len_fila + 1) {
-        if !new_numbers.contains(&i) {
-            lineas_agregadas.push(i);
+// Extiende el camino del diagonal k con d cambios: el punto mas lejano entre bajar desde
+// k + 1 o avanzar desde k - 1 (sin salirse de la grilla) y despues la snake de lineas
+// iguales. Devuelve (x al inicio de la snake, x al final)
+fn avanzar(
+    v: &mut [isize],
+    off: isize,
+    k: isize,
+    d: isize,
+    (n, m): (isize, isize),
+    iguales: impl Fn(isize, isize) -> bool,
+) -> Option<(isize, isize)> {
+    let valido = |x: isize| x >= 0 && x <= n && x - k >= 0 && x - k <= m;
+    let mut x = if d == 0 {
+        0
+    } else {
+        let abajo = if k < d { v[(off + k + 1) as usize] } else { INALCANZABLE };
+        let derecha = if k > -d { v[(off + k - 1) as usize] + 1 } else { INALCANZABLE };
+        match (valido(abajo), valido(derecha)) {
+            (true, true) => abajo.max(derecha),
+            (true, false) => abajo,
+            (false, true) => derecha,
+            (false, false) => INALCANZABLE,
         }
+    };
+    if !valido(x) {
+        v[(off + k) as usize] = INALCANZABLE;
+        return None;
     }
-
-    (lineas_eliminadas, lineas_agregadas)
+    let inicio = x;
+    while x < n && x - k < m && iguales(x, x - k) {
+        x += 1;
+    }
+    v[(off + k) as usize] = x;
+    Some((inicio, x))
 }
 
 impl Diff {
@@ -165,7 +292,7 @@ impl Diff {
                 lineas: vec![],
                 lineas_extra: 0,
             };
-            let new_lines = new.split('\n').collect::<Vec<&str>>();
+            let new_lines = new.lines().collect::<Vec<&str>>();
             for (i, line) in new_lines.iter().enumerate() {
                 only_add_diff.lineas.push((i, true, line.to_string()));
             }
@@ -174,67 +301,54 @@ impl Diff {
         let base_lines = base.lines().collect::<Vec<&str>>();
         let new_lines = new.lines().collect::<Vec<&str>>();
 
-        let mut matriz_coincidencias: Vec<Vec<Celda>> = vec![vec![]];
-
-        for (i, base_line) in base_lines.iter().enumerate() {
-            for (j, new_line) in new_lines.iter().enumerate() {
-                if base_line == new_line {
-                    let next_value = valor_match(&matriz_coincidencias, i, j);
-                    matriz_coincidencias[i].push(Celda {
-                        valor: next_value,
-                        es_match: true,
-                        //valor_matcheado: base_line.to_string(),
-                        fila: i,
-                        columna: j,
-                    });
-                } else {
-                    let next_value = valor_unmatch(&matriz_coincidencias, i, j);
-                    matriz_coincidencias[i].push(Celda {
-                        valor: next_value,
-                        es_match: false,
-                        fila: i,
-                        columna: j,
-                    });
-                }
-            }
-
-            matriz_coincidencias.push(vec![]);
-        }
+        let (indices_lineas_eliminadas, indices_lineas_agregadas) = myers(&base_lines, &new_lines);
 
-        let (indices_lineas_eliminadas, indices_lineas_agregadas) = get_diff(
-            matriz_coincidencias,
-            base_lines.len() - 1,
-            new_lines.len() - 1,
-        );
-
-        let mut lineas_eliminadas = Vec::new();
-        let mut lineas_agregadas = Vec::new();
+        let lineas_eliminadas = indices_lineas_eliminadas
+            .iter()
+            .map(|i| (*i, base_lines[*i].to_string()))
+            .collect::<Vec<(usize, String)>>();
+        let lineas_agregadas = indices_lineas_agregadas
+            .iter()
+            .map(|i| (*i, new_lines[*i].to_string()))
+            .collect::<Vec<(usize, String)>>();
 
         let mut lineas = Vec::new();
-
-        for (i, line) in base_lines.iter().enumerate() {
-            if indices_lineas_eliminadas.contains(&i) {
-                lineas.push((i, false, line.to_string()));
-            }
+        for (i, line) in lineas_eliminadas.iter() {
+            lineas.push((*i, false, line.clone()));
         }
-        for (i, line) in new_lines.iter().enumerate() {
-            if indices_lineas_agregadas.contains(&i) {
-                lineas.push((i, true, line.to_string()));
-            }
+        for (i, line) in lineas_agregadas.iter() {
+            lineas.push((*i, true, line.clone()));
         }
         lineas.sort_by(|a, b| a.0.cmp(&b.0)); //ordeno ascendente
 
-        for (i, line) in base_lines.iter().enumerate() {
-            if indices_lineas_eliminadas.contains(&i) {
-                lineas_eliminadas.push((i, line.to_string()));
-            }
-        }
-        for (i, line) in new_lines.iter().enumerate() {
-            if indices_lineas_agregadas.contains(&i) {
-                lineas_agregadas.push((i, line.to_string()));
-            }
+        Diff {
+            lineas_eliminadas,
+            lineas_agregadas,
+            lineas,
+            lineas_extra: 0,
         }
+    }
 
+    /// Diff en modo palabras: en vez de lineas compara los tokens de tokenizar, y los
+    /// indices de lineas_eliminadas/lineas_agregadas/lineas son indices de tokens
+    pub fn new_por_palabras(base: &str, new: &str) -> Diff {
+        let base_tokens = tokenizar(base);
+        let new_tokens = tokenizar(new);
+        let (eliminados, agregados) = myers(&base_tokens, &new_tokens);
+        let lineas_eliminadas = eliminados
+            .iter()
+            .map(|i| (*i, base_tokens[*i].to_string()))
+            .collect::<Vec<(usize, String)>>();
+        let lineas_agregadas = agregados
+            .iter()
+            .map(|i| (*i, new_tokens[*i].to_string()))
+            .collect::<Vec<(usize, String)>>();
+        let mut lineas = lineas_eliminadas
+            .iter()
+            .map(|(i, token)| (*i, false, token.clone()))
+            .chain(lineas_agregadas.iter().map(|(i, token)| (*i, true, token.clone())))
+            .collect::<Vec<(usize, bool, String)>>();
+        lineas.sort_by_key(|linea| linea.0);
         Diff {
             lineas_eliminadas,
             lineas_agregadas,
@@ -263,6 +377,254 @@ impl Diff {
     }
 }
 
+/// Una linea del script de edicion que transforma base en new
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operacion {
+    Igual(String),
+    Eliminada(String),
+    Agregada(String),
+}
+
+impl Operacion {
+    fn usa_base(&self) -> bool {
+        !matches!(self, Operacion::Agregada(_))
+    }
+
+    fn usa_new(&self) -> bool {
+        !matches!(self, Operacion::Eliminada(_))
+    }
+}
+
+/// Diff de lineas entre dos textos cualesquiera (Diff::new no admite que alguno este
+/// vacio). Si solo cambia el salto de linea final, la ultima linea se cuenta como
+/// eliminada y vuelta a agregar, igual que en git.
+pub fn diff_de_lineas(base: &str, new: &str) -> Diff {
+    let base_lines = base.lines().collect::<Vec<&str>>();
+    let new_lines = new.lines().collect::<Vec<&str>>();
+    let mut diff = if base_lines.is_empty() || new_lines.is_empty() {
+        Diff {
+            lineas_eliminadas: base_lines.iter().map(|l| l.to_string()).enumerate().collect(),
+            lineas_agregadas: new_lines.iter().map(|l| l.to_string()).enumerate().collect(),
+            ..empty_diff()
+        }
+    } else {
+        Diff::new(base.to_string(), new.to_string())
+    };
+    if base.ends_with('\n') != new.ends_with('\n') && !base_lines.is_empty() && !new_lines.is_empty() {
+        let (i, j) = (base_lines.len() - 1, new_lines.len() - 1);
+        if !diff.has_delete_diff(i) && !diff.has_add_diff(j).0 {
+            diff.lineas_eliminadas.push((i, base_lines[i].to_string()));
+            diff.lineas_agregadas.push((j, new_lines[j].to_string()));
+        }
+    }
+    diff
+}
+
+/// Arma el script de edicion linea por linea (comunes, eliminadas y agregadas, en orden)
+/// a partir de las lineas que marca diff_de_lineas
+pub fn script_de_edicion(base: &str, new: &str) -> Vec<Operacion> {
+    let base_lines = base.lines().collect::<Vec<&str>>();
+    let new_lines = new.lines().collect::<Vec<&str>>();
+    armar_script(&base_lines, &new_lines, &diff_de_lineas(base, new))
+}
+
+/// Script de edicion palabra por palabra (ver tokenizar), del mismo diff que las lineas
+pub fn script_por_palabras(base: &str, new: &str) -> Vec<Operacion> {
+    let base_tokens = tokenizar(base);
+    let new_tokens = tokenizar(new);
+    let diff = Diff::new_por_palabras(base, new);
+    armar_script(&base_tokens, &new_tokens, &diff)
+}
+
+// recorre base y new en orden intercalando lo que Diff marco como eliminado o agregado
+fn armar_script(base: &[&str], new: &[&str], diff: &Diff) -> Vec<Operacion> {
+    let eliminadas: HashSet<usize> = diff.lineas_eliminadas.iter().map(|l| l.0).collect();
+    let agregadas: HashSet<usize> = diff.lineas_agregadas.iter().map(|l| l.0).collect();
+    let mut script = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < base.len() || j < new.len() {
+        if i < base.len() && (eliminadas.contains(&i) || j >= new.len()) {
+            script.push(Operacion::Eliminada(base[i].to_string()));
+            i += 1;
+        } else if j < new.len() && (agregadas.contains(&j) || i >= base.len()) {
+            script.push(Operacion::Agregada(new[j].to_string()));
+            j += 1;
+        } else if base[i] == new[j] {
+            script.push(Operacion::Igual(base[i].to_string()));
+            i += 1;
+            j += 1;
+        } else {
+            // Diff no las marco pero no coinciden: se reemplaza una por otra
+            script.push(Operacion::Eliminada(base[i].to_string()));
+            script.push(Operacion::Agregada(new[j].to_string()));
+            i += 1;
+            j += 1;
+        }
+    }
+    script
+}
+
+/// Diff en formato unificado (solo los hunks, sin los headers de archivo), con
+/// `contexto` lineas sin cambios alrededor de cada cambio. Vacio si no hay cambios.
+pub fn unified_diff(base: &str, new: &str, contexto: usize) -> String {
+    let script = script_de_edicion(base, new);
+    let base_total = script.iter().filter(|op| op.usa_base()).count();
+    let new_total = script.iter().filter(|op| op.usa_new()).count();
+    let base_sin_salto = !base.is_empty() && !base.ends_with('\n');
+    let new_sin_salto = !new.is_empty() && !new.ends_with('\n');
+
+    let mut res = String::new();
+    for (inicio, fin) in rangos_hunks(&script, contexto) {
+        res.push_str(&header_hunk(&script, inicio, fin));
+        let mut base_leidas = script[..inicio].iter().filter(|op| op.usa_base()).count();
+        let mut new_leidas = script[..inicio].iter().filter(|op| op.usa_new()).count();
+        for op in &script[inicio..fin] {
+            let (prefijo, linea) = match op {
+                Operacion::Igual(linea) => (' ', linea),
+                Operacion::Eliminada(linea) => ('-', linea),
+                Operacion::Agregada(linea) => ('+', linea),
+            };
+            res.push_str(&format!("{}{}\n", prefijo, linea));
+            base_leidas += op.usa_base() as usize;
+            new_leidas += op.usa_new() as usize;
+            let ultima_base = op.usa_base() && base_leidas == base_total && base_sin_salto;
+            let ultima_new = op.usa_new() && new_leidas == new_total && new_sin_salto;
+            if ultima_base || ultima_new {
+                res.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+    res
+}
+
+/// Diff por palabras como `git diff --word-diff`: mismos hunks que unified_diff, pero
+/// las lineas cambiadas se muestran una sola vez con lo eliminado entre [-...-]
+/// y lo agregado entre {+...+}
+pub fn word_diff(base: &str, new: &str, contexto: usize) -> String {
+    let script = script_de_edicion(base, new);
+    let mut res = String::new();
+    for (inicio, fin) in rangos_hunks(&script, contexto) {
+        res.push_str(&header_hunk(&script, inicio, fin));
+        let mut k = inicio;
+        while k < fin {
+            if let Operacion::Igual(linea) = &script[k] {
+                res.push_str(&format!("{}\n", linea));
+                k += 1;
+                continue;
+            }
+            // bloque de lineas cambiadas: se comparan palabra por palabra
+            let (mut viejo, mut nuevo) = (String::new(), String::new());
+            while k < fin {
+                match &script[k] {
+                    Operacion::Eliminada(linea) => viejo.push_str(&format!("{}\n", linea)),
+                    Operacion::Agregada(linea) => nuevo.push_str(&format!("{}\n", linea)),
+                    Operacion::Igual(_) => break,
+                }
+                k += 1;
+            }
+            res.push_str(&marcar_palabras(&viejo, &nuevo));
+        }
+    }
+    res
+}
+
+// texto con las palabras eliminadas y agregadas marcadas; las marcas no cruzan saltos de linea
+fn marcar_palabras(viejo: &str, nuevo: &str) -> String {
+    let mut res = String::new();
+    let (mut eliminado, mut agregado) = (String::new(), String::new());
+    for op in script_por_palabras(viejo, nuevo) {
+        match op {
+            Operacion::Eliminada(token) => eliminado.push_str(&token),
+            Operacion::Agregada(token) => agregado.push_str(&token),
+            Operacion::Igual(token) => {
+                res.push_str(&marcas(&eliminado, &agregado));
+                eliminado.clear();
+                agregado.clear();
+                res.push_str(&token);
+            }
+        }
+    }
+    res.push_str(&marcas(&eliminado, &agregado));
+    res
+}
+
+fn marcas(eliminado: &str, agregado: &str) -> String {
+    let envolver = |texto: &str, abre: &str, cierra: &str| {
+        texto
+            .split('\n')
+            .map(|parte| match parte.is_empty() {
+                true => String::new(),
+                false => format!("{}{}{}", abre, parte, cierra),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    envolver(eliminado, "[-", "-]") + &envolver(agregado, "{+", "+}")
+}
+
+/// Separa un texto en palabras (tramos sin espacios), tramos de espacios y saltos
+/// de linea, de forma que concatenar los tokens devuelve el texto original
+pub fn tokenizar(texto: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut inicio = 0;
+    let mut anterior: Option<u8> = None;
+    for (i, c) in texto.char_indices() {
+        let clase = match c {
+            '\n' => 0,
+            c if c.is_whitespace() => 1,
+            _ => 2,
+        };
+        if anterior.is_some() && (anterior != Some(clase) || clase == 0) {
+            tokens.push(&texto[inicio..i]);
+            inicio = i;
+        }
+        anterior = Some(clase);
+    }
+    if inicio < texto.len() {
+        tokens.push(&texto[inicio..]);
+    }
+    tokens
+}
+
+// rangos [inicio, fin) del script que entran en cada hunk, con `contexto` lineas
+// comunes alrededor de cada cambio (los hunks que se tocan se juntan)
+fn rangos_hunks(script: &[Operacion], contexto: usize) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (cambio, op) in script.iter().enumerate() {
+        if matches!(op, Operacion::Igual(_)) {
+            continue;
+        }
+        let inicio = cambio.saturating_sub(contexto);
+        let fin = (cambio + 1 + contexto).min(script.len());
+        match hunks.last_mut() {
+            Some(hunk) if inicio <= hunk.1 => hunk.1 = fin,
+            _ => hunks.push((inicio, fin)),
+        }
+    }
+    hunks
+}
+
+fn header_hunk(script: &[Operacion], inicio: usize, fin: usize) -> String {
+    let base_antes = script[..inicio].iter().filter(|op| op.usa_base()).count();
+    let new_antes = script[..inicio].iter().filter(|op| op.usa_new()).count();
+    let base_largo = script[inicio..fin].iter().filter(|op| op.usa_base()).count();
+    let new_largo = script[inicio..fin].iter().filter(|op| op.usa_new()).count();
+    format!(
+        "@@ -{} +{} @@\n",
+        rango_hunk(base_antes, base_largo),
+        rango_hunk(new_antes, new_largo)
+    )
+}
+
+// "inicio,largo" de un hunk, numerado desde 1. Como git, omite el largo si es 1
+fn rango_hunk(antes: usize, largo: usize) -> String {
+    match largo {
+        0 => format!("{},0", antes),
+        1 => format!("{}", antes + 1),
+        _ => format!("{},{}", antes + 1, largo),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,4 +636,109 @@ mod tests {
         let diff = Diff::new(base, new);
         assert!(diff.lineas_eliminadas.is_empty());
     }
+
+    #[test]
+    fn test01_unified_diff_con_contexto() {
+        let base = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\n";
+        assert_eq!(
+            unified_diff(base, new, 1),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -8 +8,2 @@\n h\n+i\n"
+        );
+        // con mas contexto los dos cambios quedan en un mismo hunk
+        assert_eq!(unified_diff(base, new, 3).matches("@@ -").count(), 1);
+        assert_eq!(unified_diff(base, base, 3), "");
+    }
+
+    #[test]
+    fn test02_unified_diff_archivo_nuevo_y_sin_salto_final() {
+        assert_eq!(unified_diff("", "hola\nchau\n", 3), "@@ -0,0 +1,2 @@\n+hola\n+chau\n");
+        assert_eq!(unified_diff("hola\n", "", 3), "@@ -1 +0,0 @@\n-hola\n");
+        assert_eq!(
+            unified_diff("hola\n", "hola", 3),
+            "@@ -1 +1 @@\n-hola\n+hola\n\\ No newline at end of file\n"
+        );
+    }
+
+    // largo de la subsecuencia comun mas larga, con la matriz completa
+    fn lcs(a: &[&str], b: &[&str]) -> usize {
+        let mut matriz = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                matriz[i + 1][j + 1] = if a[i] == b[j] {
+                    matriz[i][j] + 1
+                } else {
+                    matriz[i][j + 1].max(matriz[i + 1][j])
+                };
+            }
+        }
+        matriz[a.len()][b.len()]
+    }
+
+    #[test]
+    fn test03_myers_es_minimo_y_consistente() {
+        let letras = ["a", "b", "c", "d"];
+        let mut semilla: u64 = 7;
+        let mut siguiente = |max: u64| {
+            semilla = semilla.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((semilla >> 33) % max) as usize
+        };
+        for _ in 0..300 {
+            let a = (0..siguiente(12)).map(|_| letras[siguiente(4)]).collect::<Vec<&str>>();
+            let b = (0..siguiente(12)).map(|_| letras[siguiente(4)]).collect::<Vec<&str>>();
+            let (eliminadas, agregadas) = myers(&a, &b);
+            assert_eq!(
+                eliminadas.len() + agregadas.len(),
+                a.len() + b.len() - 2 * lcs(&a, &b),
+                "{:?} {:?}",
+                a,
+                b
+            );
+            let comunes_a = (0..a.len()).filter(|i| !eliminadas.contains(i)).map(|i| a[i]);
+            let comunes_b = (0..b.len()).filter(|j| !agregadas.contains(j)).map(|j| b[j]);
+            assert!(comunes_a.eq(comunes_b));
+        }
+    }
+
+    #[test]
+    fn test04_diff_archivo_vaciado() {
+        let diff = Diff::new("uno\ndos\n".to_string(), "".to_string());
+        assert_eq!(diff.lineas_eliminadas.len(), 2);
+        assert!(diff.lineas_agregadas.is_empty());
+    }
+
+    #[test]
+    fn test05_myers_cortado_por_costo_sigue_siendo_consistente() {
+        let lineas = (0..2000).map(|i| format!("linea {}", i)).collect::<Vec<String>>();
+        let a = lineas.iter().map(|l| l.as_str()).collect::<Vec<&str>>();
+        let mut b = a[1000..].to_vec();
+        b.extend_from_slice(&a[..1000]);
+        let (eliminadas, agregadas) = myers(&a, &b);
+        assert_eq!(eliminadas.len(), agregadas.len());
+        assert!(eliminadas.len() >= 1000);
+        let comunes_a = (0..a.len()).filter(|i| !eliminadas.contains(i)).map(|i| a[i]);
+        let comunes_b = (0..b.len()).filter(|j| !agregadas.contains(j)).map(|j| b[j]);
+        assert!(comunes_a.eq(comunes_b));
+    }
+
+    #[test]
+    fn test06_tokenizar_y_diff_por_palabras() {
+        assert_eq!(
+            tokenizar("hola  mundo,\n\tchau"),
+            vec!["hola", "  ", "mundo,", "\n", "\t", "chau"]
+        );
+        let diff = Diff::new_por_palabras("el perro come", "el gato come");
+        assert_eq!(diff.lineas_eliminadas, vec![(2, "perro".to_string())]);
+        assert_eq!(diff.lineas_agregadas, vec![(2, "gato".to_string())]);
+    }
+
+    #[test]
+    fn test07_word_diff_marca_palabras_dentro_de_la_linea() {
+        let base = "titulo\nel perro come carne\nfin\n";
+        let new = "titulo\nel gato come mucha carne\nfin\nnueva linea\n";
+        assert_eq!(
+            word_diff(base, new, 1),
+            "@@ -1,3 +1,4 @@\ntitulo\nel [-perro-]{+gato+} come {+mucha +}carne\nfin\n{+nueva linea+}\n"
+        );
+    }
 }
@@ -1,12 +1,11 @@
-use std::collections::HashMap;
-
-use crate::commands::command_utils::flate2compress;
 use crate::gitr_errors::GitrError;
+use crate::index::{self, Index};
 use crate::objects::pull_request::PullRequest;
+use crate::repository::{Config, Repository};
 use crate::{file_manager, logger};
 use std::fs;
-use std::fs::{File, OpenOptions, ReadDir};
-use std::io::{prelude::*, Bytes};
+use std::fs::{File, OpenOptions};
+use std::io::prelude::*;
 use std::path::Path;
 
 use chrono::{FixedOffset, TimeZone, Utc};
@@ -32,13 +31,107 @@ pub fn read_file(path: String) -> Result<String, GitrError> {
     }
 }
 
+/// Reads a file and returns the content as raw bytes, without assuming any encoding
+/// On Error returns a FileReadError
+pub fn read_file_bytes(path: String) -> Result<Vec<u8>, GitrError> {
+    let log_msg = format!("reading data from: {}", path);
+    logger::log_file_operation(log_msg)?;
+    match fs::read(path.clone()) {
+        Ok(data) => Ok(data),
+        Err(_) => {
+            logger::log_error(format!("No se pudo leer: {}", path))?;
+            Err(GitrError::FileReadError(path))
+        }
+    }
+}
+
+/// Reads the content that gets stored in the blob of a work tree file:
+/// the file bytes, or the target path if the file is a symlink
+/// On Error returns a FileReadError
+pub fn read_blob_content(path: String) -> Result<Vec<u8>, GitrError> {
+    let is_symlink = match fs::symlink_metadata(&path) {
+        Ok(metadata) => metadata.file_type().is_symlink(),
+        Err(_) => false,
+    };
+    if !is_symlink {
+        return read_file_bytes(path);
+    }
+    match fs::read_link(&path) {
+        Ok(target) => Ok(target.to_string_lossy().as_bytes().to_vec()),
+        Err(_) => Err(GitrError::FileReadError(path)),
+    }
+}
+
+/// Writes the content of a blob in the work tree with the given tree mode:
+/// 120000 creates a symlink to the content, 100755 an executable file and
+/// anything else a regular file. Whatever was at path before is replaced.
+pub fn write_blob_content(path: String, data: &[u8], mode: u32) -> Result<(), GitrError> {
+    if let Ok(metadata) = fs::symlink_metadata(&path) {
+        if metadata.file_type().is_symlink() && fs::remove_file(&path).is_err() {
+            return Err(GitrError::FileWriteError(path));
+        }
+    }
+    if mode == index::SYMLINK_MODE {
+        return create_symlink(path, data);
+    }
+    write_file_bytes(path.clone(), data)?;
+    set_executable(path, mode == index::EXECUTABLE_FILE_MODE)
+}
+
+#[cfg(unix)]
+fn create_symlink(path: String, target: &[u8]) -> Result<(), GitrError> {
+    use std::os::unix::ffi::OsStrExt;
+    let target = std::ffi::OsStr::from_bytes(target);
+    let _ = fs::remove_file(&path);
+    match std::os::unix::fs::symlink(target, &path) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(GitrError::FileCreationError(path)),
+    }
+}
+
+// sin symlinks se deja un archivo con el path destino, como git con core.symlinks=false
+#[cfg(not(unix))]
+fn create_symlink(path: String, target: &[u8]) -> Result<(), GitrError> {
+    write_file_bytes(path, target)
+}
+
+#[cfg(unix)]
+fn set_executable(path: String, executable: bool) -> Result<(), GitrError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = match fs::metadata(&path) {
+        Ok(metadata) => metadata.permissions(),
+        Err(_) => return Err(GitrError::FileReadError(path)),
+    };
+    let mode = permissions.mode();
+    // los bits de ejecucion se copian de los de lectura, como hace git
+    let new_mode = if executable {
+        mode | ((mode & 0o444) >> 2)
+    } else {
+        mode & !0o111
+    };
+    if new_mode == mode {
+        return Ok(());
+    }
+    permissions.set_mode(new_mode);
+    match fs::set_permissions(&path, permissions) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(GitrError::FileWriteError(path)),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: String, _executable: bool) -> Result<(), GitrError> {
+    Ok(())
+}
+
 //receives a path of a repo and returns a vector of paths with all files outside gitr (only paths to files, not dirs)
+//symlinks are returned as files, even if they point to a directory
 pub fn visit_dirs(dir: &Path) -> Vec<String> {
     let mut files = Vec::new();
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.is_dir() {
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
                 if path.ends_with("gitr") {
                     continue;
                 }
@@ -66,6 +159,20 @@ pub fn write_file(path: String, text: String) -> Result<(), GitrError> {
     }
 }
 
+// Writes a file with the given bytes, byte-for-byte
+pub fn write_file_bytes(path: String, data: &[u8]) -> Result<(), GitrError> {
+    let log_msg = format!("writing data to: {}", path);
+    logger::log_file_operation(log_msg)?;
+    let mut archivo = match File::create(&path) {
+        Ok(archivo) => archivo,
+        Err(_) => return Err(GitrError::FileCreationError(path)),
+    };
+    match archivo.write_all(data) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(GitrError::FileWriteError(path)),
+    }
+}
+
 //Append text to a file (used in logger)
 pub fn append_to_file(path: String, text: String) -> Result<(), GitrError> {
     let mut file = match OpenOptions::new().write(true).append(true).open(&path) {
@@ -90,17 +197,16 @@ pub fn create_directory(path: &String) -> Result<(), GitrError> {
 }
 
 //delete all files without gitr
-pub fn delete_all_files(cliente: String) -> Result<(), GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    let _ = fs::remove_file(repo.clone() + "/gitr/index");
-    let path = Path::new(&repo);
+pub fn delete_all_files(repo: &Repository) -> Result<(), GitrError> {
+    let _ = fs::remove_file(repo.git_path("index"));
+    let path = Path::new(repo.work_tree());
     if let Ok(entries) = fs::read_dir(path) {
         for entry in entries.flatten() {
             if entry.file_name() != "gitr"
                 && entry.file_name() != ".git"
                 && entry.file_name() != "gitrignore"
             {
-                if entry.path().is_file() {
+                if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
                     match fs::remove_file(entry.path()) {
                         Ok(_) => continue,
                         Err(_) => {
@@ -124,28 +230,6 @@ pub fn delete_all_files(cliente: String) -> Result<(), GitrError> {
     Ok(())
 }
 
-//updates index and adds files from three-way merge that don't have conflicts
-pub fn add_new_files_from_merge(
-    origin_hashmap: HashMap<String, String>,
-    branch_hashmap: HashMap<String, String>,
-    cliente: String,
-) -> Result<(), GitrError> {
-    for (path, hash) in branch_hashmap.iter() {
-        if !origin_hashmap.contains_key(path) {
-            file_manager::add_to_index(path, hash, cliente.clone())?;
-            if let Some(parent) = std::path::Path::new(&path).parent() {
-                match fs::create_dir_all(parent) {
-                    Ok(_) => (),
-                    Err(_) => return Err(GitrError::FileWriteError(parent.display().to_string())),
-                };
-            };
-            let raw_data = read_file_data_from_blob_hash(hash.to_string(), cliente.clone())?;
-            write_file(path.to_string(), raw_data)?;
-        }
-    }
-    Ok(())
-}
-
 /***************************
  ***************************
  *      GIT OBJECTS
@@ -158,84 +242,47 @@ pub fn add_new_files_from_merge(
 
 // ***reading***
 
-//receives a path to a compressed object and return its raw data in UTF8 format
-fn read_compressed_file(path: &str) -> Result<Vec<u8>, GitrError> {
-    let log_msg = format!("reading data from: {}", path);
-    logger::log_file_operation(log_msg)?;
-    let file = match File::open(path) {
-        Ok(file) => file,
-        Err(_) => return Err(GitrError::FileReadError(path.to_string())),
-    };
-    let mut decoder = ZlibDecoder::new(file);
-    let mut buffer = Vec::new();
-    match decoder.read_to_end(&mut buffer) {
-        Ok(_) => Ok(buffer.clone()),
-        Err(_) => {
-            println!("error");
-            Err(GitrError::FileReadError(path.to_string()))
-        }
-    }
+//reads an object and returns its decompressed bytes (header included), untouched
+pub fn read_object_bytes(object: &String, repo: &Repository) -> Result<Vec<u8>, GitrError> {
+    repo.objects().read(object)
 }
 
-//reads and object and returns raw data
-pub fn read_object(object: &String, path: String, add_gitr: bool) -> Result<String, GitrError> {
-    let path = parse_object_hash(object, path, add_gitr)?;
-    let bytes = deflate_file(path.clone())?;
-    let object_data: Vec<u8> = get_object_data_with_bytes(bytes)?;
-    let first_byte = object_data[0];
-    if first_byte as char == 't' {
+//reads and object and returns raw data (trees are rendered as "mode name\0hexhash" lines)
+pub fn read_object(object: &String, repo: &Repository) -> Result<String, GitrError> {
+    let object_data = read_object_bytes(object, repo)?;
+    if object_data.starts_with(b"tree") {
         let tree_data = match read_tree_file(object_data) {
             Ok(data) => data,
-            Err(_) => return Err(GitrError::FileReadError(path)),
+            Err(_) => return Err(GitrError::FileReadError(object.to_string())),
         };
         return Ok(tree_data);
     }
-    if first_byte as char == 'b' || first_byte as char == 'c' {
-        let mut buffer = String::new();
-        for byte in object_data {
-            buffer.push(byte as char);
-        }
-        return Ok(buffer);
+    let first_byte = object_data[0];
+    if first_byte == b'b' || first_byte == b'c' || first_byte == b't' {
+        return Ok(String::from_utf8_lossy(&object_data).to_string());
     }
     Err(GitrError::FileReadError(
         "No se pudo leer el objeto, bytes invalidos".to_string(),
     ))
 }
 
-// receives an blob hash and returns its raw data without header. Calls read_object() and parses. Error if not a blob.
-pub fn read_file_data_from_blob_hash(hash: String, cliente: String) -> Result<String, GitrError> {
-    let object_raw_data = read_object(
-        &hash,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
-    let (header, raw_data) = match object_raw_data.split_once('\0') {
-        Some((header, raw_data)) => (header, raw_data),
+// receives an blob hash and returns its raw bytes without header. Error if not a blob.
+pub fn read_file_data_from_blob_hash(hash: String, repo: &Repository) -> Result<Vec<u8>, GitrError> {
+    let object_raw_data = read_object_bytes(&hash, repo)?;
+    let header_len = match object_raw_data.iter().position(|b| *b == 0) {
+        Some(header_len) => header_len,
         None => {
             println!("Error: invalid object type");
             return Err(GitrError::FileReadError(hash));
         }
     };
 
-    if !header.starts_with("blob") {
+    if !object_raw_data.starts_with(b"blob ") {
         println!("Error: invalid object type");
         return Err(GitrError::FileReadError(hash));
     }
 
-    Ok(raw_data.to_string())
-}
-
-// auxiliar function of read_object().
-fn get_object_data_with_bytes(bytes: Bytes<ZlibDecoder<File>>) -> Result<Vec<u8>, GitrError> {
-    let mut object_data: Vec<u8> = Vec::new();
-    for byte in bytes {
-        let byte = match byte {
-            Ok(byte) => byte,
-            Err(_) => return Err(GitrError::CompressionError),
-        };
-        object_data.push(byte);
-    }
-    Ok(object_data)
+    Ok(object_raw_data[header_len + 1..].to_vec())
 }
 
 //auxiliar for read_object(). Receives raw data and returns a String with readable data.
@@ -297,173 +344,123 @@ pub fn write_compressed_data(path: &str, data: &[u8]) -> Result<(), GitrError> {
     }
 }
 
-pub fn get_remote(cliente: String) -> Result<String, GitrError> {
-    let repo = get_current_repo(cliente)?;
-    let path = repo + "/gitr/" + "remote";
-    let remote = read_file(path)?;
+pub fn get_remote(repo: &Repository) -> Result<String, GitrError> {
+    let remote = read_file(repo.git_path("remote"))?;
     Ok(remote)
 }
 
-///receive compressed raw data from a file with his hash and write it in the objects folder
-pub fn write_object(data: Vec<u8>, hashed_name: String, cliente: String) -> Result<(), GitrError> {
-    let log_msg = format!("writing object {}", hashed_name);
-    logger::log_file_operation(log_msg)?;
-
-    let folder_name = hashed_name[0..2].to_string();
-    let file_name = hashed_name[2..].to_string();
-    let repo = get_current_repo(cliente.clone())?;
-    let dir = repo + "/gitr/objects/";
-    let folder_dir = dir.clone() + &folder_name;
-
-    if fs::metadata(&folder_dir).is_err() {
-        create_directory(&folder_dir)?;
-    }
-    write_compressed_data(&(folder_dir.clone() + "/" + &file_name), &data)?;
-    Ok(())
-}
-
-// ***others***
-
-//receives a path to a file and returns the decompressed data of the file content.
-fn deflate_file(path: String) -> Result<Bytes<ZlibDecoder<File>>, GitrError> {
-    let file = match File::open(&path) {
-        Ok(file) => file,
-        Err(_) => return Err(GitrError::FileReadError(path.to_string())),
-    };
-    let decoder = ZlibDecoder::new(file);
-
-    let bytes = decoder.bytes();
-    Ok(bytes)
-}
-
-// Le das un hash de objeto, se fija si existe y te devuelve el path completo de ese object
-//podríamos recibir el path aca así es una funcion sola
-//además la funcion _w_path no necesita el gitr y el del cliente si
-fn parse_object_hash(object: &String, path: String, mut add_gitr: bool) -> Result<String, GitrError> {
-    if object.len() < 3 {
-        return Err(GitrError::ObjectNotFound(object.clone()));
-    }
-    let folder_name = object[0..2].to_string();
-    let file_name = object[2..].to_string();
-
-    let mut repo = path.clone();
-    if path.starts_with("server") {
-        add_gitr = false;
-    }
-    if add_gitr {
-        repo += "/gitr";
-    }
-    let dir = repo + "/objects/";
-    let folder_dir = dir.clone() + &folder_name;
-    let path = dir + &folder_name + "/" + &file_name;
-    if fs::metadata(folder_dir).is_err() {
-        return Err(GitrError::ObjectNotFound(object.clone()));
-    }
-    if fs::metadata(&path).is_err() {
-        return Err(GitrError::ObjectNotFound(object.clone()));
-    }
-    Ok(path)
-}
-
 /***************************
  ***************************
  *      GIT FILES
  **************************
  **************************/
 
-//creates all necessary folder for the repo
-pub fn init_repository(name: &String) -> Result<(), GitrError> {
-    create_directory(name)?;
-    create_directory(&(name.clone() + "/gitr"))?;
-    create_directory(&(name.clone() + "/gitr/objects"))?;
-    create_directory(&(name.clone() + "/gitr/refs"))?;
-    create_directory(&(name.clone() + "/gitr/refs/heads"))?;
-    create_directory(&(name.clone() + "/gitr/refs/remotes"))?;
-    create_directory(&(name.clone() + "/gitr/refs/tags"))?;
-    create_directory(&(name.clone() + "/gitr/refs/remotes/daemon"))?;
-    write_file(
-        name.clone() + "/gitr/HEAD",
-        "ref: refs/heads/master".to_string(),
-    )?;
-    write_file(name.clone() + "/gitr/remote", "".to_string())?;
-    write_file(name.clone() + "/gitrignore", "".to_string())?;
-    Ok(())
-}
-
+//returns the path of the client's current repo (cliente/<repo>)
 pub fn get_current_repo(cliente: String) -> Result<String, GitrError> {
-    if cliente.contains('/') {
-        return Ok(cliente );
-    }
     let current_repo = read_file(cliente.clone() + "/.head_repo")?;
     Ok(cliente + "/" + &current_repo)
 }
 
-pub fn read_index(cliente: String) -> Result<String, GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    let path = repo + "/gitr/index";
-    let data = match String::from_utf8(read_compressed_file(&path)?) {
-        Ok(data) => data,
-        Err(_) => return Err(GitrError::FileReadError(path)),
-    };
-    Ok(data)
+/// Opens the client's current repo, with the user config of the client (cliente/gitrconfig)
+/// On Error returns a FileReadError if the client has no current repo
+pub fn open_current_repo(cliente: String) -> Result<Repository, GitrError> {
+    let mut repo = Repository::open(&get_current_repo(cliente.clone())?)?;
+    let mut config = Config::load(&(cliente.clone() + "/gitrconfig")).unwrap_or_default();
+    if config.user_name.is_none() {
+        config.user_name = Some(cliente);
+    }
+    repo.set_config(config);
+    Ok(repo)
 }
 
-//receives a blob's path and hash, and adds it to the index file
-pub fn add_to_index(path: &String, hash: &String, cliente: String) -> Result<(), GitrError> {
-    let mut index;
-    let repo = get_current_repo(cliente.clone())?;
-    let new_blob = format!("100644 {} 0 {}", hash, path);
-    let dir = repo + "/gitr/index";
-    if fs::metadata(dir.clone()).is_err() {
-        let _ = write_file(dir.clone(), String::from(""));
-        index = new_blob;
-    } else {
-        index = read_index(cliente.clone())?;
-        let mut overwrited = false;
-        for line in index.clone().lines() {
-            let attributes = line.split(' ').collect::<Vec<&str>>();
-
-            if attributes[3] == path {
-                let log_msg = format!("adding {} to index", path);
-                logger::log_action(log_msg)?;
-                index = index.replace(line, &new_blob);
-                overwrited = true;
-                break;
-            }
-        }
-        if !overwrited {
-            index = index + "\n" + &new_blob;
-        }
+/// Reads the index (DIRC format) of the repo
+/// On Error returns a FileReadError if there is no index yet
+pub fn read_index(repo: &Repository) -> Result<Index, GitrError> {
+    let path = repo.git_path("index");
+    if !Path::new(&path).is_file() {
+        return Err(GitrError::FileReadError(path));
     }
-    let compressed_index = flate2compress(index)?;
-    write_compressed_data(dir.as_str(), compressed_index.as_slice())?;
-    Ok(())
+    let data = read_file_bytes(path)?;
+    Index::from_bytes(&data)
 }
 
-///returns the path of the head branch
-pub fn get_head(cliente: String) -> Result<String, GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    let mut path = repo + "/gitr/HEAD";
+/// Writes the index of the repo
+pub fn write_index(index: &Index, repo: &Repository) -> Result<(), GitrError> {
+    write_file_bytes(repo.git_path("index"), &index.to_bytes()?)
+}
 
-    if cliente.contains('/') {
-        path = path.replace("/gitr/", "/")
-    }
+//receives a blob's path (including the repo) and hash, and adds it to the index file
+pub fn add_to_index(path: &String, hash: &String, repo: &Repository) -> Result<(), GitrError> {
+    let mut index = if Path::new(&repo.git_path("index")).is_file() {
+        read_index(repo)?
+    } else {
+        Index::new()
+    };
+    let log_msg = format!("adding {} to index", path);
+    logger::log_action(log_msg)?;
+    index.add_file(path, repo.work_tree(), hash.to_string());
+    write_index(&index, repo)
+}
 
+///returns the path of the head branch, or "HEAD" if the head is detached (HEAD holds a commit hash)
+pub fn get_head(repo: &Repository) -> Result<String, GitrError> {
+    let path = repo.git_path("HEAD");
     if fs::metadata(path.clone()).is_err() {
         write_file(path.clone(), String::from("ref: refs/heads/master"))?;
         return Ok("None".to_string());
     }
     let head = read_file(path.clone())?;
-    let head = head.trim_end().to_string();
-    let head = head.split(' ').collect::<Vec<&str>>()[1];
-    Ok(head.to_string())
+    match head.trim_end().strip_prefix("ref: ") {
+        Some(head) => Ok(head.to_string()),
+        None => Ok("HEAD".to_string()),
+    }
+}
+
+///returns the name of the current branch, None if the head is detached
+pub fn get_current_branch(repo: &Repository) -> Result<Option<String>, GitrError> {
+    let head = get_head(repo)?;
+    Ok(head.strip_prefix("refs/heads/").map(|branch| branch.to_string()))
 }
 
 //receives the path of the new head, updates head file
-pub fn update_head(head: &String, cliente: String) -> Result<(), GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    let path = repo + "/gitr/HEAD";
-    write_file(path.clone(), format!("ref: {}", head))?;
+pub fn update_head(head: &String, repo: &Repository) -> Result<(), GitrError> {
+    write_file(repo.git_path("HEAD"), format!("ref: {}", head))?;
+    Ok(())
+}
+
+//receives a commit and leaves the head detached on it
+pub fn detach_head(commit: &String, repo: &Repository) -> Result<(), GitrError> {
+    write_file(repo.git_path("HEAD"), commit.to_string())
+}
+
+//saves the commit the head pointed to before a merge, reset or rebase
+pub fn write_orig_head(commit: &String, repo: &Repository) -> Result<(), GitrError> {
+    write_file(repo.git_path("ORIG_HEAD"), commit.to_string())
+}
+
+//saves the merge in progress: the commit being merged and the message for the merge commit
+pub fn write_merge_state(merge_head: &String, message: &String, repo: &Repository) -> Result<(), GitrError> {
+    write_file(repo.git_path("MERGE_HEAD"), merge_head.to_string())?;
+    write_file(repo.git_path("MERGE_MSG"), message.to_string())
+}
+
+//returns the commit being merged, None if there is no merge in progress
+pub fn read_merge_head(repo: &Repository) -> Result<Option<String>, GitrError> {
+    let path = repo.git_path("MERGE_HEAD");
+    if fs::metadata(&path).is_err() {
+        return Ok(None);
+    }
+    Ok(Some(read_file(path)?.trim_end().to_string()))
+}
+
+//ends the merge in progress, ORIG_HEAD is kept
+pub fn clear_merge_state(repo: &Repository) -> Result<(), GitrError> {
+    for file in ["MERGE_HEAD", "MERGE_MSG"] {
+        let path = repo.git_path(file);
+        if fs::metadata(&path).is_ok() && fs::remove_file(&path).is_err() {
+            return Err(GitrError::FileDeletionError("clear_merge_state".to_string()));
+        }
+    }
     Ok(())
 }
 
@@ -479,18 +476,16 @@ fn find_new_path(hash: String, sec_vec: Vec<(String, String)>) -> String {
 // recibe el vector de los hashes de las referencias que sacas del ref discovery, y actualiza el gitr en base a eso
 pub fn update_client_refs(
     hash_n_refs: Vec<(String, String)>,
-    r_path: String,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<(), GitrError> {
-    let path = r_path + "/gitr/";
     let sec_vec = hash_n_refs.clone();
     for (h, r) in hash_n_refs {
         if r.clone() == "HEAD" {
             let path_head = find_new_path(h.clone(), sec_vec.clone());
-            file_manager::update_head(&path_head.replace('\\', "/"), cliente.clone())?;
+            file_manager::update_head(&path_head.replace('\\', "/"), repo)?;
             continue;
         }
-        let path_ref = path.clone() + &r.replace('\\', "/"); //esto se borra?
+        let path_ref = repo.git_path(&r.replace('\\', "/")); //esto se borra?
         if let Ok(()) = file_manager::write_file(path_ref.clone(), h) {
             continue;
         }
@@ -500,14 +495,9 @@ pub fn update_client_refs(
 }
 
 //returns a vec with all branches paths in repo
-pub fn get_branches(cliente: String) -> Result<Vec<String>, GitrError> {
+pub fn get_branches(repo: &Repository) -> Result<Vec<String>, GitrError> {
     let mut branches: Vec<String> = Vec::new();
-    let repo = get_current_repo(cliente.clone())?;
-    let dir = if cliente.contains('/') {
-        repo + "/refs/heads"
-    } else { 
-        repo + "/gitr/refs/heads/"
-    };
+    let dir = repo.git_path("refs/heads");
     let paths = match fs::read_dir(dir.clone()) {
         Ok(paths) => paths,
         Err(_) => return Err(GitrError::FileReadError(dir)),
@@ -529,10 +519,9 @@ pub fn get_branches(cliente: String) -> Result<Vec<String>, GitrError> {
     }
     Ok(branches)
 }
-pub fn get_tags(cliente: String) -> Result<Vec<String>, GitrError> {
+pub fn get_tags(repo: &Repository) -> Result<Vec<String>, GitrError> {
     let mut branches: Vec<String> = Vec::new();
-    let repo = get_current_repo(cliente.clone())?;
-    let dir = repo + "/gitr/refs/tags";
+    let dir = repo.git_path("refs/tags");
     let paths = match fs::read_dir(dir.clone()) {
         Ok(paths) => paths,
         Err(_) => return Err(GitrError::FileReadError(dir)),
@@ -555,9 +544,8 @@ pub fn get_tags(cliente: String) -> Result<Vec<String>, GitrError> {
     Ok(branches)
 }
 
-pub fn delete_tag(tag: String, cliente: String) -> Result<String, GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    let path = format!("{}/gitr/refs/tags/{}", repo, tag);
+pub fn delete_tag(tag: String, repo: &Repository) -> Result<String, GitrError> {
+    let path = repo.git_path(&format!("refs/tags/{}", tag));
     let hash = match read_file(path.clone()) {
         Ok(hash) => hash,
         Err(_) => return Err(GitrError::TagNonExistsError(tag)),
@@ -572,15 +560,14 @@ pub fn delete_tag(tag: String, cliente: String) -> Result<String, GitrError> {
 }
 
 //delete a branch in folder refs/heads
-pub fn delete_branch(branch: String, moving: bool, cliente: String) -> Result<(), GitrError> {
-    let repo = get_current_repo(cliente.clone())?;
-    let path = format!("{}/gitr/refs/heads/{}", repo, branch);
-    let head = get_head(cliente.clone())?;
+pub fn delete_branch(branch: String, moving: bool, repo: &Repository) -> Result<(), GitrError> {
+    let path = repo.git_path(&format!("refs/heads/{}", branch));
+    let head = get_head(repo)?;
     if moving {
         let _ = fs::remove_file(path);
         return Ok(());
     }
-    let current_head = repo + "/gitr/" + &head;
+    let current_head = repo.git_path(&head);
     if current_head == path || head == "None" {
         return Err(GitrError::DeleteCurrentBranchError(branch));
     }
@@ -598,49 +585,25 @@ pub fn move_branch(old_branch: String, new_branch: String) -> Result<(), GitrErr
 }
 
 ///returns the current commit hash
-pub fn get_current_commit(cliente: String) -> Result<String, GitrError> {
-    let head_path = get_head(cliente.clone())?;
+pub fn get_current_commit(repo: &Repository) -> Result<String, GitrError> {
+    let head_path = get_head(repo)?;
     if head_path == "None" {
         return Err(GitrError::NoHead);
     }
-    let repo = get_current_repo(cliente.clone())?;
-    let mut path = repo + "/gitr/" + &head_path;
-
-    if cliente.contains('/') {
-        path = path.replace("/gitr/", "/")
-    }
-
-    let head = read_file(path)?;
+    let head = read_file(repo.git_path(&head_path))?;
     Ok(head)
 }
 
 //receives a branch and returns its commit hash
-pub fn get_commit(branch: String, cliente: String) -> Result<String, GitrError> {
-    let repo = match get_current_repo(cliente.clone()) {
-        Ok(repo) => repo,
-        Err(_) => cliente.clone() //si no hay repo, es porque es un server
-    };
-    let path = format!("{}/gitr/refs/heads/{}", repo, branch);
-
-    let commit = match read_file(path) {
-        Ok(commit) => commit,
-        Err(_) => {
-            let path_server = format!("{}/refs/heads/{}", repo, branch);
-            read_file(path_server)?
-        },
-    
-    };
+pub fn get_commit(branch: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_file(repo.git_path(&format!("refs/heads/{}", branch)))?;
     Ok(commit)
 }
 
 //receives a path and a hash and creates a tree
-pub fn create_tree(path: String, hash: String, cliente: String) -> Result<(), GitrError> {
+pub fn create_tree(path: String, hash: String, repo: &Repository) -> Result<(), GitrError> {
     file_manager::create_directory(&path)?;
-    let tree_raw_data = read_object(
-        &hash,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+    let tree_raw_data = read_object(&hash, repo)?;
     let raw_data = match tree_raw_data.split_once('\0') {
         Some((_, raw_data)) => raw_data,
         None => {
@@ -650,19 +613,15 @@ pub fn create_tree(path: String, hash: String, cliente: String) -> Result<(), Gi
     };
     for entry in raw_data.split('\n') {
         let object = entry.split(' ').collect::<Vec<&str>>()[0];
-        if object == "100644" {
-            let path_completo = path.clone() + "/" + &parse_blob_path(entry.to_string().clone());
-            let hash = parse_blob_hash(entry.to_string().clone());
-            create_blob(path_completo, hash, cliente.clone())?;
-        } else {
+        if object == "40000" {
             let _new_path_hash = entry.split(' ').collect::<Vec<&str>>()[1];
             let new_path = _new_path_hash.split('\0').collect::<Vec<&str>>()[0];
             let hash = _new_path_hash.split('\0').collect::<Vec<&str>>()[1];
-            create_tree(
-                path.clone() + "/" + new_path,
-                hash.to_string(),
-                cliente.clone(),
-            )?;
+            create_tree(path.clone() + "/" + new_path, hash.to_string(), repo)?;
+        } else {
+            let path_completo = path.clone() + "/" + &parse_blob_path(entry.to_string().clone());
+            let hash = parse_blob_hash(entry.to_string().clone());
+            create_blob(path_completo, hash, parse_blob_mode(object)?, repo)?;
         }
     }
     Ok(())
@@ -673,6 +632,13 @@ fn parse_blob_hash(blob_entry: String) -> String {
     let hash = _new_path_hash.split('\0').collect::<Vec<&str>>()[1];
     hash.to_string()
 }
+//auxiliar function for create_tree, parses the octal mode of a tree entry
+fn parse_blob_mode(mode: &str) -> Result<u32, GitrError> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) => Ok(mode),
+        Err(_) => Err(GitrError::FileReadError(format!("modo invalido: {}", mode))),
+    }
+}
 //auxiliar function for create_tree
 fn parse_blob_path(blob_entry: String) -> String {
     let _new_path_hash = blob_entry.split(' ').collect::<Vec<&str>>()[1];
@@ -680,28 +646,19 @@ fn parse_blob_path(blob_entry: String) -> String {
     new_path.to_string()
 }
 
-//receives a path and a hash and creates a blob
-pub fn create_blob(path: String, hash: String, cliente: String) -> Result<(), GitrError> {
-    let new_blob = read_object(
-        &(hash.to_string()),
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
-    let new_blob_only_data = new_blob.split('\0').collect::<Vec<&str>>()[1];
-    add_to_index(&path, &hash, cliente.clone())?;
-    write_file(path.to_string(), new_blob_only_data.to_string())?;
+//receives a path, a hash and the tree mode and creates a blob (regular file, executable or symlink)
+pub fn create_blob(path: String, hash: String, mode: u32, repo: &Repository) -> Result<(), GitrError> {
+    let new_blob_only_data = read_file_data_from_blob_hash(hash.clone(), repo)?;
+    write_blob_content(path.to_string(), &new_blob_only_data, mode)?;
+    add_to_index(&path, &hash, repo)?;
     Ok(())
 }
 
 //receives a commit and updates the repo with the content of the commit
-pub fn update_working_directory(commit: String, cliente: String) -> Result<(), GitrError> {
-    delete_all_files(cliente.clone())?;
-    let main_tree = get_main_tree(commit, cliente.clone())?;
-    let tree = read_object(
-        &main_tree,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn update_working_directory(commit: String, repo: &Repository) -> Result<(), GitrError> {
+    delete_all_files(repo)?;
+    let main_tree = get_main_tree(commit, repo)?;
+    let tree = read_object(&main_tree, repo)?;
     let raw_data = match tree.split_once('\0') {
         Some((_, raw_data)) => raw_data,
         None => {
@@ -709,31 +666,26 @@ pub fn update_working_directory(commit: String, cliente: String) -> Result<(), G
             return Ok(());
         }
     };
-    let repo = get_current_repo(cliente.clone())? + "/";
     for entry in raw_data.split('\n') {
         let object: &str = entry.split(' ').collect::<Vec<&str>>()[0];
         if object == "40000" {
             let _new_path_hash = entry.split(' ').collect::<Vec<&str>>()[1];
-            let new_path = repo.clone() + _new_path_hash.split('\0').collect::<Vec<&str>>()[0];
+            let new_path = repo.work_path(_new_path_hash.split('\0').collect::<Vec<&str>>()[0]);
             let hash = _new_path_hash.split('\0').collect::<Vec<&str>>()[1];
-            create_tree(new_path.to_string(), hash.to_string(), cliente.clone())?;
+            create_tree(new_path.to_string(), hash.to_string(), repo)?;
         } else {
-            let path_completo = repo.clone() + parse_blob_path(entry.to_string().clone()).as_str();
+            let path_completo = repo.work_path(&parse_blob_path(entry.to_string().clone()));
             let hash = parse_blob_hash(entry.to_string().clone());
 
-            create_blob(path_completo, hash, cliente.clone())?;
+            create_blob(path_completo, hash, parse_blob_mode(object)?, repo)?;
         }
     }
     Ok(())
 }
 
 //receives a commit and returns its main tree hash
-pub fn get_main_tree(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_main_tree(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
     let tree_base = commit[0].split('\0').collect::<Vec<&str>>()[1];
     let tree_hash_str = tree_base.split(' ').collect::<Vec<&str>>()[1];
@@ -741,13 +693,8 @@ pub fn get_main_tree(commit: String, cliente: String) -> Result<String, GitrErro
 }
 
 //receives a commit and returns its parent commit hash
-pub fn get_parent_commit(commit: String, cliente: String) -> Result<Vec<String>, GitrError> {
-    let add_gitr = !cliente.contains('/');
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        add_gitr,
-    )?;
+pub fn get_parent_commit(commit: String, repo: &Repository) -> Result<Vec<String>, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
     if commit[1].split(' ').collect::<Vec<&str>>()[0] != "parent" {
         return Ok(vec!["None".to_string()]);
@@ -763,12 +710,8 @@ pub fn get_parent_commit(commit: String, cliente: String) -> Result<Vec<String>,
 }
 
 //receives a commit and returns its commmiter mail
-pub fn get_commit_commiter_mail(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_commit_commiter_mail(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
 
     let mut idx = 3;
@@ -781,12 +724,8 @@ pub fn get_commit_commiter_mail(commit: String, cliente: String) -> Result<Strin
 }
 
 //receives a commit and returns its commmiter name
-pub fn get_commit_commiter(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_commit_commiter(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
 
     let mut idx = 3;
@@ -800,12 +739,8 @@ pub fn get_commit_commiter(commit: String, cliente: String) -> Result<String, Gi
 }
 
 //receives a commit and returns its author
-pub fn get_commit_author_mail(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_commit_author_mail(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
 
     let mut idx = 2;
@@ -817,12 +752,8 @@ pub fn get_commit_author_mail(commit: String, cliente: String) -> Result<String,
 }
 
 //receives a commit and returns its author name
-pub fn get_commit_author(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_commit_author(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
     let mut idx = 2;
     if commit[1].split(' ').collect::<Vec<&str>>()[0] != "parent" {
@@ -835,12 +766,8 @@ pub fn get_commit_author(commit: String, cliente: String) -> Result<String, Gitr
 }
 
 //receives a commit and returns its date
-pub fn get_commit_date(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_commit_date(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
     let mut idx = 2;
     if commit[1].split(' ').collect::<Vec<&str>>()[0] != "parent" {
@@ -849,6 +776,11 @@ pub fn get_commit_date(commit: String, cliente: String) -> Result<String, GitrEr
         idx += 1;
     }
     let timestamp = commit[idx].split(' ').collect::<Vec<&str>>()[3];
+    format_timestamp(timestamp)
+}
+
+//receives a unix timestamp and returns the date as shown by log
+pub fn format_timestamp(timestamp: &str) -> Result<String, GitrError> {
     let timestamp_parsed = match timestamp.parse::<i64>() {
         Ok(timestamp) => timestamp,
         Err(_) => return Err(GitrError::TimeError),
@@ -871,12 +803,8 @@ pub fn get_commit_date(commit: String, cliente: String) -> Result<String, GitrEr
 }
 
 //receives a commit and returns its message
-pub fn get_commit_message(commit: String, cliente: String) -> Result<String, GitrError> {
-    let commit = read_object(
-        &commit,
-        file_manager::get_current_repo(cliente.clone())?,
-        true,
-    )?;
+pub fn get_commit_message(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let commit = read_object(&commit, repo)?;
     let commit = commit.split('\n').collect::<Vec<&str>>();
     let mut idx = 5;
     if commit[1].split(' ').collect::<Vec<&str>>()[0] != "parent" {
@@ -894,10 +822,9 @@ pub fn update_current_repo(dir_name: &String, cliente: String) -> Result<(), Git
 }
 
 /// Devuelve vector con los ids de los commits en los heads activos
-pub fn get_refs_ids(carpeta: &str, cliente: String) -> Result<Vec<String>, GitrError> {
+pub fn get_refs_ids(carpeta: &str, repo: &Repository) -> Result<Vec<String>, GitrError> {
     let mut branches: Vec<String> = Vec::new();
-    let repo = get_current_repo(cliente.clone())?;
-    let dir = repo + "/gitr/refs/" + carpeta;
+    let dir = repo.git_path(&format!("refs/{}", carpeta));
     let paths = match fs::read_dir(dir.clone()) {
         Ok(paths) => paths,
         Err(_) => return Err(GitrError::FileReadError(dir)),
@@ -920,9 +847,9 @@ pub fn get_refs_ids(carpeta: &str, cliente: String) -> Result<Vec<String>, GitrE
 }
 
 //receives a quantity and returns that number of commits from logs
-pub fn commit_log(quantity: String, cliente: String) -> Result<String, GitrError> {
+pub fn commit_log(quantity: String, repo: &Repository) -> Result<String, GitrError> {
     let mut res: String = "".to_owned();
-    let mut current_commit = get_current_commit(cliente.clone())?;
+    let mut current_commit = get_current_commit(repo)?;
     let limit = match quantity.parse::<i32>() {
         Ok(quantity) => quantity,
         Err(_) => {
@@ -935,21 +862,8 @@ pub fn commit_log(quantity: String, cliente: String) -> Result<String, GitrError
     let mut counter = 0;
     loop {
         counter += 1;
-        let parents = get_parent_commit(current_commit.clone(), cliente.clone())?;
-        if parents.len() == 2 {
-            let parent_1 = parents[0].split_at(7).0;
-            let parent_2 = parents[1].split_at(7).0;
-            let format_merge = format!("Merge: {} {}\n", parent_1, parent_2);
-            res.push_str(&format_merge);
-        }
-        let format_commit = format!("commit: {}\n", current_commit);
-        res.push_str(&format_commit);
-        let date = get_commit_date(current_commit.clone(), cliente.clone())?;
-        let author = get_commit_author(current_commit.clone(), cliente.clone())?;
-        let message = get_commit_message(current_commit.clone(), cliente.clone())?;
-        res.push_str(&format!("Author: {}\n", author));
-        res.push_str(&format!("Date: {}\n", date));
-        res.push_str(&format!("\t{}\n\n", message));
+        let parents = get_parent_commit(current_commit.clone(), repo)?;
+        res.push_str(&commit_header(current_commit.clone(), repo)?);
         if parents[0] == "None" || counter == limit {
             break;
         }
@@ -958,6 +872,27 @@ pub fn commit_log(quantity: String, cliente: String) -> Result<String, GitrError
     Ok(res.to_string())
 }
 
+//receives a commit and returns its header as shown by log (merge parents, author, date and message)
+pub fn commit_header(commit: String, repo: &Repository) -> Result<String, GitrError> {
+    let mut res = String::new();
+    let parents = get_parent_commit(commit.clone(), repo)?;
+    if parents.len() == 2 {
+        let parent_1 = parents[0].split_at(7).0;
+        let parent_2 = parents[1].split_at(7).0;
+        let format_merge = format!("Merge: {} {}\n", parent_1, parent_2);
+        res.push_str(&format_merge);
+    }
+    let format_commit = format!("commit: {}\n", commit);
+    res.push_str(&format_commit);
+    let date = get_commit_date(commit.clone(), repo)?;
+    let author = get_commit_author(commit.clone(), repo)?;
+    let message = get_commit_message(commit.clone(), repo)?;
+    res.push_str(&format!("Author: {}\n", author));
+    res.push_str(&format!("Date: {}\n", date));
+    res.push_str(&format!("\t{}\n\n", message));
+    Ok(res)
+}
+
 //returns all repos
 pub fn get_repos(cliente: String) -> Vec<String> {
     let mut repos: Vec<String> = Vec::new();
@@ -993,80 +928,17 @@ pub fn remove_file(path: String) -> Result<(), GitrError> {
     }
 }
 
-pub fn get_all_objects_hashes(cliente: String) -> Result<Vec<String>, GitrError> {
-    let mut objects: Vec<String> = Vec::new();
-    let repo = get_current_repo(cliente.clone())?;
-    let dir: String = repo + "/gitr/objects";
-    let dir_reader = match fs::read_dir(dir.clone()) {
-        Ok(l) => l,
-        Err(_) => return Err(GitrError::FileReadError(dir)),
-    };
-    iterate_over_dirs_for_getting_objects_hashes(dir_reader, &mut objects, dir)?;
-    Ok(objects)
-}
-
-//Función auxiliar de get_all_objects_hashes
-fn iterate_over_dirs_for_getting_objects_hashes(
-    dir_reader: ReadDir,
-    objects: &mut Vec<String>,
-    dir: String,
-) -> Result<(), GitrError> {
-    for carpeta_rs in dir_reader {
-        let carpeta = match carpeta_rs {
-            Ok(path) => path,
-            Err(_) => return Err(GitrError::FileReadError(dir)),
-        };
-        let f = carpeta.file_name();
-        let dir_name = f.to_str().unwrap_or("Error");
-        if dir_name == "Error" {
-            return Err(GitrError::FileReadError(dir));
-        }
-        let file_reader = match fs::read_dir(dir.clone() + "/" + dir_name) {
-            Ok(l) => l,
-            Err(_) => return Err(GitrError::FileReadError(dir)),
-        };
-        for file in file_reader {
-            let file = match file {
-                Ok(path) => path,
-                Err(_) => return Err(GitrError::FileReadError(dir)),
-            };
-            let f = file.file_name();
-            let file_name = f.to_str().unwrap_or("Error");
-            if file_name == "Error" {
-                return Err(GitrError::FileReadError(dir));
-            }
-            let object = dir_name.to_string() + file_name;
-            objects.push(object);
-        }
-    }
-    Ok(())
+pub fn get_all_objects_hashes(repo: &Repository) -> Result<Vec<String>, GitrError> {
+    Ok(repo.objects().iter().collect())
 }
 
-pub fn get_object(id: String, r_path: String) -> Result<String, GitrError> {
-    let dir_path = format!("{}/objects/{}", r_path.clone(), id.split_at(2).0);
-    let mut archivo = match File::open(format!("{}/{}", dir_path, id.split_at(2).1)) {
-        Ok(archivo) => archivo,
-        Err(_) => return Err(GitrError::FileReadError(dir_path)),
-    };
-    let mut contenido: Vec<u8> = Vec::new();
-    if archivo.read_to_end(&mut contenido).is_err() {
-        return Err(GitrError::FileReadError(dir_path));
-    }
-    let descomprimido = String::from_utf8_lossy(&decode(&contenido)?).to_string();
+pub fn get_object(id: String, repo: &Repository) -> Result<String, GitrError> {
+    let descomprimido = String::from_utf8_lossy(&repo.objects().read(&id)?).to_string();
     Ok(descomprimido)
 }
 
-pub fn get_object_bytes(id: String, r_path: String) -> Result<Vec<u8>, GitrError> {
-    let dir_path = format!("{}/objects/{}", r_path.clone(), id.split_at(2).0);
-    let mut archivo = match File::open(format!("{}/{}", dir_path, id.split_at(2).1)) {
-        Ok(archivo) => archivo,
-        Err(_) => return Err(GitrError::FileReadError(dir_path)),
-    };
-    let mut contenido: Vec<u8> = Vec::new();
-    if archivo.read_to_end(&mut contenido).is_err() {
-        return Err(GitrError::FileReadError(dir_path));
-    }
-    decode(&contenido)
+pub fn get_object_bytes(id: String, repo: &Repository) -> Result<Vec<u8>, GitrError> {
+    repo.objects().read(&id)
 }
 
 pub fn decode(input: &[u8]) -> Result<Vec<u8>, GitrError> {
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::gitr_errors::GitrError;
 
 pub fn get_offset(data: &[u8]) -> Result<(usize, usize), GitrError> {
@@ -43,6 +45,13 @@ fn parse_copy_instruction(instruction: Vec<u8>) -> Result<(usize, usize, usize),
     Ok((ofs, size, j))
 }
 
+fn delta_fuera_de_rango() -> GitrError {
+    GitrError::PackFileError(
+        "transform_delta".to_string(),
+        "instruccion fuera de rango".to_string(),
+    )
+}
+
 pub fn transform_delta(data: &[u8], base: &[u8]) -> Result<Vec<u8>, GitrError> {
     let mut final_data: Vec<u8> = Vec::new();
     let mut i: usize = 1;
@@ -59,16 +68,175 @@ pub fn transform_delta(data: &[u8], base: &[u8]) -> Result<Vec<u8>, GitrError> {
         if byte & 0x80 == 0 {
             // empieza con 0 -> nueva data
             let size = (byte << 1 >> 1) as usize;
-            let new_data = &data[i + 1..i + 1 + size];
+            let new_data = match data.get(i + 1..i + 1 + size) {
+                Some(new_data) => new_data,
+                None => return Err(delta_fuera_de_rango()),
+            };
             final_data.extend(new_data);
             i += size + 1;
         } else {
             // empieza con 1 -> copiar de la base
             let (ofs, size, tamanio) = parse_copy_instruction(data[i..].to_vec())?;
-            let base_data = &base[ofs..ofs + size];
+            let size = if size == 0 { 0x10000 } else { size }; // size 0 significa 64KiB
+            let base_data = match base.get(ofs..ofs + size) {
+                Some(base_data) => base_data,
+                None => return Err(delta_fuera_de_rango()),
+            };
             final_data.extend(base_data);
             i += 1 + tamanio;
         }
     }
     Ok(final_data)
 }
+
+/// Lee el header de un delta: tamaño de la base y tamaño del resultado,
+/// cada uno en bloques de 7 bits (little-endian, el bit alto indica que sigue).
+/// Devuelve (tamaño base, tamaño resultado, bytes leidos)
+pub fn parse_delta_header(delta: &[u8]) -> Result<(usize, usize, usize), GitrError> {
+    let mut sizes = [0usize; 2];
+    let mut i = 0;
+    for size in sizes.iter_mut() {
+        let mut shift = 0;
+        loop {
+            let byte = match delta.get(i) {
+                Some(byte) => *byte,
+                None => {
+                    return Err(GitrError::PackFileError(
+                        "parse_delta_header".to_string(),
+                        "delta truncado".to_string(),
+                    ))
+                }
+            };
+            *size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            i += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+    Ok((sizes[0], sizes[1], i))
+}
+
+/// Aplica un delta completo (header incluido) sobre un objeto base con header ("blob 12\0...")
+/// y devuelve el objeto resultante, tambien con header y del mismo tipo que la base.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, GitrError> {
+    let (_, result_size, leidos) = parse_delta_header(delta)?;
+    let data = transform_delta(&delta[leidos..], base)?;
+    if data.len() != result_size {
+        return Err(GitrError::PackFileError(
+            "apply_delta".to_string(),
+            "el tamaño del objeto reconstruido no coincide".to_string(),
+        ));
+    }
+    let base_type = match base.iter().position(|b| *b == b' ') {
+        Some(pos) => &base[..pos],
+        None => {
+            return Err(GitrError::PackFileError(
+                "apply_delta".to_string(),
+                "objeto base sin header".to_string(),
+            ))
+        }
+    };
+    let mut object = [base_type, b" ", data.len().to_string().as_bytes(), b"\0"].concat();
+    object.extend(data);
+    Ok(object)
+}
+
+/*******************
+ *   CREACION DE DELTAS
+ * *****************/
+
+/// Tamaño de los bloques de la base que se indexan para buscar coincidencias
+const DELTA_BLOCK: usize = 16;
+/// Maximo de bytes que se copian en una sola instruccion
+const MAX_COPY: usize = 0xffff;
+/// Maximo de bytes nuevos en una sola instruccion de insercion
+const MAX_INSERT: usize = 0x7f;
+
+/// Escribe un tamaño en el formato del header de los deltas (bloques de 7 bits)
+fn encode_delta_size(mut size: usize, delta: &mut Vec<u8>) {
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            delta.push(byte);
+            return;
+        }
+        delta.push(byte | 0x80);
+    }
+}
+
+fn push_insert(data: &[u8], delta: &mut Vec<u8>) {
+    for chunk in data.chunks(MAX_INSERT) {
+        delta.push(chunk.len() as u8);
+        delta.extend(chunk);
+    }
+}
+
+fn push_copy(mut ofs: usize, mut size: usize, delta: &mut Vec<u8>) {
+    while size > 0 {
+        let chunk = size.min(MAX_COPY);
+        let mut instruction = vec![0x80u8];
+        for i in 0..4 {
+            let byte = ((ofs >> (8 * i)) & 0xff) as u8;
+            if byte != 0 {
+                instruction[0] |= 1 << i;
+                instruction.push(byte);
+            }
+        }
+        for i in 0..2 {
+            let byte = ((chunk >> (8 * i)) & 0xff) as u8;
+            if byte != 0 {
+                instruction[0] |= 0x10 << i;
+                instruction.push(byte);
+            }
+        }
+        delta.extend(instruction);
+        ofs += chunk;
+        size -= chunk;
+    }
+}
+
+/// Arma un delta (header incluido) que transforma base en target, ambos sin header
+/// de objeto. Usa las mismas instrucciones de copia/insercion que lee transform_delta:
+/// se indexan los bloques de la base y se copian las coincidencias de target.
+pub fn create_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut delta = vec![];
+    encode_delta_size(base.len(), &mut delta);
+    encode_delta_size(target.len(), &mut delta);
+
+    let mut bloques: HashMap<&[u8], usize> = HashMap::new();
+    for (i, bloque) in base.chunks_exact(DELTA_BLOCK).enumerate() {
+        bloques.entry(bloque).or_insert(i * DELTA_BLOCK);
+    }
+
+    let mut pendiente: Vec<u8> = vec![];
+    let mut j = 0;
+    while j < target.len() {
+        let coincidencia = target
+            .get(j..j + DELTA_BLOCK)
+            .and_then(|bloque| bloques.get(bloque));
+        match coincidencia {
+            Some(&ofs) => {
+                let mut largo = DELTA_BLOCK;
+                while ofs + largo < base.len()
+                    && j + largo < target.len()
+                    && base[ofs + largo] == target[j + largo]
+                {
+                    largo += 1;
+                }
+                push_insert(&pendiente, &mut delta);
+                pendiente.clear();
+                push_copy(ofs, largo, &mut delta);
+                j += largo;
+            }
+            None => {
+                pendiente.push(target[j]);
+                j += 1;
+            }
+        }
+    }
+    push_insert(&pendiente, &mut delta);
+    delta
+}
@@ -1,3 +1,4 @@
 pub mod deltas;
 pub mod pack_file;
+pub mod pack_index;
 pub mod ref_discovery;
@@ -7,32 +7,46 @@ use std::fmt::Write as FmtWrite;
 use crate::commands::command_utils::*;
 use crate::git_transport::{deltas::*, ref_discovery::*};
 use crate::gitr_errors::{self, GitrError};
+use crate::object_database::hash_object_data;
 use crate::objects::blob::Blob;
 use crate::objects::commit::Commit;
 use crate::objects::git_object::GitObject;
 use crate::objects::tag::Tag;
 use crate::objects::tree::Tree;
 use flate2::write::ZlibEncoder;
-use flate2::{Compression, Decompress};
+use flate2::{Compression, Decompress, FlushDecompress, Status};
 #[derive(Debug)]
 pub struct PackFile {
     _version: u32,
     pub objects: Vec<GitObject>,
 }
 
+/// Descomprime un objeto zlib del packfile, sin importar su tamaño.
+/// Devuelve los bytes descomprimidos y la cantidad de bytes comprimidos consumidos.
 pub fn decode(input: &[u8]) -> Result<(Vec<u8>, u64), GitrError> {
     let mut decoder = Decompress::new(true);
-    let mut output: [u8; 1024] = [0; 1024];
-    if decoder
-        .decompress(input, &mut output, flate2::FlushDecompress::Finish)
-        .is_err()
-    {
-        return Err(GitrError::CompressionError);
+    let mut output: Vec<u8> = Vec::with_capacity(1024);
+    loop {
+        let (antes_in, antes_out) = (decoder.total_in(), decoder.total_out());
+        let status = match decoder.decompress_vec(
+            &input[antes_in as usize..],
+            &mut output,
+            FlushDecompress::None,
+        ) {
+            Ok(status) => status,
+            Err(_) => return Err(GitrError::CompressionError),
+        };
+        if status == Status::StreamEnd {
+            break;
+        }
+        if output.len() == output.capacity() {
+            // se lleno el buffer, lo agrando y sigo descomprimiendo
+            output.reserve(output.capacity());
+        } else if decoder.total_in() == antes_in && decoder.total_out() == antes_out {
+            return Err(GitrError::CompressionError);
+        }
     }
-    let cant_leidos = decoder.total_in();
-    let output_return = output[..decoder.total_out() as usize].to_vec();
-
-    Ok((output_return, cant_leidos))
+    Ok((output, decoder.total_in()))
 }
 
 pub fn code(input: &[u8]) -> Result<Vec<u8>, GitrError> {
@@ -95,8 +109,7 @@ fn create_tag_object(decoded_data: &[u8]) -> Result<GitObject, GitrError> {
 }
 
 fn create_blob_object(decoded_data: &[u8]) -> Result<GitObject, GitrError> {
-    let data_str = String::from_utf8_lossy(decoded_data);
-    let blob = GitObject::Blob(Blob::new(data_str.to_string())?);
+    let blob = GitObject::Blob(Blob::new(decoded_data.to_vec())?);
 
     Ok(blob)
 }
@@ -218,6 +231,91 @@ fn delta_ref_from_packfile(
     ))
 }
 
+/// Entrada de un packfile tal como esta guardada, sin resolver los deltas
+#[derive(Debug)]
+pub enum PackEntry {
+    /// tipo (1 commit, 2 tree, 3 blob, 4 tag) y data descomprimida, sin header
+    Object(u8, Vec<u8>),
+    /// offset (absoluto dentro del pack) del objeto base y delta descomprimido
+    OfsDelta(usize, Vec<u8>),
+    /// hash del objeto base y delta descomprimido
+    RefDelta(String, Vec<u8>),
+}
+
+/// Lee la entrada del pack que empieza en offset.
+/// Devuelve la entrada y la cantidad de bytes que ocupa dentro del pack.
+pub fn read_pack_entry(pack: &[u8], offset: usize) -> Result<(PackEntry, usize), GitrError> {
+    if offset >= pack.len() {
+        return Err(GitrError::PackFileError(
+            "read_pack_entry".to_string(),
+            "offset fuera del packfile".to_string(),
+        ));
+    }
+    parse_pack_entry(&pack[offset..], offset)
+}
+
+/// Como read_pack_entry, pero con solo los bytes de la entrada (o los que le siguen):
+/// offset es donde empieza dentro del pack, para ubicar la base de un OFS_DELTA
+pub fn parse_pack_entry(entry: &[u8], offset: usize) -> Result<(PackEntry, usize), GitrError> {
+    let (object_type, _length, object_content, cursor) = parse_git_object(entry)?;
+    match object_type {
+        1..=4 => {
+            let (data, leidos) = decode(object_content)?;
+            Ok((PackEntry::Object(object_type, data), cursor + leidos as usize))
+        }
+        6 => {
+            let (ofs, c1) = get_offset(object_content)?;
+            if ofs == 0 || ofs > offset {
+                return Err(GitrError::PackFileError(
+                    "read_pack_entry".to_string(),
+                    "offset de la base invalido".to_string(),
+                ));
+            }
+            let (delta, leidos) = decode(&object_content[c1..])?;
+            Ok((PackEntry::OfsDelta(offset - ofs, delta), cursor + c1 + leidos as usize))
+        }
+        7 => {
+            if object_content.len() < 20 {
+                return Err(GitrError::PackFileError(
+                    "read_pack_entry".to_string(),
+                    "falta el hash de la base".to_string(),
+                ));
+            }
+            let base_hash = object_content[..20]
+                .iter()
+                .fold(String::new(),|mut output,b| {
+                    let _ =write!(output,"{b:02x}");
+                    output
+                });
+            let (delta, leidos) = decode(&object_content[20..])?;
+            Ok((PackEntry::RefDelta(base_hash, delta), cursor + 20 + leidos as usize))
+        }
+        _ => Err(GitrError::PackFileError(
+            "read_pack_entry".to_string(),
+            "Tipo de objeto no válido".to_string(),
+        )),
+    }
+}
+
+/// Arma el objeto con header ("blob 12\0...") a partir del tipo del pack y la data
+pub fn object_with_header(object_type: u8, data: &[u8]) -> Result<Vec<u8>, GitrError> {
+    let type_name = match object_type {
+        1 => "commit",
+        2 => "tree",
+        3 => "blob",
+        4 => "tag",
+        _ => {
+            return Err(GitrError::PackFileError(
+                "object_with_header".to_string(),
+                "Tipo de objeto no válido".to_string(),
+            ))
+        }
+    };
+    let mut object = format!("{} {}\0", type_name, data.len()).into_bytes();
+    object.extend(data);
+    Ok(object)
+}
+
 pub fn prepare_contents(datos: Vec<Vec<u8>>) -> Vec<(String, String, Vec<u8>)> {
     let mut contents: Vec<(String, String, Vec<u8>)> = Vec::new();
     for data in datos {
@@ -237,20 +335,61 @@ pub fn prepare_contents(datos: Vec<Vec<u8>>) -> Vec<(String, String, Vec<u8>)> {
     contents
 }
 
-/// Recibe vector de strings con los objetos a comprimir y devuelve un vector de bytes con el packfile
+/// Codifica el header de una entrada del pack: tipo y tamaño descomprimido
+fn encode_entry_header(obj_type: u8, obj_len: usize) -> Vec<u8> {
+    if obj_len < 16 {
+        return vec![(obj_type << 4) | obj_len as u8];
+    }
+    // ###### SIZE ENCODING ######
+    let mut size = obj_len;
+    let mut size_bytes: Vec<u8> = Vec::new();
+    size_bytes.push((obj_type << 4) | (size & 0x0F) as u8 | 0x80); // meto el tipo de objeto y los primeros 4 bits de la longitud
+    size >>= 4;
+    while size >= 128 {
+        size_bytes.push((size & 0x7F) as u8 | 0x80); // meto los siguientes 7 bits de la longitud con un 1 adelante
+        size >>= 7;
+    }
+    size_bytes.push(size as u8); // meto los últimos ultimos 7 bits de la longitud con un 0 adelante
+    size_bytes
+}
+
+/// Codifica la distancia a la base de un OFS_DELTA (inversa de get_offset)
+fn encode_ofs(mut ofs: usize) -> Vec<u8> {
+    let mut bytes = vec![(ofs & 0x7f) as u8];
+    ofs >>= 7;
+    while ofs > 0 {
+        ofs -= 1;
+        bytes.insert(0, 0x80 | (ofs & 0x7f) as u8);
+        ofs >>= 7;
+    }
+    bytes
+}
+
+/// Cantidad de objetos anteriores contra los que se prueba armar un delta
+const DELTA_WINDOW: usize = 10;
+/// Largo maximo de una cadena de deltas
+const MAX_DELTA_DEPTH: usize = 50;
+/// Los objetos mas chicos que esto se guardan siempre enteros
+const MIN_DELTA_SIZE: usize = 32;
+
+/// Objeto a escribir en el pack
+struct PackCandidate {
+    obj_type: u8,
+    /// nombre con el que aparece en algun tree del pack (vacio si no aparece)
+    name: String,
+    data: Vec<u8>,
+}
+
+/// Recibe vector de strings con los objetos a comprimir y devuelve un vector de bytes con el packfile.
+/// Los objetos parecidos a alguno de los anteriores se guardan como OFS_DELTA: se ordenan por
+/// tipo, nombre (el que tienen en los trees del pack, asi las versiones de un mismo archivo
+/// quedan juntas) y tamaño, y cada uno se compara contra los DELTA_WINDOW anteriores del mismo
+/// tipo. Se usa el delta solo si ocupa menos de la mitad del objeto.
 pub fn create_packfile(contents: Vec<(String, String, Vec<u8>)>) -> Result<Vec<u8>, GitrError> {
-    // ########## HEADER ##########
-    let mut final_data: Vec<u8> = Vec::new();
-    let header = "PACK".to_string();
-    final_data.extend(header.as_bytes());
-    let cant_bytes = contents.len().to_be_bytes();
-    let ver: u32 = 2;
-    final_data.extend(&ver.to_be_bytes());
-    final_data.extend(&cant_bytes[4..8]);
-    // ########## OBJECTS ##########
+    let mut objetos: Vec<PackCandidate> = Vec::new();
+    let mut hashes: Vec<String> = Vec::new();
     for (obj_type, len, raw_data) in contents {
-        let mut obj_data: Vec<u8> = Vec::new();
-        let obj_type: u8 = match obj_type.as_str() {
+        let obj_type_num: u8 = match obj_type.as_str() {
             // obtengo el tipo de objeto
             "commit" => 1,
             "tree" => 2,
@@ -263,35 +402,82 @@ pub fn create_packfile(contents: Vec<(String, String, Vec<u8>)>) -> Result<Vec<u
                 ))
             }
         };
+        if len.parse::<usize>().is_err() {
+            // valido la longitud del objeto
+            return Err(GitrError::PackFileError(
+                "create_packfile".to_string(),
+                "Longitud de objeto no válida".to_string(),
+            ));
+        }
+        let mut object = format!("{} {}\0", obj_type, raw_data.len()).into_bytes();
+        object.extend(&raw_data);
+        hashes.push(hash_object_data(&object));
+        objetos.push(PackCandidate {
+            obj_type: obj_type_num,
+            name: String::new(),
+            data: raw_data,
+        });
+    }
+    // ###### NOMBRES DESDE LOS TREES ######
+    let mut nombres: HashMap<String, String> = HashMap::new();
+    for objeto in objetos.iter().filter(|o| o.obj_type == 2) {
+        for (_, nombre, hash) in Tree::entries_from_data(&objeto.data).unwrap_or_default() {
+            nombres.entry(hash).or_insert(nombre);
+        }
+    }
+    for (objeto, hash) in objetos.iter_mut().zip(hashes) {
+        if let Some(nombre) = nombres.get(&hash) {
+            objeto.name = nombre.clone();
+        }
+    }
+    objetos.sort_by(|a, b| {
+        a.obj_type
+            .cmp(&b.obj_type)
+            .then(a.name.cmp(&b.name))
+            .then(b.data.len().cmp(&a.data.len()))
+    });
 
-        let obj_len = match len.parse::<usize>() {
-            // obtengo la longitud del objeto
-            Ok(len) => len,
-            Err(_e) => {
-                return Err(GitrError::PackFileError(
-                    "create_packfile".to_string(),
-                    "Longitud de objeto no válida".to_string(),
-                ))
+    // ########## HEADER ##########
+    let mut final_data: Vec<u8> = Vec::new();
+    let header = "PACK".to_string();
+    final_data.extend(header.as_bytes());
+    let ver: u32 = 2;
+    final_data.extend(&ver.to_be_bytes());
+    final_data.extend(&(objetos.len() as u32).to_be_bytes());
+    // ########## OBJECTS ##########
+    // (offset en el pack, profundidad de la cadena de deltas) de cada objeto escrito
+    let mut escritos: Vec<(usize, usize)> = Vec::new();
+    for (i, objeto) in objetos.iter().enumerate() {
+        let mut mejor: Option<(usize, Vec<u8>)> = None;
+        if objeto.data.len() >= MIN_DELTA_SIZE {
+            for base in (i.saturating_sub(DELTA_WINDOW)..i).rev() {
+                if objetos[base].obj_type != objeto.obj_type || escritos[base].1 >= MAX_DELTA_DEPTH {
+                    continue;
+                }
+                let delta = create_delta(&objetos[base].data, &objeto.data);
+                let limite = match &mejor {
+                    Some((_, mejor_delta)) => mejor_delta.len(),
+                    None => objeto.data.len() / 2,
+                };
+                if delta.len() < limite {
+                    mejor = Some((base, delta));
+                }
             }
-        };
-        if obj_len < 16 {
-            obj_data.push((obj_type << 4) | obj_len as u8);
-        } else {
-            // ###### SIZE ENCODING ######
-            let mut size = obj_len;
-            let mut size_bytes: Vec<u8> = Vec::new();
-            size_bytes.push((obj_type << 4) | (size & 0x0F) as u8 | 0x80); // meto el tipo de objeto y los primeros 4 bits de la longitud
-            size >>= 4;
-            while size >= 128 {
-                size_bytes.push((size & 0x7F) as u8 | 0x80); // meto los siguientes 7 bits de la longitud con un 1 adelante
-                size >>= 7;
+        }
+        let offset = final_data.len();
+        match mejor {
+            Some((base, delta)) => {
+                final_data.extend(encode_entry_header(6, delta.len()));
+                final_data.extend(encode_ofs(offset - escritos[base].0));
+                final_data.extend(code(&delta)?);
+                escritos.push((offset, escritos[base].1 + 1));
+            }
+            None => {
+                final_data.extend(encode_entry_header(objeto.obj_type, objeto.data.len()));
+                final_data.extend(code(&objeto.data)?);
+                escritos.push((offset, 0));
             }
-            size_bytes.push(size as u8); // meto los últimos ultimos 7 bits de la longitud con un 0 adelante
-            obj_data.extend(size_bytes);
         }
-        let compressed = code(&raw_data)?;
-        obj_data.extend(compressed);
-        final_data.extend(obj_data);
     }
 
     // ########## CHECKSUM ##########
@@ -320,3 +506,60 @@ impl PackFile {
         })
     }
 }
+
+#[cfg(test)]
+mod pack_file_tests {
+    use super::*;
+
+    fn tree_con(nombre: &str, hash: &str) -> Vec<u8> {
+        let mut data = format!("100644 {}\0", nombre).into_bytes();
+        data.extend((0..40).step_by(2).map(|i| u8::from_str_radix(&hash[i..i + 2], 16).unwrap()));
+        [format!("tree {}\0", data.len()).into_bytes(), data].concat()
+    }
+
+    #[test]
+    fn test_packfile_con_deltas_round_trip() {
+        let v1 = "fn main() {\n    println!(\"hola\");\n}\n".repeat(20);
+        let v2 = v1.clone() + "// una linea mas\n";
+        let otro = "contenido que no se parece a nada de lo anterior".repeat(15);
+        let mut objetos: Vec<Vec<u8>> = [v1, v2, otro]
+            .iter()
+            .map(|contenido| format!("blob {}\0{}", contenido.len(), contenido).into_bytes())
+            .collect();
+        let hashes: Vec<String> = objetos.iter().map(|o| hash_object_data(o)).collect();
+        objetos.push(tree_con("main.rs", &hashes[0]));
+        objetos.push(tree_con("main.rs", &hashes[1]));
+        objetos.push(tree_con("otro.txt", &hashes[2]));
+
+        let mut pack = create_packfile(prepare_contents(objetos.clone())).unwrap();
+        let mut offset = 12;
+        let mut deltas = 0;
+        for _ in 0..objetos.len() {
+            let (entry, leidos) = read_pack_entry(&pack, offset).unwrap();
+            if let PackEntry::OfsDelta(..) = entry {
+                deltas += 1;
+            }
+            offset += leidos;
+        }
+        assert!(deltas >= 1);
+
+        let mut leidos: Vec<String> = read_pack_file(&mut pack)
+            .unwrap()
+            .iter()
+            .map(|o| o.get_hash())
+            .collect();
+        let mut esperados: Vec<String> = objetos.iter().map(|o| hash_object_data(o)).collect();
+        leidos.sort();
+        esperados.sort();
+        assert_eq!(leidos, esperados);
+    }
+
+    #[test]
+    fn test_decode_objeto_mas_grande_que_el_buffer() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut comprimido = code(&data).unwrap();
+        let largo = comprimido.len() as u64;
+        comprimido.extend_from_slice(b"siguiente objeto");
+        assert_eq!(decode(&comprimido).unwrap(), (data, largo));
+    }
+}
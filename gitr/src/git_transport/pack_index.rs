@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::commands::command_utils::sha1hashing2;
+use crate::git_transport::deltas::apply_delta;
+use crate::git_transport::pack_file::{object_with_header, read_pack_entry, PackEntry};
+use crate::git_transport::ref_discovery::{extract_version, verify_header};
+use crate::gitr_errors::GitrError;
+use crate::object_database::{hash_object_data, MAX_DELTA_CHAIN};
+
+const IDX_MAGIC: [u8; 4] = [0xff, b't', b'O', b'c'];
+const IDX_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackIndexEntry {
+    pub hash: String,
+    pub crc32: u32,
+    pub offset: u64,
+}
+
+/// Indice (.idx version 2) de un packfile: para cada objeto su hash, el CRC32
+/// de la entrada comprimida y el offset dentro del pack. Las entradas quedan
+/// ordenadas por hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackIndex {
+    entries: Vec<PackIndexEntry>,
+    pack_checksum: Vec<u8>,
+}
+
+impl PackIndex {
+    /// Genera el indice recorriendo el pack completo y resolviendo los deltas
+    /// para poder calcular el hash de cada objeto.
+    pub fn from_pack(pack: &[u8]) -> Result<Self, GitrError> {
+        Ok(index_pack(pack)?.0)
+    }
+
+    /// Parsea un archivo .idx version 2
+    pub fn from_bytes(data: &[u8]) -> Result<Self, GitrError> {
+        if data.len() < 8 + 256 * 4 + 40 || data[..4] != IDX_MAGIC {
+            return Err(idx_invalido("header invalido"));
+        }
+        if u32::from_be_bytes([data[4], data[5], data[6], data[7]]) != IDX_VERSION {
+            return Err(idx_invalido("version no soportada"));
+        }
+        let checksum = &data[data.len() - 20..];
+        if sha1hashing2(data[..data.len() - 20].to_vec()) != checksum {
+            return Err(idx_invalido("checksum incorrecto"));
+        }
+        let fanout = 8;
+        let n = read_u32(data, fanout + 255 * 4)? as usize;
+        let hashes = fanout + 256 * 4;
+        let crcs = hashes + n * 20;
+        let offsets = crcs + n * 4;
+        let large_offsets = offsets + n * 4;
+        let mut entries = Vec::with_capacity(n);
+        for i in 0..n {
+            let hash = match data.get(hashes + i * 20..hashes + (i + 1) * 20) {
+                Some(hash) => hash.iter().fold(String::new(), |mut output, b| {
+                    let _ = write!(output, "{b:02x}");
+                    output
+                }),
+                None => return Err(idx_invalido("faltan hashes")),
+            };
+            let crc32 = read_u32(data, crcs + i * 4)?;
+            let mut offset = read_u32(data, offsets + i * 4)? as u64;
+            if offset & 0x8000_0000 != 0 {
+                // el offset no entra en 31 bits, esta en la tabla de offsets de 8 bytes
+                let pos = large_offsets + (offset & 0x7fff_ffff) as usize * 8;
+                offset = ((read_u32(data, pos)? as u64) << 32) | read_u32(data, pos + 4)? as u64;
+            }
+            entries.push(PackIndexEntry { hash, crc32, offset });
+        }
+        Ok(PackIndex {
+            entries,
+            pack_checksum: data[data.len() - 40..data.len() - 20].to_vec(),
+        })
+    }
+
+    /// Serializa el indice en formato .idx version 2
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GitrError> {
+        let mut data = IDX_MAGIC.to_vec();
+        data.extend(IDX_VERSION.to_be_bytes());
+        // fanout: cantidad de objetos cuyo primer byte es <= i
+        let mut fanout = [0u32; 256];
+        for entry in &self.entries {
+            let first_byte = hex_to_bytes(&entry.hash)?[0] as usize;
+            for count in fanout[first_byte..].iter_mut() {
+                *count += 1;
+            }
+        }
+        for count in fanout {
+            data.extend(count.to_be_bytes());
+        }
+        for entry in &self.entries {
+            data.extend(hex_to_bytes(&entry.hash)?);
+        }
+        for entry in &self.entries {
+            data.extend(entry.crc32.to_be_bytes());
+        }
+        let mut large_offsets: Vec<u8> = vec![];
+        for entry in &self.entries {
+            if entry.offset < 0x8000_0000 {
+                data.extend((entry.offset as u32).to_be_bytes());
+            } else {
+                let pos = (large_offsets.len() / 8) as u32 | 0x8000_0000;
+                data.extend(pos.to_be_bytes());
+                large_offsets.extend(entry.offset.to_be_bytes());
+            }
+        }
+        data.extend(large_offsets);
+        data.extend(&self.pack_checksum);
+        let checksum = sha1hashing2(data.clone());
+        data.extend(checksum);
+        Ok(data)
+    }
+
+    /// Busca el offset de un objeto dentro del pack
+    pub fn find(&self, hash: &str) -> Option<u64> {
+        match self.entries.binary_search_by(|entry| entry.hash.as_str().cmp(hash)) {
+            Ok(i) => Some(self.entries[i].offset),
+            Err(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &Vec<PackIndexEntry> {
+        &self.entries
+    }
+
+    /// Checksum (sha-1) del pack al que corresponde el indice, en hexa
+    pub fn pack_checksum(&self) -> String {
+        self.pack_checksum
+            .iter()
+            .fold(String::new(), |mut output, b| {
+                let _ = write!(output, "{b:02x}");
+                output
+            })
+    }
+}
+
+/// Recorre el pack y arma su indice. Ademas del indice devuelve el largo real del
+/// pack (checksum incluido), por si el buffer recibido trae bytes de mas al final.
+pub(crate) fn index_pack(pack: &[u8]) -> Result<(PackIndex, usize), GitrError> {
+    if pack.len() < 32 {
+        return Err(GitrError::PackFileError(
+            "index_pack".to_string(),
+            "No hay suficientes bytes para el encabezado mínimo".to_string(),
+        ));
+    }
+    verify_header(&pack[..4])?;
+    extract_version(&pack[4..8])?;
+    let num_objects = extract_version(&pack[8..12])? as usize;
+
+    let mut entries = Vec::with_capacity(num_objects);
+    // solo se guardan los offsets: las bases de los deltas se vuelven a
+    // descomprimir, asi no queda el pack entero resuelto en memoria
+    let mut offsets_por_hash: HashMap<String, usize> = HashMap::new();
+    let mut offset = 12;
+    for _ in 0..num_objects {
+        let (entry, leidos) = read_pack_entry(pack, offset)?;
+        let object = resolver_entry(pack, entry, &offsets_por_hash, 0)?;
+        let hash = hash_object_data(&object);
+        entries.push(PackIndexEntry {
+            hash: hash.clone(),
+            crc32: crc32fast::hash(&pack[offset..offset + leidos]),
+            offset: offset as u64,
+        });
+        offsets_por_hash.insert(hash, offset);
+        offset += leidos;
+    }
+    let pack_checksum = match pack.get(offset..offset + 20) {
+        Some(checksum) => checksum.to_vec(),
+        None => return Err(idx_invalido("falta el checksum del pack")),
+    };
+    if sha1hashing2(pack[..offset].to_vec()) != pack_checksum {
+        return Err(idx_invalido("el checksum del pack no coincide"));
+    }
+    entries.sort_by(|a, b| a.hash.cmp(&b.hash));
+    Ok((
+        PackIndex {
+            entries,
+            pack_checksum,
+        },
+        offset + 20,
+    ))
+}
+
+// objeto con header de una entrada, resolviendo su cadena de deltas con las
+// entradas anteriores del pack
+fn resolver_entry(
+    pack: &[u8],
+    entry: PackEntry,
+    offsets_por_hash: &HashMap<String, usize>,
+    profundidad: usize,
+) -> Result<Vec<u8>, GitrError> {
+    if profundidad > MAX_DELTA_CHAIN {
+        return Err(idx_invalido("cadena de deltas demasiado larga"));
+    }
+    let (base_offset, delta) = match entry {
+        PackEntry::Object(object_type, data) => return object_with_header(object_type, &data),
+        PackEntry::OfsDelta(base_offset, delta) => (base_offset, delta),
+        PackEntry::RefDelta(base_hash, delta) => match offsets_por_hash.get(&base_hash) {
+            Some(base_offset) => (*base_offset, delta),
+            None => return Err(base_no_encontrada()),
+        },
+    };
+    let base = read_pack_entry(pack, base_offset)?.0;
+    apply_delta(
+        &resolver_entry(pack, base, offsets_por_hash, profundidad + 1)?,
+        &delta,
+    )
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, GitrError> {
+    match data.get(pos..pos + 4) {
+        Some(bytes) => Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(idx_invalido("archivo truncado")),
+    }
+}
+
+fn hex_to_bytes(hash: &str) -> Result<Vec<u8>, GitrError> {
+    let mut bytes = Vec::with_capacity(20);
+    for i in (0..hash.len()).step_by(2) {
+        match hash.get(i..i + 2).map(|byte| u8::from_str_radix(byte, 16)) {
+            Some(Ok(byte)) => bytes.push(byte),
+            _ => return Err(GitrError::ObjectNotFound(hash.to_string())),
+        }
+    }
+    Ok(bytes)
+}
+
+fn idx_invalido(motivo: &str) -> GitrError {
+    GitrError::PackFileError("PackIndex".to_string(), motivo.to_string())
+}
+
+fn base_no_encontrada() -> GitrError {
+    GitrError::PackFileError(
+        "index_pack".to_string(),
+        "No se encontro el objeto base".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod pack_index_tests {
+    use super::*;
+    use crate::git_transport::pack_file::{create_packfile, prepare_contents};
+
+    #[test]
+    fn test_index_round_trip() {
+        let pack = create_packfile(prepare_contents(vec![
+            b"blob 10\0Hola mundo".to_vec(),
+            b"blob 10\0Chau mundo".to_vec(),
+        ]))
+        .unwrap();
+        let index = PackIndex::from_pack(&pack).unwrap();
+        let hashes: Vec<&str> = index.entries().iter().map(|e| e.hash.as_str()).collect();
+        assert_eq!(
+            hashes,
+            vec![
+                "06ae662f3a48ae0354f4eaec7a03008a63b2dc4b",
+                "37d8675fc0a706d4ea62f38b41469bf3fa9c5ed9"
+            ]
+        );
+        assert_eq!(index.find("37d8675fc0a706d4ea62f38b41469bf3fa9c5ed9"), Some(12));
+        assert!(index.find("0000000000000000000000000000000000000000").is_none());
+
+        let idx = index.to_bytes().unwrap();
+        assert_eq!(idx[..8], [0xff, b't', b'O', b'c', 0, 0, 0, 2]);
+        // fanout[0x06] = 1, fanout[0x37] = 2
+        assert_eq!(read_u32(&idx, 8 + 0x05 * 4).unwrap(), 0);
+        assert_eq!(read_u32(&idx, 8 + 0x06 * 4).unwrap(), 1);
+        assert_eq!(read_u32(&idx, 8 + 0x37 * 4).unwrap(), 2);
+        assert_eq!(read_u32(&idx, 8 + 0xff * 4).unwrap(), 2);
+        assert_eq!(PackIndex::from_bytes(&idx).unwrap(), index);
+    }
+
+    #[test]
+    fn test_index_offsets_grandes() {
+        let index = PackIndex {
+            entries: vec![
+                PackIndexEntry {
+                    hash: "06ae662f3a48ae0354f4eaec7a03008a63b2dc4b".to_string(),
+                    crc32: 1,
+                    offset: 12,
+                },
+                PackIndexEntry {
+                    hash: "37d8675fc0a706d4ea62f38b41469bf3fa9c5ed9".to_string(),
+                    crc32: 2,
+                    offset: 0x1_0000_0000,
+                },
+            ],
+            pack_checksum: vec![0; 20],
+        };
+        let idx = index.to_bytes().unwrap();
+        assert_eq!(PackIndex::from_bytes(&idx).unwrap(), index);
+
+        let mut corrupto = idx.clone();
+        corrupto[10] ^= 0xff;
+        assert!(PackIndex::from_bytes(&corrupto).is_err());
+    }
+}
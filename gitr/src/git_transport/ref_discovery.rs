@@ -1,6 +1,7 @@
 use crate::{
     file_manager,
     gitr_errors::{self, GitrError},
+    repository::Repository,
 };
 use std::{
     collections::HashSet,
@@ -105,18 +106,18 @@ pub fn reference_update_request(
 /// * hash_n_references: Vector de tuplas (hash, referencia) del servidor
 /// * heads_ids: Vector de ids de los heads del cliente
 /// * heads_refs: Vector de referencias de los heads del cliente
-/// * cliente: String que indica el nombre del cliente
+/// * repo: Repositorio del cliente
 /// # Devuelve:
 /// * Ok(()) si se puede pushear
 /// * Err(GitrError::PushError) si no se puede pushear
-pub fn check_push( hash_n_references: Vec<(String, String)>, heads_ids: Vec<String>, heads_refs: Vec<String>, cliente: String) -> Result<(), GitrError> {
+pub fn check_push( hash_n_references: Vec<(String, String)>, heads_ids: Vec<String>, heads_refs: Vec<String>, repo: &Repository) -> Result<(), GitrError> {
     for hash_n_ref in hash_n_references.clone() {
         if hash_n_ref.1 == "HEAD" {
             continue;
         }
         for (j, h_refer) in heads_refs.iter().enumerate() {
             if hash_n_ref.1 == format!("refs/heads/{}",h_refer).as_str(){
-                if hash_n_ref.0 != heads_ids[j] && !is_parent(heads_ids[j].clone(),hash_n_ref.0, cliente.clone())  {
+                if hash_n_ref.0 != heads_ids[j] && !is_parent(heads_ids[j].clone(),hash_n_ref.0, repo)  {
                     return Err(GitrError::PushError("Cliente desactualizado".to_string()));
                 }
                 break;
@@ -126,8 +127,8 @@ pub fn check_push( hash_n_references: Vec<(String, String)>, heads_ids: Vec<Stri
     Ok(())
 }
 
-fn is_parent(child: String, parent: String, cliente: String) -> bool {
-    let parents = match crate::objects::commit::Commit::get_parents(vec![child],vec![],cliente) {
+fn is_parent(child: String, parent: String, repo: &Repository) -> bool {
+    let parents = match crate::objects::commit::Commit::get_parents(vec![child],vec![],repo) {
         Ok(parents) => parents,
         Err(_) => return false,
     };
@@ -184,7 +185,7 @@ fn analizar_ref(
 pub fn assemble_want_message(
     references: &Vec<(String, String)>,
     client_commits: Vec<String>,
-    cliente: String,
+    repo: &Repository,
 ) -> Result<String, GitrError> {
     let set = client_commits
         .clone()
@@ -203,7 +204,7 @@ pub fn assemble_want_message(
         return Ok(want_message.to_string());
     }
     if !client_commits.is_empty() {
-        for have in file_manager::get_all_objects_hashes(cliente.clone())? {
+        for have in file_manager::get_all_objects_hashes(repo)? {
             let have_line = format!("have {}", have);
             want_message.push_str(&format!("{:04X}{}\n", have_line.len() + 5, have_line));
         }
@@ -214,7 +215,8 @@ pub fn assemble_want_message(
     Ok(want_message)
 }
 
-pub fn ref_discovery(r_path: &str) -> std::io::Result<(String, HashSet<String>)> {
+pub fn ref_discovery(repo: &Repository) -> std::io::Result<(String, HashSet<String>)> {
+    let r_path = repo.git_dir();
     let mut contenido_total = String::new();
     let mut guardados: HashSet<String> = HashSet::new();
     let ruta = format!("{}/HEAD", r_path);
@@ -20,6 +20,7 @@ pub enum GitrError {
     InvalidTreeError,
     InvalidCommitError,
     InvalidTagError,
+    InvalidIndexError(String),
     ConnectionError,
     SocketError(String, String),
     PackFileError(String, String),
@@ -32,6 +33,17 @@ pub enum GitrError {
     PullRequestReadError,
     PushError(String),
     BranchNotFound,
+    AmbiguousRevision(String, Vec<String>),
+    NoUpstream(String),
+    PathspecError(String),
+    CheckoutConflict(Vec<String>),
+    MergeInProgress,
+    NoMergeInProgress,
+    UnmergedPaths(Vec<String>),
+    NonFastForward(String),
+    MergeLocalChanges(Vec<String>),
+    UnrelatedHistories(String, String),
+    FsckError(usize, usize),
 }
 
 impl fmt::Display for GitrError {
@@ -76,6 +88,7 @@ impl fmt::Display for GitrError {
             Self::InvalidCommitError => write!(f, "ERROR: El commit no es valido"),
             Self::ConnectionError => write!(f, "ERROR: No se pudo conectar al servidor"),
             Self::InvalidTagError => write!(f, "ERROR: La tag no es valida"),
+            Self::InvalidIndexError(info) => write!(f, "ERROR: El index no es valido: {}", info),
             Self::SocketError(origin_function, info) => write!(
                 f,
                 "SocketError en la funcion {}. Info: {}",
@@ -97,6 +110,57 @@ impl fmt::Display for GitrError {
             Self::PullRequestReadError => write!(f, "ERROR: No se pudo leer el PR del server"),
             Self::PushError(info) => write!(f, "ERROR: No se pudo hacer push. Info: {}", info),
             Self::BranchNotFound => write!(f, "ERROR: No se encontro la branch"),
+            Self::AmbiguousRevision(prefijo, candidatos) => write!(
+                f,
+                "ERROR: el hash corto '{}' es ambiguo. Candidatos: {}",
+                prefijo,
+                candidatos.join(", ")
+            ),
+            Self::NoUpstream(branch) => {
+                write!(f, "ERROR: la branch '{}' no tiene upstream", branch)
+            }
+            Self::CheckoutConflict(paths) => write!(
+                f,
+                "ERROR: el checkout pisaria los cambios locales de estos archivos:\n\t{}\nHaga commit de sus cambios o descartelos antes de cambiar de branch.",
+                paths.join("\n\t")
+            ),
+            Self::PathspecError(path) => write!(
+                f,
+                "ERROR: '{}' no coincide con ningun archivo conocido por gitr",
+                path
+            ),
+            Self::MergeInProgress => write!(
+                f,
+                "ERROR: hay un merge sin terminar (existe MERGE_HEAD). Haga commit o merge --abort antes de mergear."
+            ),
+            Self::UnmergedPaths(paths) => write!(
+                f,
+                "ERROR: no se puede commitear, hay archivos sin mergear:\n\t{}\nResuelva los conflictos y agreguelos con add.",
+                paths.join("\n\t")
+            ),
+            Self::NoMergeInProgress => {
+                write!(f, "ERROR: no hay ningun merge en curso (no existe MERGE_HEAD)")
+            }
+            Self::NonFastForward(branch) => write!(
+                f,
+                "ERROR: no se puede hacer fast-forward a '{}', se cancela el merge (--ff-only)",
+                branch
+            ),
+            Self::MergeLocalChanges(paths) => write!(
+                f,
+                "ERROR: el merge pisaria los cambios locales de estos archivos:\n\t{}\nHaga commit de sus cambios o descartelos antes de mergear.",
+                paths.join("\n\t")
+            ),
+            Self::UnrelatedHistories(a, b) => write!(
+                f,
+                "ERROR: '{}' y '{}' no tienen ningun commit en comun (refusing to merge unrelated histories)",
+                a, b
+            ),
+            Self::FsckError(verificados, errores) => write!(
+                f,
+                "ERROR: fsck: {} objetos verificados, {} errores",
+                verificados, errores
+            ),
 
         }
     }
@@ -12,7 +12,8 @@ use crate::gitr_errors::GitrError;
 use crate::objects::pull_request::PullRequest;
 
 fn get_commits(cliente: String) -> String {
-    let mut commits = match file_manager::commit_log("-1".to_string(), cliente) {
+    let mut commits = match file_manager::open_current_repo(cliente)
+        .and_then(|repo| file_manager::commit_log("-1".to_string(), &repo)) {
         Ok(commits) => commits,
         Err(_) => return "No hay commits para mostrar".to_string(),
     };
@@ -92,7 +93,8 @@ fn email_valido(email_recibido: String) -> bool {
 
 fn update_branches(branch_selector: &ComboBoxText, cliente: String) {
     branch_selector.remove_all();
-    let branches = match file_manager::get_branches(cliente.clone()) {
+    let branches = match file_manager::open_current_repo(cliente.clone())
+        .and_then(|repo| file_manager::get_branches(&repo)) {
         Ok(branches) => branches,
         Err(e) => {
             println!("Error al obtener branches: {:?}", e);
@@ -282,16 +284,8 @@ fn build_ui(application: &gtk::Application, cliente: String) -> Option<String> {
         };
         let message = format!("\"{}\"", commit_message.text());
         let cm_msg = vec!["-m".to_string(), message];
-        let parent = match file_manager::read_file("parent".to_string()){
-            Ok(parent) => parent,
-            Err(_) => "None".to_string(),
-        };
-        match commands_fn::commit(cm_msg, parent, cliente_.clone()) {
-            Ok(_) => {
-                if fs::remove_file("parent").is_err(){
-                    ()
-                }
-            },
+        match commands_fn::commit(cm_msg, "None".to_string(), cliente_.clone()) {
+            Ok(_) => (),
             Err(e) => {
                 println!("Error al hacer commit: {:?}", e);
                 return;
@@ -466,13 +460,13 @@ fn build_ui(application: &gtk::Application, cliente: String) -> Option<String> {
         };
         let flags = vec![branch.to_string()];
         match commands_fn::merge(flags,cliente_.clone()){
-            Ok((hubo_conflict, parent, _)) => {
+            Ok((hubo_conflict, _, _)) => {
                 if !hubo_conflict{
                     return;
                 }
                 remote_error_label_clone.set_text("Surgieron conflicts al hacer merge, por favor arreglarlos y commitear el resultado.");
                 remote_error_dialog_clone.show();
-                file_manager::write_file("parent".to_string(),parent).unwrap();           },
+            },
             Err(e) => {
                 println!("Error al hacer merge: {:?}",e);
             },
@@ -491,7 +485,8 @@ fn build_ui(application: &gtk::Application, cliente: String) -> Option<String> {
     let pr_create_button_clone = pr_create_button.clone();
     let creation_pr_clone = creation_pr.clone();
     pr_create_button_clone.connect_clicked(move |_|{
-        if file_manager::get_remote(cliente_clone.clone()).is_err(){
+        if file_manager::open_current_repo(cliente_clone.clone())
+            .and_then(|repo| file_manager::get_remote(&repo)).is_err(){
             
             clone_error.show();
         }else{
@@ -528,7 +523,8 @@ fn build_ui(application: &gtk::Application, cliente: String) -> Option<String> {
             return;
         }
         let vec_pr = vec![title.to_string(),description.to_string(),compare.to_string(),base.to_string()];
-        _create_pr(vec_pr,cliente_clone.clone()).unwrap();
+        let repo = file_manager::open_current_repo(cliente_clone.clone()).unwrap();
+        _create_pr(vec_pr, &repo).unwrap();
         //crear el pr
         creation_pr_clone.hide();
     });
@@ -541,13 +537,15 @@ fn build_ui(application: &gtk::Application, cliente: String) -> Option<String> {
     let clone_error = remote_error_dialog.clone();
     let pr_open_button = pr_open_button.clone();
     pr_open_button.connect_clicked(move |_|{
-        if file_manager::get_remote(cliente_clone.clone()).is_err(){
+        if file_manager::open_current_repo(cliente_clone.clone())
+            .and_then(|repo| file_manager::get_remote(&repo)).is_err(){
             clone_error.show();
         }else{
             pr_list_clone.foreach(|row|{
                 pr_list_clone.remove(row);
             });
-            let remote = file_manager::get_remote(cliente_clone.clone()).unwrap();
+            let remote = file_manager::open_current_repo(cliente_clone.clone())
+            .and_then(|repo| file_manager::get_remote(&repo)).unwrap();
             let sv_url = remote.split('/').collect::<Vec<&str>>()[0].replace("localhost:", "server");
             let sv_name = remote.split('/').collect::<Vec<&str>>()[1];
             let dir = sv_url + "/repos/" + sv_name;
@@ -571,13 +569,15 @@ fn build_ui(application: &gtk::Application, cliente: String) -> Option<String> {
     let pr_closed_button = pr_closed_button.clone();
     let pr_list_clone = pr_list.clone();
     pr_closed_button.connect_clicked(move |_|{
-        if file_manager::get_remote(cliente_clone.clone()).is_err(){
+        if file_manager::open_current_repo(cliente_clone.clone())
+            .and_then(|repo| file_manager::get_remote(&repo)).is_err(){
             clone_error.show();
         }else{
             pr_list_clone.foreach(|row|{
                 pr_list_clone.remove(row);
             });
-            let remote = file_manager::get_remote(cliente_clone.clone()).unwrap();
+            let remote = file_manager::open_current_repo(cliente_clone.clone())
+            .and_then(|repo| file_manager::get_remote(&repo)).unwrap();
             let sv_url = remote.split('/').collect::<Vec<&str>>()[0].replace("localhost:", "server");
             let sv_name = remote.split('/').collect::<Vec<&str>>()[1];
             let dir = sv_url + "/repos/" + sv_name;
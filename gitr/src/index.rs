@@ -0,0 +1,583 @@
+use std::fmt::Write;
+use std::fs;
+
+use crate::commands::command_utils::sha1hashing2;
+use crate::gitr_errors::GitrError;
+use crate::objects::tree::get_formated_hash;
+
+/// Index file format, same as git's "DIRC" version 2:
+/// 12 bytes header ("DIRC", version, number of entries),
+/// the entries sorted by path, and a SHA-1 of everything before it.
+const INDEX_SIGNATURE: &[u8; 4] = b"DIRC";
+const INDEX_VERSION: u32 = 2;
+const HEADER_LEN: usize = 12;
+const CHECKSUM_LEN: usize = 20;
+// ctime, mtime, dev, ino, mode, uid, gid, size, sha1 and flags
+const ENTRY_FIXED_LEN: usize = 62;
+const NAME_MASK: u16 = 0x0FFF;
+const STAGE_SHIFT: u16 = 12;
+const STAGE_MASK: u16 = 0x3 << STAGE_SHIFT;
+
+pub const REGULAR_FILE_MODE: u32 = 0o100644;
+pub const EXECUTABLE_FILE_MODE: u32 = 0o100755;
+pub const SYMLINK_MODE: u32 = 0o120000;
+pub const TREE_MODE: u32 = 0o40000;
+
+/// Mode with which git would stage the file at `file_path`: symlinks are 120000,
+/// files with any executable bit 100755 and the rest 100644.
+/// The link itself is inspected, never its target.
+pub fn file_mode(file_path: &str) -> u32 {
+    let metadata = match fs::symlink_metadata(file_path) {
+        Ok(metadata) => metadata,
+        Err(_) => return REGULAR_FILE_MODE,
+    };
+    if metadata.file_type().is_symlink() {
+        return SYMLINK_MODE;
+    }
+    if is_executable(&metadata) {
+        EXECUTABLE_FILE_MODE
+    } else {
+        REGULAR_FILE_MODE
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// One staged file. Paths are relative to the work tree and always use '/'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    pub ctime_s: u32,
+    pub ctime_ns: u32,
+    pub mtime_s: u32,
+    pub mtime_ns: u32,
+    pub dev: u32,
+    pub ino: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u32,
+    pub hash: String,
+    pub flags: u16,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Index {
+    entries: Vec<IndexEntry>,
+}
+
+impl IndexEntry {
+    /// Creates an entry without stat data (used when the file isn't in the work tree)
+    pub fn new(path: String, hash: String, mode: u32) -> Self {
+        let flags = name_flags(&path);
+        IndexEntry {
+            ctime_s: 0,
+            ctime_ns: 0,
+            mtime_s: 0,
+            mtime_ns: 0,
+            dev: 0,
+            ino: 0,
+            mode,
+            uid: 0,
+            gid: 0,
+            size: 0,
+            hash,
+            flags,
+            path,
+        }
+    }
+
+    /// Creates an entry taking the stat data from the file at `file_path`
+    pub fn from_file(file_path: &str, path: String, hash: String) -> Result<Self, GitrError> {
+        let mut entry = IndexEntry::new(path, hash, file_mode(file_path));
+        entry.update_stat(file_path)?;
+        Ok(entry)
+    }
+
+    /// Refreshes the stat data of the entry with the file at `file_path`
+    pub fn update_stat(&mut self, file_path: &str) -> Result<(), GitrError> {
+        let metadata = match fs::symlink_metadata(file_path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Err(GitrError::FileReadError(file_path.to_string())),
+        };
+        let stat = FileStat::from_metadata(&metadata);
+        self.ctime_s = stat.ctime_s;
+        self.ctime_ns = stat.ctime_ns;
+        self.mtime_s = stat.mtime_s;
+        self.mtime_ns = stat.mtime_ns;
+        self.dev = stat.dev;
+        self.ino = stat.ino;
+        self.uid = stat.uid;
+        self.gid = stat.gid;
+        self.size = stat.size;
+        Ok(())
+    }
+
+    /// True if the file at `file_path` still has the stat data of the entry,
+    /// so the stored hash can be used instead of rehashing the file.
+    /// Files changed after the index was written (`index_mtime`) are "racy" and never trusted.
+    pub fn stat_matches(&self, file_path: &str, index_mtime: (u32, u32)) -> bool {
+        let metadata = match fs::symlink_metadata(file_path) {
+            Ok(metadata) => metadata,
+            Err(_) => return false,
+        };
+        let stat = FileStat::from_metadata(&metadata);
+        if (stat.mtime_s, stat.mtime_ns) >= index_mtime {
+            return false;
+        }
+        self.size == stat.size
+            && self.mtime_s == stat.mtime_s
+            && self.mtime_ns == stat.mtime_ns
+            && self.ctime_s == stat.ctime_s
+            && self.ctime_ns == stat.ctime_ns
+            && self.ino == stat.ino
+    }
+
+    /// Merge stage of the entry (0 when there is no conflict)
+    pub fn stage(&self) -> u8 {
+        ((self.flags & STAGE_MASK) >> STAGE_SHIFT) as u8
+    }
+
+    /// Sets the merge stage: 1 is the common ancestor, 2 ours and 3 theirs
+    pub fn set_stage(&mut self, stage: u8) {
+        self.flags = (self.flags & !STAGE_MASK) | (((stage as u16) << STAGE_SHIFT) & STAGE_MASK);
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, GitrError> {
+        let mut data: Vec<u8> = Vec::new();
+        for field in [
+            self.ctime_s,
+            self.ctime_ns,
+            self.mtime_s,
+            self.mtime_ns,
+            self.dev,
+            self.ino,
+            self.mode,
+            self.uid,
+            self.gid,
+            self.size,
+        ] {
+            data.extend(field.to_be_bytes());
+        }
+        data.extend(get_formated_hash(self.hash.clone(), &self.path)?);
+        let flags = (self.flags & !NAME_MASK) | name_flags(&self.path);
+        data.extend(flags.to_be_bytes());
+        data.extend(self.path.as_bytes());
+        // 1 to 8 nul bytes so the entry length is a multiple of 8
+        let padding = 8 - (data.len() % 8);
+        data.extend(vec![0; padding]);
+        Ok(data)
+    }
+
+    /// Parses the entry starting at `data[0]`, returns it and the bytes it used
+    fn from_bytes(data: &[u8]) -> Result<(Self, usize), GitrError> {
+        let (fixed, name) = match (data.get(..ENTRY_FIXED_LEN), data.get(ENTRY_FIXED_LEN..)) {
+            (Some(fixed), Some(name)) => (fixed, name),
+            _ => return Err(entrada_truncada()),
+        };
+        let mut fields = [0u32; 10];
+        for (i, field) in fields.iter_mut().enumerate() {
+            *field = read_u32(&fixed[i * 4..])?;
+        }
+        let hash = fixed[40..60].iter().fold(String::new(), |mut output, b| {
+            let _ = write!(output, "{b:02x}");
+            output
+        });
+        let flags = u16::from_be_bytes([fixed[60], fixed[61]]);
+        let name_len = match name.iter().position(|b| *b == 0) {
+            Some(len) => len,
+            None => {
+                return Err(GitrError::InvalidIndexError(
+                    "path sin terminar".to_string(),
+                ))
+            }
+        };
+        let path = String::from_utf8_lossy(&name[..name_len]).to_string();
+        let name_end = ENTRY_FIXED_LEN + name_len;
+        let padding = 8 - (name_end % 8);
+        if data.len() < name_end + padding {
+            return Err(entrada_truncada());
+        }
+        let entry = IndexEntry {
+            ctime_s: fields[0],
+            ctime_ns: fields[1],
+            mtime_s: fields[2],
+            mtime_ns: fields[3],
+            dev: fields[4],
+            ino: fields[5],
+            mode: fields[6],
+            uid: fields[7],
+            gid: fields[8],
+            size: fields[9],
+            hash,
+            flags,
+            path,
+        };
+        Ok((entry, name_end + padding))
+    }
+}
+
+impl Index {
+    pub fn new() -> Self {
+        Index { entries: vec![] }
+    }
+
+    /// Parses an index file, checking its header and checksum
+    pub fn from_bytes(data: &[u8]) -> Result<Self, GitrError> {
+        if data.len() < HEADER_LEN + CHECKSUM_LEN || &data[..4] != INDEX_SIGNATURE {
+            return Err(GitrError::InvalidIndexError("firma invalida".to_string()));
+        }
+        let version = read_u32(&data[4..])?;
+        if version != INDEX_VERSION {
+            return Err(GitrError::InvalidIndexError(format!(
+                "version {} no soportada",
+                version
+            )));
+        }
+        let (content, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+        if sha1hashing2(content.to_vec()) != checksum {
+            return Err(GitrError::InvalidIndexError(
+                "checksum invalido".to_string(),
+            ));
+        }
+        let cant_entries = read_u32(&data[8..])?;
+        let mut entries = Vec::new();
+        let mut cursor = HEADER_LEN;
+        for _ in 0..cant_entries {
+            let entry_data = content.get(cursor..).ok_or_else(entrada_truncada)?;
+            let (entry, leidos) = IndexEntry::from_bytes(entry_data)?;
+            entries.push(entry);
+            cursor += leidos;
+        }
+        Ok(Index { entries })
+    }
+
+    /// Serializes the index, sorting the entries as git does
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GitrError> {
+        let mut entries = self.entries.clone();
+        entries.sort_by(|a, b| {
+            a.path
+                .as_bytes()
+                .cmp(b.path.as_bytes())
+                .then(a.stage().cmp(&b.stage()))
+        });
+        let mut data: Vec<u8> = Vec::new();
+        data.extend(INDEX_SIGNATURE);
+        data.extend(INDEX_VERSION.to_be_bytes());
+        data.extend((entries.len() as u32).to_be_bytes());
+        for entry in entries.iter() {
+            data.extend(entry.to_bytes()?);
+        }
+        let checksum = sha1hashing2(data.clone());
+        data.extend(checksum);
+        Ok(data)
+    }
+
+    pub fn entries(&self) -> &Vec<IndexEntry> {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, path: &str) -> Option<&IndexEntry> {
+        self.entries.iter().find(|e| e.path == path)
+    }
+
+    pub fn get_mut(&mut self, path: &str) -> Option<&mut IndexEntry> {
+        self.entries.iter_mut().find(|e| e.path == path)
+    }
+
+    /// Adds the entry, replacing the one with the same path (and stage) if any.
+    /// A stage 0 entry resolves the conflict of the path (drops its stages 1 to 3)
+    /// and a conflict stage replaces the stage 0 entry.
+    pub fn add(&mut self, entry: IndexEntry) {
+        let unmerged = entry.stage() != 0;
+        self.entries
+            .retain(|e| e.path != entry.path || (e.stage() != 0) == unmerged);
+        match self
+            .entries
+            .iter()
+            .position(|e| e.path == entry.path && e.stage() == entry.stage())
+        {
+            Some(i) => self.entries[i] = entry,
+            None => {
+                let i = self.entries.partition_point(|e| {
+                    (e.path.as_bytes(), e.stage()) < (entry.path.as_bytes(), entry.stage())
+                });
+                self.entries.insert(i, entry);
+            }
+        }
+    }
+
+    /// Stages the file at `file_path` (inside the work tree `repo`) with the given hash,
+    /// taking its stat data if the file is there
+    pub fn add_file(&mut self, file_path: &str, repo: &str, hash: String) {
+        let path = relative_path(file_path, repo);
+        let entry = match IndexEntry::from_file(file_path, path.clone(), hash.clone()) {
+            Ok(entry) => entry,
+            Err(_) => IndexEntry::new(path, hash, REGULAR_FILE_MODE),
+        };
+        self.add(entry);
+    }
+
+    /// Removes every entry of the path, returns false if it wasn't staged
+    pub fn remove(&mut self, path: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|e| e.path != path);
+        len != self.entries.len()
+    }
+
+    /// Paths with conflict stages (unmerged), sorted and without repeating
+    pub fn unmerged_paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|e| e.stage() != 0)
+            .map(|e| e.path.clone())
+            .collect();
+        paths.dedup();
+        paths
+    }
+
+    /// Keeps only the entries for which `f` returns true
+    pub fn retain<F: FnMut(&IndexEntry) -> bool>(&mut self, f: F) {
+        self.entries.retain(f);
+    }
+}
+
+// stat data of a file, truncated to 32 bits like git does
+struct FileStat {
+    ctime_s: u32,
+    ctime_ns: u32,
+    mtime_s: u32,
+    mtime_ns: u32,
+    dev: u32,
+    ino: u32,
+    uid: u32,
+    gid: u32,
+    size: u32,
+}
+
+impl FileStat {
+    #[cfg(unix)]
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        FileStat {
+            ctime_s: metadata.ctime() as u32,
+            ctime_ns: metadata.ctime_nsec() as u32,
+            mtime_s: metadata.mtime() as u32,
+            mtime_ns: metadata.mtime_nsec() as u32,
+            dev: metadata.dev() as u32,
+            ino: metadata.ino() as u32,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            size: metadata.size() as u32,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let (mtime_s, mtime_ns) = match metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        {
+            Some(d) => (d.as_secs() as u32, d.subsec_nanos()),
+            None => (0, 0),
+        };
+        FileStat {
+            ctime_s: mtime_s,
+            ctime_ns: mtime_ns,
+            mtime_s,
+            mtime_ns,
+            dev: 0,
+            ino: 0,
+            uid: 0,
+            gid: 0,
+            size: metadata.len() as u32,
+        }
+    }
+}
+
+fn name_flags(path: &str) -> u16 {
+    path.len().min(NAME_MASK as usize) as u16
+}
+
+fn read_u32(data: &[u8]) -> Result<u32, GitrError> {
+    match data.get(..4) {
+        Some(bytes) => Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        None => Err(entrada_truncada()),
+    }
+}
+
+fn entrada_truncada() -> GitrError {
+    GitrError::InvalidIndexError("entrada truncada".to_string())
+}
+
+/// Modification time (seconds, nanoseconds) of the file, (0, 0) if it doesn't exist
+pub fn file_mtime(file_path: &str) -> (u32, u32) {
+    match fs::metadata(file_path) {
+        Ok(metadata) => {
+            let stat = FileStat::from_metadata(&metadata);
+            (stat.mtime_s, stat.mtime_ns)
+        }
+        Err(_) => (0, 0),
+    }
+}
+
+/// Path of `file_path` relative to the repo work tree (`repo`)
+pub fn relative_path(file_path: &str, repo: &str) -> String {
+    let prefix = repo.trim_end_matches('/').to_string() + "/";
+    match file_path.strip_prefix(&prefix) {
+        Some(path) => path.to_string(),
+        None => file_path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod index_tests {
+    use super::*;
+
+    #[test]
+    fn test_index_round_trip() {
+        let mut index = Index::new();
+        index.add(IndexEntry::new(
+            "src/main.rs".to_string(),
+            "016a41a6a35d50d311286359f1a7611948a9c529".to_string(),
+            REGULAR_FILE_MODE,
+        ));
+        index.add(IndexEntry::new(
+            "README".to_string(),
+            "18d74b139e1549bb6a96b281e6ac3a0ec9e563e8".to_string(),
+            REGULAR_FILE_MODE,
+        ));
+        let data = index.to_bytes().unwrap();
+        assert_eq!(&data[..4], b"DIRC");
+        // cada entrada ocupa un multiplo de 8 bytes
+        assert_eq!((data.len() - HEADER_LEN - CHECKSUM_LEN) % 8, 0);
+        let leido = Index::from_bytes(&data).unwrap();
+        assert_eq!(leido, index);
+        assert_eq!(leido.entries()[0].path, "README");
+        assert_eq!(leido.entries()[1].path, "src/main.rs");
+    }
+
+    #[test]
+    fn test_index_stages_de_conflicto() {
+        let mut index = Index::new();
+        let hash = "18d74b139e1549bb6a96b281e6ac3a0ec9e563e8".to_string();
+        index.add(IndexEntry::new("README".to_string(), hash.clone(), REGULAR_FILE_MODE));
+        for stage in [3, 1, 2] {
+            let mut entry = IndexEntry::new("a.txt".to_string(), hash.clone(), REGULAR_FILE_MODE);
+            entry.set_stage(stage);
+            index.add(entry);
+        }
+        assert_eq!(index.unmerged_paths(), vec!["a.txt".to_string()]);
+        let stages: Vec<u8> = index.entries().iter().map(|e| e.stage()).collect();
+        assert_eq!(stages, vec![0, 1, 2, 3]);
+        let leido = Index::from_bytes(&index.to_bytes().unwrap()).unwrap();
+        assert_eq!(leido, index);
+
+        // agregar el archivo resuelve el conflicto
+        index.add(IndexEntry::new("a.txt".to_string(), hash, REGULAR_FILE_MODE));
+        assert!(index.unmerged_paths().is_empty());
+        assert_eq!(index.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_index_checksum_invalido() {
+        let mut index = Index::new();
+        index.add(IndexEntry::new(
+            "README".to_string(),
+            "18d74b139e1549bb6a96b281e6ac3a0ec9e563e8".to_string(),
+            REGULAR_FILE_MODE,
+        ));
+        let mut data = index.to_bytes().unwrap();
+        data[HEADER_LEN] ^= 0xFF;
+        assert!(Index::from_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn test_index_truncado() {
+        let mut index = Index::new();
+        index.add(IndexEntry::new(
+            "README".to_string(),
+            "18d74b139e1549bb6a96b281e6ac3a0ec9e563e8".to_string(),
+            REGULAR_FILE_MODE,
+        ));
+        let data = index.to_bytes().unwrap();
+        let content = &data[..data.len() - CHECKSUM_LEN];
+        // con el checksum recalculado, para que falle al leer las entradas
+        let con_checksum = |mut content: Vec<u8>| {
+            let checksum = sha1hashing2(content.clone());
+            content.extend(checksum);
+            content
+        };
+        let mut de_mas = content.to_vec();
+        de_mas[11] = 2;
+        let cortes = [
+            content[..HEADER_LEN + 30].to_vec(),
+            content[..HEADER_LEN + ENTRY_FIXED_LEN + 3].to_vec(),
+            content[..content.len() - 1].to_vec(),
+            de_mas,
+        ];
+        for corte in cortes {
+            assert!(matches!(
+                Index::from_bytes(&con_checksum(corte)),
+                Err(GitrError::InvalidIndexError(_))
+            ));
+        }
+        assert!(matches!(
+            Index::from_bytes(&data[..HEADER_LEN]),
+            Err(GitrError::InvalidIndexError(_))
+        ));
+    }
+    #[test]
+    fn test_stat_matches_con_mtimes_explicitos() {
+        let dir = "test_stat_matches";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let file_path = format!("{}/archivo", dir);
+        fs::write(&file_path, "contenido\n").unwrap();
+        let entry = IndexEntry::from_file(
+            &file_path,
+            "archivo".to_string(),
+            "18d74b139e1549bb6a96b281e6ac3a0ec9e563e8".to_string(),
+        )
+        .unwrap();
+        let mtime = (entry.mtime_s, entry.mtime_ns);
+
+        // index escrito despues del archivo: se confia en el stat data
+        assert!(entry.stat_matches(&file_path, (mtime.0 + 1, 0)));
+        assert!(entry.stat_matches(&file_path, (mtime.0, mtime.1 + 1)));
+        // archivo con el mismo mtime que el index (o posterior): es racy y se rehashea
+        assert!(!entry.stat_matches(&file_path, mtime));
+        assert!(!entry.stat_matches(&file_path, (mtime.0, 0)));
+
+        // cualquier diferencia en el stat data invalida la entrada
+        let index_mtime = (mtime.0 + 1, 0);
+        let cambios: [fn(&mut IndexEntry); 4] = [
+            |e| e.size += 1,
+            |e| e.mtime_ns ^= 1,
+            |e| e.ctime_s ^= 1,
+            |e| e.ino ^= 1,
+        ];
+        for cambio in cambios {
+            let mut otra = entry.clone();
+            cambio(&mut otra);
+            assert!(!otra.stat_matches(&file_path, index_mtime));
+        }
+        assert!(!entry.stat_matches(&format!("{}/no_existe", dir), index_mtime));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
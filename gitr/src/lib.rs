@@ -6,5 +6,9 @@ pub mod objects;
 
 pub mod file_manager;
 pub mod gitr_errors;
+pub mod index;
 pub mod logger;
+pub mod object_database;
+pub mod repository;
+pub mod rev_parse;
 pub mod server;
@@ -0,0 +1,687 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+use crate::commands::command_utils::{flate2compress2, sha1hashing2};
+use crate::file_manager::{
+    create_directory, decode, read_file_bytes, write_compressed_data, write_file_bytes,
+};
+use crate::git_transport::deltas::apply_delta;
+use crate::git_transport::pack_file::{
+    object_with_header, parse_pack_entry, read_pack_entry, PackEntry,
+};
+use crate::git_transport::pack_index::{index_pack, PackIndex};
+use crate::gitr_errors::GitrError;
+use crate::logger;
+
+/// Almacenamiento de objetos de un repositorio.
+///
+/// Los objetos se leen y escriben descomprimidos y con el header incluido
+/// ("blob 12\0..."); cada backend decide como guardarlos.
+pub trait ObjectDatabase: Debug + Send + Sync {
+    /// Devuelve el objeto descomprimido (header incluido).
+    /// On Error returns a ObjectNotFound
+    fn read(&self, hash: &str) -> Result<Vec<u8>, GitrError>;
+
+    /// Guarda un objeto descomprimido (header incluido) y devuelve su hash
+    fn write(&self, data: &[u8]) -> Result<String, GitrError>;
+
+    /// Guarda un objeto ya comprimido con zlib cuyo hash se conoce.
+    /// Por defecto se descomprime y se escribe con write
+    fn write_compressed(&self, _hash: &str, compressed: &[u8]) -> Result<(), GitrError> {
+        self.write(&decode(compressed)?)?;
+        Ok(())
+    }
+
+    fn contains(&self, hash: &str) -> bool;
+
+    /// Itera los hashes de todos los objetos guardados
+    fn iter(&self) -> Box<dyn Iterator<Item = String> + '_>;
+
+    /// Guarda todos los objetos de un packfile completo (header, objetos y checksum)
+    /// y devuelve sus hashes. Por defecto se descomprimen y se escriben de a uno.
+    fn write_pack(&self, pack: &[u8]) -> Result<Vec<String>, GitrError> {
+        let pack = PackObjectDatabase::from_bytes(pack)?;
+        let mut hashes = vec![];
+        for hash in pack.iter() {
+            hashes.push(self.write(&pack.read(&hash)?)?);
+        }
+        Ok(hashes)
+    }
+}
+
+/// Devuelve el hash (sha-1 en hexa) de un objeto descomprimido
+pub fn hash_object_data(data: &[u8]) -> String {
+    sha1hashing2(data.to_vec())
+        .iter()
+        .fold(String::new(), |mut output, b| {
+            let _ = write!(output, "{b:02x}");
+            output
+        })
+}
+
+/*******************
+ *   LOOSE OBJECTS
+ * *****************/
+
+/// Objetos sueltos en objects/xx/yyyy, comprimidos con zlib
+#[derive(Debug, Clone)]
+pub struct LooseObjectDatabase {
+    objects_dir: String,
+}
+
+impl LooseObjectDatabase {
+    pub fn new(objects_dir: &str) -> Self {
+        LooseObjectDatabase {
+            objects_dir: objects_dir.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn object_path(&self, hash: &str) -> Option<String> {
+        if hash.len() < 3 || !hash.is_char_boundary(2) {
+            return None;
+        }
+        let (folder_name, file_name) = hash.split_at(2);
+        Some(format!("{}/{}/{}", self.objects_dir, folder_name, file_name))
+    }
+
+    /// Fecha de ultima modificacion del objeto suelto
+    pub fn modified(&self, hash: &str) -> Option<SystemTime> {
+        let path = self.object_path(hash)?;
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Borra el objeto suelto, y su carpeta si queda vacia
+    pub fn remove(&self, hash: &str) -> Result<(), GitrError> {
+        let path = match self.object_path(hash) {
+            Some(path) => path,
+            None => return Err(GitrError::ObjectNotFound(hash.to_string())),
+        };
+        if fs::remove_file(&path).is_err() {
+            return Err(GitrError::ObjectNotFound(hash.to_string()));
+        }
+        let _ = fs::remove_dir(format!("{}/{}", self.objects_dir, &hash[..2]));
+        Ok(())
+    }
+}
+
+impl ObjectDatabase for LooseObjectDatabase {
+    fn read(&self, hash: &str) -> Result<Vec<u8>, GitrError> {
+        let path = match self.object_path(hash) {
+            Some(path) => path,
+            None => return Err(GitrError::ObjectNotFound(hash.to_string())),
+        };
+        let compressed = match fs::read(&path) {
+            Ok(compressed) => compressed,
+            Err(_) => return Err(GitrError::ObjectNotFound(hash.to_string())),
+        };
+        let data = decode(&compressed)?;
+        if data.is_empty() {
+            return Err(GitrError::FileReadError(path));
+        }
+        Ok(data)
+    }
+
+    fn write(&self, data: &[u8]) -> Result<String, GitrError> {
+        let hash = hash_object_data(data);
+        self.write_compressed(&hash, &flate2compress2(data.to_vec())?)?;
+        Ok(hash)
+    }
+
+    /// Los objetos sueltos ya estan comprimidos, se guardan tal cual
+    fn write_compressed(&self, hash: &str, compressed: &[u8]) -> Result<(), GitrError> {
+        let path = match self.object_path(hash) {
+            Some(path) => path,
+            None => return Err(GitrError::FileWriteError(hash.to_string())),
+        };
+        logger::log_file_operation(format!("writing object {}", hash))?;
+        let folder_dir = format!("{}/{}", self.objects_dir, &hash[..2]);
+        if fs::metadata(&folder_dir).is_err() {
+            create_directory(&folder_dir)?;
+        }
+        write_compressed_data(&path, compressed)
+    }
+
+    fn contains(&self, hash: &str) -> bool {
+        match self.object_path(hash) {
+            Some(path) => fs::metadata(path).is_ok(),
+            None => false,
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        let mut hashes = vec![];
+        let carpetas = match fs::read_dir(&self.objects_dir) {
+            Ok(carpetas) => carpetas,
+            Err(_) => return Box::new(hashes.into_iter()),
+        };
+        for carpeta in carpetas.flatten() {
+            let dir_name = carpeta.file_name().to_string_lossy().to_string();
+            if dir_name.len() != 2 {
+                continue; // objects/pack, objects/info
+            }
+            let archivos = match fs::read_dir(carpeta.path()) {
+                Ok(archivos) => archivos,
+                Err(_) => continue,
+            };
+            for archivo in archivos.flatten() {
+                hashes.push(dir_name.clone() + &archivo.file_name().to_string_lossy());
+            }
+        }
+        Box::new(hashes.into_iter())
+    }
+}
+
+/*******************
+ *   PACKFILE
+ * *****************/
+
+/// Cantidad maxima de deltas encadenados que se resuelven al leer un objeto
+pub(crate) const MAX_DELTA_CHAIN: usize = 4096;
+
+/// De donde salen los bytes de un packfile
+#[derive(Debug)]
+enum PackData {
+    /// el pack completo en memoria
+    Memoria(Vec<u8>),
+    /// el .pack abierto: de cada objeto se lee solo su entrada. limites son los
+    /// offsets de las entradas ordenados, con el del checksum al final
+    Archivo {
+        file: Mutex<File>,
+        limites: Vec<u64>,
+    },
+}
+
+/// Objetos de un packfile, de solo lectura. Los objetos se buscan con el
+/// indice (.idx) y se descomprimen, resolviendo los deltas, recien al leerlos.
+#[derive(Debug)]
+pub struct PackObjectDatabase {
+    pack: PackData,
+    index: PackIndex,
+}
+
+impl PackObjectDatabase {
+    /// Abre un archivo .pack junto con su .idx, sin cargar el pack en memoria. Si el
+    /// .idx no existe se genera leyendo el pack completo una vez (sin guardarlo).
+    pub fn open(path: &str) -> Result<Self, GitrError> {
+        let idx_path = format!("{}.idx", path.trim_end_matches(".pack"));
+        let index = match fs::read(&idx_path) {
+            Ok(idx) => PackIndex::from_bytes(&idx)?,
+            Err(_) => PackIndex::from_pack(&read_file_bytes(path.to_string())?)?,
+        };
+        let file = File::open(path).map_err(|_| GitrError::FileReadError(path.to_string()))?;
+        let pack_len = match file.metadata() {
+            Ok(metadata) if metadata.len() >= 32 => metadata.len(),
+            _ => return Err(GitrError::FileReadError(path.to_string())),
+        };
+        let mut limites: Vec<u64> = index.entries().iter().map(|e| e.offset).collect();
+        limites.push(pack_len - 20);
+        limites.sort();
+        Ok(PackObjectDatabase {
+            pack: PackData::Archivo {
+                file: Mutex::new(file),
+                limites,
+            },
+            index,
+        })
+    }
+
+    /// Arma la base a partir de un packfile completo (header, objetos y checksum)
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, GitrError> {
+        let (index, pack_len) = index_pack(buffer)?;
+        Ok(PackObjectDatabase {
+            pack: PackData::Memoria(buffer[..pack_len].to_vec()),
+            index,
+        })
+    }
+
+    pub fn index(&self) -> &PackIndex {
+        &self.index
+    }
+
+    /// Lee la entrada (sin resolver) que empieza en offset
+    fn entry_at(&self, offset: usize) -> Result<PackEntry, GitrError> {
+        let (file, limites) = match &self.pack {
+            PackData::Memoria(pack) => return Ok(read_pack_entry(pack, offset)?.0),
+            PackData::Archivo { file, limites } => (file, limites),
+        };
+        let error = || GitrError::PackFileError("entry_at".to_string(), offset.to_string());
+        // la entrada termina donde empieza la siguiente
+        let fin = match limites.binary_search(&(offset as u64)) {
+            Ok(i) if i + 1 < limites.len() => limites[i + 1],
+            _ => return Err(error()),
+        };
+        let mut entry = vec![0; (fin - offset as u64) as usize];
+        let mut file = file.lock().map_err(|_| error())?;
+        file.seek(SeekFrom::Start(offset as u64))
+            .and_then(|_| file.read_exact(&mut entry))
+            .map_err(|_| error())?;
+        Ok(parse_pack_entry(&entry, offset)?.0)
+    }
+
+    /// Lee el objeto que esta en offset, resolviendo la cadena de deltas
+    fn read_at(&self, offset: usize, profundidad: usize) -> Result<Vec<u8>, GitrError> {
+        if profundidad > MAX_DELTA_CHAIN {
+            return Err(GitrError::PackFileError(
+                "read_at".to_string(),
+                "cadena de deltas demasiado larga".to_string(),
+            ));
+        }
+        match self.entry_at(offset)? {
+            PackEntry::Object(object_type, data) => object_with_header(object_type, &data),
+            PackEntry::OfsDelta(base_offset, delta) => {
+                apply_delta(&self.read_at(base_offset, profundidad + 1)?, &delta)
+            }
+            PackEntry::RefDelta(base_hash, delta) => match self.index.find(&base_hash) {
+                Some(base_offset) => {
+                    apply_delta(&self.read_at(base_offset as usize, profundidad + 1)?, &delta)
+                }
+                None => Err(GitrError::ObjectNotFound(base_hash)),
+            },
+        }
+    }
+}
+
+impl ObjectDatabase for PackObjectDatabase {
+    fn read(&self, hash: &str) -> Result<Vec<u8>, GitrError> {
+        match self.index.find(hash) {
+            Some(offset) => self.read_at(offset as usize, 0),
+            None => Err(GitrError::ObjectNotFound(hash.to_string())),
+        }
+    }
+
+    fn write(&self, _data: &[u8]) -> Result<String, GitrError> {
+        Err(GitrError::PackFileError(
+            "write".to_string(),
+            "el packfile es de solo lectura".to_string(),
+        ))
+    }
+
+    fn contains(&self, hash: &str) -> bool {
+        self.index.find(hash).is_some()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        Box::new(self.index.entries().iter().map(|entry| entry.hash.clone()))
+    }
+}
+
+/*******************
+ *   DISK (LOOSE + PACKS)
+ * *****************/
+
+/// Base de objetos de un repositorio en disco: los objetos nuevos se escriben
+/// sueltos y los packfiles recibidos se guardan tal cual en objects/pack,
+/// cada uno con su .idx. Las lecturas buscan primero en los sueltos y despues
+/// en los packs, que se cargan la primera vez que se necesitan.
+#[derive(Debug)]
+pub struct DiskObjectDatabase {
+    loose: LooseObjectDatabase,
+    pack_dir: String,
+    packs: RwLock<Option<Vec<PackObjectDatabase>>>,
+}
+
+impl DiskObjectDatabase {
+    pub fn new(objects_dir: &str) -> Self {
+        let objects_dir = objects_dir.trim_end_matches('/');
+        DiskObjectDatabase {
+            loose: LooseObjectDatabase::new(objects_dir),
+            pack_dir: format!("{}/pack", objects_dir),
+            packs: RwLock::new(None),
+        }
+    }
+
+    pub fn loose(&self) -> &LooseObjectDatabase {
+        &self.loose
+    }
+
+    /// Paths de todos los .pack de objects/pack
+    pub fn pack_files(&self) -> Vec<String> {
+        let mut paths = vec![];
+        if let Ok(archivos) = fs::read_dir(&self.pack_dir) {
+            for archivo in archivos.flatten() {
+                let path = archivo.path().to_string_lossy().to_string();
+                if path.ends_with(".pack") {
+                    paths.push(path);
+                }
+            }
+        }
+        paths.sort();
+        paths
+    }
+
+    /// Abre todos los .pack de objects/pack
+    fn load_packs(&self) -> Vec<PackObjectDatabase> {
+        let mut packs = vec![];
+        for path in self.pack_files() {
+            match PackObjectDatabase::open(&path) {
+                Ok(pack) => packs.push(pack),
+                Err(e) => {
+                    let _ = logger::log_error(format!("no se pudo abrir {}: {}", path, e));
+                }
+            }
+        }
+        packs
+    }
+
+    /// Ejecuta f sobre los packs, cargandolos si todavia no se leyeron
+    fn with_packs<T>(&self, f: impl FnOnce(&[PackObjectDatabase]) -> T) -> T {
+        if let Ok(packs) = self.packs.read() {
+            if let Some(packs) = packs.as_ref() {
+                return f(packs);
+            }
+        }
+        match self.packs.write() {
+            Ok(mut packs) => f(packs.get_or_insert_with(|| self.load_packs())),
+            Err(_) => f(&[]),
+        }
+    }
+}
+
+impl ObjectDatabase for DiskObjectDatabase {
+    fn read(&self, hash: &str) -> Result<Vec<u8>, GitrError> {
+        if self.loose.contains(hash) {
+            return self.loose.read(hash);
+        }
+        self.with_packs(|packs| {
+            for pack in packs {
+                if pack.contains(hash) {
+                    return pack.read(hash);
+                }
+            }
+            Err(GitrError::ObjectNotFound(hash.to_string()))
+        })
+    }
+
+    fn write(&self, data: &[u8]) -> Result<String, GitrError> {
+        self.loose.write(data)
+    }
+
+    fn write_compressed(&self, hash: &str, compressed: &[u8]) -> Result<(), GitrError> {
+        self.loose.write_compressed(hash, compressed)
+    }
+
+    fn contains(&self, hash: &str) -> bool {
+        self.loose.contains(hash) || self.with_packs(|packs| packs.iter().any(|p| p.contains(hash)))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        let mut hashes: Vec<String> = self.loose.iter().collect();
+        self.with_packs(|packs| {
+            for pack in packs {
+                hashes.extend(pack.iter());
+            }
+        });
+        hashes.sort();
+        hashes.dedup();
+        Box::new(hashes.into_iter())
+    }
+
+    /// Guarda el pack en objects/pack/pack-<checksum>.pack junto con su .idx
+    fn write_pack(&self, pack: &[u8]) -> Result<Vec<String>, GitrError> {
+        let (index, pack_len) = index_pack(pack)?;
+        let hashes: Vec<String> = index.entries().iter().map(|e| e.hash.clone()).collect();
+        if hashes.is_empty() {
+            return Ok(hashes);
+        }
+        if fs::metadata(&self.pack_dir).is_err() {
+            create_directory(&self.pack_dir)?;
+        }
+        let name = format!("{}/pack-{}", self.pack_dir, index.pack_checksum());
+        logger::log_file_operation(format!("writing packfile {}.pack", name))?;
+        // primero el .pack y despues el .idx, asi nunca queda un .idx sin su pack
+        write_file_bytes(format!("{}.pack", name), &pack[..pack_len])?;
+        write_file_bytes(format!("{}.idx", name), &index.to_bytes()?)?;
+        if let Ok(mut packs) = self.packs.write() {
+            if let Some(packs) = packs.as_mut() {
+                packs.push(PackObjectDatabase::open(&format!("{}.pack", name))?);
+            }
+        }
+        Ok(hashes)
+    }
+}
+
+/*******************
+ *   IN-MEMORY
+ * *****************/
+
+/// Objetos guardados en memoria, para tests que no tocan el disco
+#[derive(Debug, Default)]
+pub struct MemoryObjectDatabase {
+    objects: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryObjectDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ObjectDatabase for MemoryObjectDatabase {
+    fn read(&self, hash: &str) -> Result<Vec<u8>, GitrError> {
+        let objects = match self.objects.lock() {
+            Ok(objects) => objects,
+            Err(_) => return Err(GitrError::ObjectNotFound(hash.to_string())),
+        };
+        match objects.get(hash) {
+            Some(data) => Ok(data.clone()),
+            None => Err(GitrError::ObjectNotFound(hash.to_string())),
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> Result<String, GitrError> {
+        let hash = hash_object_data(data);
+        match self.objects.lock() {
+            Ok(mut objects) => {
+                objects.insert(hash.clone(), data.to_vec());
+                Ok(hash)
+            }
+            Err(_) => Err(GitrError::FileWriteError(hash)),
+        }
+    }
+
+    fn contains(&self, hash: &str) -> bool {
+        match self.objects.lock() {
+            Ok(objects) => objects.contains_key(hash),
+            Err(_) => false,
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        let hashes: Vec<String> = match self.objects.lock() {
+            Ok(objects) => objects.keys().cloned().collect(),
+            Err(_) => vec![],
+        };
+        Box::new(hashes.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod object_database_tests {
+    use super::*;
+    use crate::git_transport::pack_file::{code, create_packfile, prepare_contents};
+
+    const BLOB: &[u8] = b"blob 10\0Hola mundo";
+    const BLOB_HASH: &str = "37d8675fc0a706d4ea62f38b41469bf3fa9c5ed9";
+
+    fn check_read_write(db: &dyn ObjectDatabase) {
+        assert!(!db.contains(BLOB_HASH));
+        let hash = db.write(BLOB).unwrap();
+        assert_eq!(hash, BLOB_HASH);
+        assert!(db.contains(&hash));
+        assert_eq!(db.read(&hash).unwrap(), BLOB);
+        assert_eq!(db.iter().collect::<Vec<String>>(), vec![hash]);
+        assert!(db.read("0000000000000000000000000000000000000000").is_err());
+    }
+
+    #[test]
+    fn test_memory_object_database() {
+        check_read_write(&MemoryObjectDatabase::new());
+    }
+
+    #[test]
+    fn test_loose_object_database() {
+        let dir = "test_loose_object_database";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let db = LooseObjectDatabase::new(dir);
+        check_read_write(&db);
+        assert!(fs::metadata(format!("{}/37/d8675fc0a706d4ea62f38b41469bf3fa9c5ed9", dir)).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_compressed_guarda_el_objeto_sin_recomprimir() {
+        let dir = "test_write_compressed";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let compressed = flate2compress2(BLOB.to_vec()).unwrap();
+        let db = LooseObjectDatabase::new(dir);
+        db.write_compressed(BLOB_HASH, &compressed).unwrap();
+        let path = format!("{}/37/d8675fc0a706d4ea62f38b41469bf3fa9c5ed9", dir);
+        assert_eq!(fs::read(path).unwrap(), compressed);
+        assert_eq!(db.read(BLOB_HASH).unwrap(), BLOB);
+
+        let memory = MemoryObjectDatabase::new();
+        memory.write_compressed(BLOB_HASH, &compressed).unwrap();
+        assert_eq!(memory.read(BLOB_HASH).unwrap(), BLOB);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_pack_object_database() {
+        let pack = create_packfile(prepare_contents(vec![BLOB.to_vec()])).unwrap();
+        let db = PackObjectDatabase::from_bytes(&pack).unwrap();
+        assert!(db.contains(BLOB_HASH));
+        assert_eq!(db.read(BLOB_HASH).unwrap(), BLOB);
+        assert_eq!(db.iter().count(), 1);
+        assert!(db.write(BLOB).is_err());
+    }
+
+    /// Pack con BLOB, un OFS_DELTA y un REF_DELTA que lo usan de base
+    fn pack_con_deltas() -> Vec<u8> {
+        let mut pack = b"PACK".to_vec();
+        pack.extend(2u32.to_be_bytes());
+        pack.extend(3u32.to_be_bytes());
+        // blob "Hola mundo"
+        pack.push((3 << 4) | 10);
+        pack.extend(code(b"Hola mundo").unwrap());
+        // "Hola mundo!!": copia los 10 bytes de la base e inserta "!!"
+        let ofs = pack.len() - 12;
+        pack.push((6 << 4) | 7);
+        pack.push(ofs as u8);
+        pack.extend(code(&[10, 12, 0x90, 10, 2, b'!', b'!']).unwrap());
+        // "Hola mundo??", con la base referenciada por hash
+        pack.push((7 << 4) | 7);
+        pack.extend(hex_to_bytes(BLOB_HASH));
+        pack.extend(code(&[10, 12, 0x90, 10, 2, b'?', b'?']).unwrap());
+        let checksum = sha1hashing2(pack.clone());
+        pack.extend(checksum);
+        pack
+    }
+
+    fn hex_to_bytes(hash: &str) -> Vec<u8> {
+        (0..hash.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hash[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_pack_object_database_resuelve_deltas() {
+        let db = PackObjectDatabase::from_bytes(&pack_con_deltas()).unwrap();
+        assert_eq!(db.iter().count(), 3);
+        let ofs = hash_object_data(b"blob 12\0Hola mundo!!");
+        let refd = hash_object_data(b"blob 12\0Hola mundo??");
+        assert_eq!(db.read(&ofs).unwrap(), b"blob 12\0Hola mundo!!");
+        assert_eq!(db.read(&refd).unwrap(), b"blob 12\0Hola mundo??");
+        assert_eq!(db.read(BLOB_HASH).unwrap(), BLOB);
+    }
+
+    #[test]
+    fn test_pack_con_deltas_round_trip() {
+        let base = "una linea que se repite en los dos archivos\n".repeat(30);
+        let objetos: Vec<Vec<u8>> = [base.clone(), base.clone() + "y otra al final\n", "chico".into()]
+            .iter()
+            .map(|contenido| format!("blob {}\0{}", contenido.len(), contenido).into_bytes())
+            .collect();
+        let pack = create_packfile(prepare_contents(objetos.clone())).unwrap();
+        let db = PackObjectDatabase::from_bytes(&pack).unwrap();
+        assert_eq!(db.iter().count(), 3);
+        let deltas = db
+            .index()
+            .entries()
+            .iter()
+            .filter(|e| {
+                let (entry, _) = read_pack_entry(&pack, e.offset as usize).unwrap();
+                matches!(entry, PackEntry::OfsDelta(..))
+            })
+            .count();
+        assert_eq!(deltas, 1);
+        for objeto in objetos {
+            assert_eq!(db.read(&hash_object_data(&objeto)).unwrap(), objeto);
+        }
+    }
+
+    #[test]
+    fn test_pack_object_database_checksum_invalido() {
+        let mut pack = pack_con_deltas();
+        let ultimo = pack.len() - 1;
+        pack[ultimo] ^= 0xff;
+        assert!(PackObjectDatabase::from_bytes(&pack).is_err());
+    }
+
+    #[test]
+    fn test_pack_object_database_open_lee_las_entradas_del_archivo() {
+        let dir = "test_pack_open";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let path = format!("{}/pack-test.pack", dir);
+        fs::write(&path, pack_con_deltas()).unwrap();
+        let ofs = hash_object_data(b"blob 12\0Hola mundo!!");
+        let refd = hash_object_data(b"blob 12\0Hola mundo??");
+
+        // sin .idx se indexa el pack, con .idx se usa ese
+        let sin_idx = PackObjectDatabase::open(&path).unwrap();
+        fs::write(format!("{}/pack-test.idx", dir), sin_idx.index().to_bytes().unwrap()).unwrap();
+        let con_idx = PackObjectDatabase::open(&path).unwrap();
+        for db in [sin_idx, con_idx] {
+            assert_eq!(db.read(&ofs).unwrap(), b"blob 12\0Hola mundo!!");
+            assert_eq!(db.read(&refd).unwrap(), b"blob 12\0Hola mundo??");
+            assert_eq!(db.read(BLOB_HASH).unwrap(), BLOB);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_disk_object_database_write_pack() {
+        let dir = "test_disk_object_database";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let db = DiskObjectDatabase::new(dir);
+        db.write(b"blob 4\0chau").unwrap();
+        let hashes = db.write_pack(&pack_con_deltas()).unwrap();
+        assert_eq!(hashes.len(), 3);
+
+        // se guarda el pack con su idx, sin explotarlo en objetos sueltos
+        let packs: Vec<String> = fs::read_dir(format!("{}/pack", dir))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(packs.len(), 2);
+        assert!(!db.loose.contains(BLOB_HASH));
+
+        // una base nueva lee los packs del disco
+        let db = DiskObjectDatabase::new(dir);
+        assert_eq!(db.iter().count(), 4);
+        for hash in hashes {
+            assert!(db.contains(&hash));
+            assert!(db.read(&hash).is_ok());
+        }
+        assert_eq!(db.read(&hash_object_data(b"blob 4\0chau")).unwrap(), b"blob 4\0chau");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,13 +1,13 @@
-use crate::commands::command_utils::{flate2compress, sha1hashing};
-use crate::file_manager;
+use crate::commands::command_utils::{flate2compress2, sha1hashing2};
 use crate::gitr_errors::GitrError;
 use crate::objects::tree::Tree;
+use crate::repository::Repository;
 use std::fmt::Write;
 
+/// Entrada de un tree: un blob con su modo (100644, 100755 o 120000) o un subtree
 #[derive(Debug)]
-
 pub enum TreeEntry {
-    Blob(Blob),
+    Blob(Blob, u32),
     Tree(Tree),
 }
 
@@ -18,10 +18,11 @@ pub struct Blob {
 }
 
 impl Blob {
-    pub fn new(raw_data: String) -> Result<Self, GitrError> {
-        let format_data = format!("blob {}\0{}", raw_data.as_bytes().len(), raw_data);
-        let compressed_data = flate2compress(format_data.clone())?;
-        let hashed_file = sha1hashing(format_data);
+    pub fn new(raw_data: Vec<u8>) -> Result<Self, GitrError> {
+        let mut format_data = format!("blob {}\0", raw_data.len()).into_bytes();
+        format_data.extend(raw_data);
+        let compressed_data = flate2compress2(format_data.clone())?;
+        let hashed_file = sha1hashing2(format_data);
         let hashed_file_str = hashed_file
             .iter()
             .fold(String::new(),|mut output,b| {
@@ -33,8 +34,8 @@ impl Blob {
             hash: hashed_file_str,
         })
     }
-    pub fn save(&self, cliente: String) -> Result<(), GitrError> {
-        file_manager::write_object(self.compressed_data.clone(), self.get_hash(), cliente)?;
+    pub fn save(&self, repo: &Repository) -> Result<(), GitrError> {
+        repo.objects().write_compressed(&self.hash, &self.compressed_data)?;
         Ok(())
     }
 
@@ -6,6 +6,7 @@ use chrono::Utc;
 use crate::commands::command_utils::{flate2compress, get_user_mail_from_config, sha1hashing};
 use crate::file_manager::{self};
 use crate::gitr_errors::GitrError;
+use crate::repository::Repository;
 
 use super::tag::Tag;
 use super::tree::Tree;
@@ -25,7 +26,7 @@ impl Commit {
         author: String,
         committer: String,
         message: String,
-        cliente: String,
+        repo: &Repository,
     ) -> Result<Self, GitrError> {
         let mut format_data = String::new();
         let header = "commit ";
@@ -39,13 +40,13 @@ impl Commit {
         format_data.push_str(&format!(
             "author {} <{}> {} -0300\n",
             author,
-            get_user_mail_from_config(cliente.clone())?,
+            get_user_mail_from_config(repo)?,
             Utc::now().timestamp()
         ));
         format_data.push_str(&format!(
             "committer {} <{}> {} -0300\n",
             committer,
-            get_user_mail_from_config(cliente.clone())?,
+            get_user_mail_from_config(repo)?,
             Utc::now().timestamp()
         ));
         format_data.push('\n');
@@ -107,8 +108,8 @@ impl Commit {
         })
     }
 
-    pub fn save(&self, cliente: String) -> Result<(), GitrError> {
-        crate::file_manager::write_object(self.data.clone(), self.hash.clone(), cliente)?;
+    pub fn save(&self, repo: &Repository) -> Result<(), GitrError> {
+        repo.objects().write_compressed(&self.hash, &self.data)?;
         Ok(())
     }
 
@@ -162,7 +163,7 @@ impl Commit {
     pub fn get_objects_from_commits(
         commits_id: Vec<String>,
         client_objects: Vec<String>,
-        r_path: String,
+        repo: &Repository,
     ) -> Result<Vec<String>, GitrError> {
         let mut object_ids: HashSet<String> = HashSet::new();
         for obj_id in client_objects.clone() {
@@ -173,16 +174,16 @@ impl Commit {
             object_ids.insert(id.clone());
             match Commit::new_commit_from_data(file_manager::get_object(
                 id.clone(),
-                r_path.clone(),
+                repo,
             )?) {
                 Ok(commit) => commits.push(commit),
                 _ => {
                     match Tag::new_tag_from_data(file_manager::get_object(
                         id.clone(),
-                        r_path.clone(),
+                        repo,
                     )?) {
                         Ok(tag) => commits.push(Commit::new_commit_from_data(
-                            file_manager::get_object(tag.get_commit_hash(), r_path.clone())?,
+                            file_manager::get_object(tag.get_commit_hash(), repo)?,
                         )?),
                         Err(_) => return Err(GitrError::InvalidCommitError),
                     }
@@ -192,7 +193,7 @@ impl Commit {
         for commit in commits {
             object_ids.insert(commit.get_tree());
 
-            Tree::get_all_tree_objects(commit.get_tree(), r_path.clone(), &mut object_ids)?;
+            Tree::get_all_tree_objects(commit.get_tree(), repo, &mut object_ids)?;
         }
         for obj in client_objects {
             object_ids.remove(&obj);
@@ -204,7 +205,7 @@ impl Commit {
     pub fn get_parents(
         commits_ids: Vec<String>,
         receivers_commits: Vec<String>,
-        r_path: String,
+        repo: &Repository,
     ) -> Result<Vec<String>, GitrError> {
         let mut parents: Vec<String> = Vec::new();
         let mut rcv_commits = HashSet::new();
@@ -218,19 +219,19 @@ impl Commit {
             parents.push(id.clone());
             match Commit::new_commit_from_data(file_manager::get_object(
                 id.clone(),
-                r_path.clone(),
+                repo,
             )?) {
                 Ok(commit) => Self::get_parents_rec(
                     commit.parents.clone(),
                     &rcv_commits,
-                    r_path.clone(),
+                    repo,
                     &mut parents,
                 )?,
-                _ => match Tag::new_tag_from_data(file_manager::get_object(id, r_path.clone())?) {
+                _ => match Tag::new_tag_from_data(file_manager::get_object(id, repo)?) {
                     Ok(tag) => Self::get_parents_rec(
                         vec![tag.get_commit_hash()],
                         &rcv_commits,
-                        r_path.clone(),
+                        repo,
                         &mut parents,
                     )?,
                     Err(_) => return Err(GitrError::InvalidCommitError),
@@ -243,7 +244,7 @@ impl Commit {
     fn get_parents_rec(
         ids: Vec<String>,
         receivers_commits: &HashSet<String>,
-        r_path: String,
+        repo: &Repository,
         parents: &mut Vec<String>,
     ) -> Result<(), GitrError> {
         for id in ids {
@@ -251,12 +252,12 @@ impl Commit {
                 continue;
             }
             parents.push(id.clone());
-            match Commit::new_commit_from_data(file_manager::get_object(id, r_path.clone())?) {
+            match Commit::new_commit_from_data(file_manager::get_object(id, repo)?) {
                 Ok(commit) => {
                     Self::get_parents_rec(
                         commit.parents.clone(),
                         receivers_commits,
-                        r_path.clone(),
+                        repo,
                         parents,
                     )?;
                 }
@@ -6,6 +6,7 @@ use crate::{
         flate2compress, get_current_username, get_user_mail_from_config, sha1hashing,
     },
     gitr_errors::GitrError,
+    repository::Repository,
 };
 
 #[derive(Debug)]
@@ -21,7 +22,7 @@ impl Tag {
         tag_name: String,
         tag_message: String,
         commit_hash: String,
-        cliente: String,
+        repo: &Repository,
     ) -> Result<Self, GitrError> {
         let mut format_data = String::new();
         format_data.push_str(&format!("object {}\n", commit_hash));
@@ -29,8 +30,8 @@ impl Tag {
         format_data.push_str(&format!("tag {}\n", tag_name));
         format_data.push_str(&format!(
             "tagger {} <{}> {} -0300\n",
-            get_current_username(cliente.clone()),
-            get_user_mail_from_config(cliente.clone())?,
+            get_current_username(repo),
+            get_user_mail_from_config(repo)?,
             Utc::now().timestamp()
         ));
         format_data.push_str('\n'.to_string().as_str());
@@ -107,8 +108,8 @@ impl Tag {
         })
     }
 
-    pub fn save(&self, cliente: String) -> Result<(), GitrError> {
-        crate::file_manager::write_object(self.data.clone(), self.hash.clone(), cliente)?;
+    pub fn save(&self, repo: &Repository) -> Result<(), GitrError> {
+        repo.objects().write_compressed(&self.hash, &self.data)?;
         Ok(())
     }
 
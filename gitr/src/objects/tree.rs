@@ -4,6 +4,7 @@ use std::fmt::Write;
 use super::blob::TreeEntry;
 use crate::gitr_errors::GitrError;
 use crate::{commands, file_manager};
+use crate::repository::Repository;
 
 #[derive(Debug)]
 pub struct Tree {
@@ -34,14 +35,15 @@ impl Tree {
         let mut entries_size: usize = 0;
         for (path, entry) in &entries {
             match entry {
-                TreeEntry::Blob(blob) => {
+                TreeEntry::Blob(blob, mode) => {
                     let hash = blob.get_hash();
                     let formated_hash = get_formated_hash(hash, path)?;
 
                     let _path_no_repo = path.split_once('/').unwrap().1;
                     let file_name = path.split('/').last().unwrap();
+                    let mode = format!("{:o} ", mode);
                     let obj_entry =
-                        [b"100644 ", file_name.as_bytes(), b"\0", &formated_hash].concat();
+                        [mode.as_bytes(), file_name.as_bytes(), b"\0", &formated_hash].concat();
 
                     entries_size += obj_entry.len();
                     objs_entries.push(obj_entry);
@@ -105,8 +107,8 @@ impl Tree {
         Ok(tree)
     }
 
-    pub fn save(&self, cliente: String) -> Result<(), GitrError> {
-        file_manager::write_object(self.data.clone(), self.hash.clone(), cliente)?;
+    pub fn save(&self, repo: &Repository) -> Result<(), GitrError> {
+        repo.objects().write_compressed(&self.hash, &self.data)?;
         Ok(())
     }
 
@@ -118,6 +120,42 @@ impl Tree {
         self.data.clone()
     }
 
+    /// Parsea las entradas de un tree sin header ("mode name\0<20 bytes>...").
+    /// Devuelve (mode, nombre, hash) de cada entrada.
+    /// On Error returns a InvalidTreeError
+    pub fn entries_from_data(data: &[u8]) -> Result<Vec<(String, String, String)>, GitrError> {
+        let mut entries = vec![];
+        let mut i = 0;
+        while i < data.len() {
+            let fin_nombre = match data[i..].iter().position(|b| *b == 0) {
+                Some(pos) => i + pos,
+                None => return Err(GitrError::InvalidTreeError),
+            };
+            let hash = match data.get(fin_nombre + 1..fin_nombre + 21) {
+                Some(hash) => hash.iter().fold(String::new(), |mut output, b| {
+                    let _ = write!(output, "{b:02x}");
+                    output
+                }),
+                None => return Err(GitrError::InvalidTreeError),
+            };
+            let entrada = match std::str::from_utf8(&data[i..fin_nombre]) {
+                Ok(entrada) => entrada,
+                Err(_) => return Err(GitrError::InvalidTreeError),
+            };
+            let (mode, nombre) = match entrada.split_once(' ') {
+                Some((mode, nombre)) => (mode, nombre),
+                None => return Err(GitrError::InvalidTreeError),
+            };
+            let mode_valido = !mode.is_empty() && mode.chars().all(|c| ('0'..='7').contains(&c));
+            if !mode_valido || nombre.is_empty() {
+                return Err(GitrError::InvalidTreeError);
+            }
+            entries.push((mode.to_string(), nombre.to_string(), hash));
+            i = fin_nombre + 21;
+        }
+        Ok(entries)
+    }
+
     pub fn get_objects_id_from_string(data: String) -> Result<Vec<String>, GitrError> {
         if data.split_at(4).0 != "tree" {
             return Err(GitrError::InvalidTreeError);
@@ -142,17 +180,17 @@ impl Tree {
 
     pub fn get_all_tree_objects(
         tree_id: String,
-        r_path: String,
+        repo: &Repository,
         object_ids: &mut HashSet<String>,
     ) -> Result<(), GitrError> {
-        if let Ok(tree_str) = file_manager::read_object(&tree_id, r_path.clone(), false) {
+        if let Ok(tree_str) = file_manager::read_object(&tree_id, repo) {
             let tree_objects = match Tree::get_objects_id_from_string(tree_str) {
                 Ok(ids) => ids,
                 _ => return Err(GitrError::InvalidTreeError),
             };
             for obj_id in tree_objects {
                 object_ids.insert(obj_id.clone());
-                let _ = Self::get_all_tree_objects(obj_id.clone(), r_path.clone(), object_ids);
+                let _ = Self::get_all_tree_objects(obj_id.clone(), repo, object_ids);
             }
 
             return Ok(());
@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::file_manager::{create_directory, read_file, write_file};
+use crate::gitr_errors::GitrError;
+use crate::object_database::{DiskObjectDatabase, MemoryObjectDatabase, ObjectDatabase};
+
+/// Nombre del directorio interno de un repositorio con work tree
+pub const GITR_DIR: &str = "gitr";
+
+/// Configuracion del usuario ([user] name/email) usada al crear commits y tags
+/// y estilo de los conflictos del merge ([merge] conflictstyle = merge | diff3)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub user_name: Option<String>,
+    pub user_email: Option<String>,
+    pub merge_conflict_style: Option<String>,
+}
+
+impl Config {
+    /// Lee un archivo de configuracion con el formato de gitrconfig
+    pub fn load(path: &str) -> Result<Self, GitrError> {
+        Ok(Self::parse(&read_file(path.to_string())?))
+    }
+
+    /// Parsea las lineas "clave = valor" de las secciones [user] y [merge]
+    pub fn parse(data: &str) -> Self {
+        let mut config = Config::default();
+        let mut section = "";
+        for line in data.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                section = line;
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim().to_string()),
+                None => continue,
+            };
+            match (section, key.to_lowercase().as_str()) {
+                ("[user]", "name") => config.user_name = Some(value),
+                ("[user]", "email") => config.user_email = Some(value),
+                ("[merge]", "conflictstyle") => config.merge_conflict_style = Some(value),
+                _ => (),
+            }
+        }
+        config
+    }
+}
+
+/// Handle de un repositorio gitr. Se abre una sola vez y se pasa por referencia
+/// a todas las funciones que leen o escriben objetos, refs, index o work tree.
+///
+/// Un repositorio normal tiene su work tree en `path` y los datos en `path/gitr`.
+/// Un repositorio bare (los del server) guarda los datos directamente en `path`,
+/// que hace tambien de work tree.
+///
+/// Los objetos se leen y escriben a traves de su ObjectDatabase (por defecto
+/// objetos sueltos y packfiles en git_dir/objects).
+#[derive(Debug, Clone)]
+pub struct Repository {
+    work_tree: String,
+    git_dir: String,
+    bare: bool,
+    config: Config,
+    objects: Arc<dyn ObjectDatabase>,
+}
+
+impl Repository {
+    /// Abre un repositorio existente, normal o bare.
+    /// On Error returns a NoRepository
+    pub fn open(path: &str) -> Result<Self, GitrError> {
+        let path = path.trim_end_matches('/').to_string();
+        let gitr_dir = format!("{}/{}", path, GITR_DIR);
+        let (git_dir, bare) = if Path::new(&gitr_dir).is_dir() {
+            (gitr_dir, false)
+        } else if Path::new(&format!("{}/objects", path)).is_dir()
+            && Path::new(&format!("{}/refs", path)).is_dir()
+        {
+            (path.clone(), true)
+        } else {
+            return Err(GitrError::NoRepository);
+        };
+        let config = Config::load(&format!("{}/config", git_dir)).unwrap_or_default();
+        let objects = Arc::new(DiskObjectDatabase::new(&format!("{}/objects", git_dir)));
+        Ok(Repository {
+            work_tree: path,
+            git_dir,
+            bare,
+            config,
+            objects,
+        })
+    }
+
+    /// Repositorio bare sin directorio, con los objetos en memoria.
+    /// Solo sirve para leer y escribir objetos (no tiene refs, HEAD ni index).
+    pub fn in_memory() -> Self {
+        Repository {
+            work_tree: String::new(),
+            git_dir: String::new(),
+            bare: true,
+            config: Config::default(),
+            objects: Arc::new(MemoryObjectDatabase::new()),
+        }
+    }
+
+    /// Crea un repositorio nuevo con work tree en path.
+    /// On Error returns a AlreadyInitialized
+    pub fn init(path: &str) -> Result<Self, GitrError> {
+        let path = path.trim_end_matches('/').to_string();
+        create_directory(&path)?;
+        let git_dir = format!("{}/{}", path, GITR_DIR);
+        Self::create_git_dir(&git_dir)?;
+        create_directory(&(git_dir.clone() + "/refs/remotes"))?;
+        create_directory(&(git_dir.clone() + "/refs/remotes/daemon"))?;
+        write_file(git_dir + "/remote", "".to_string())?;
+        write_file(path.clone() + "/gitrignore", "".to_string())?;
+        Self::open(&path)
+    }
+
+    /// Crea (o abre, si ya existe) un repositorio bare en path
+    pub fn init_bare(path: &str) -> Result<Self, GitrError> {
+        let path = path.trim_end_matches('/').to_string();
+        if let Ok(repo) = Self::open(&path) {
+            return Ok(repo);
+        }
+        if let Err(e) = fs::create_dir_all(&path) {
+            return Err(GitrError::FileCreationError(format!("{}: {}", path, e)));
+        }
+        Self::create_git_dir(&path)?;
+        Self::open(&path)
+    }
+
+    // objects, refs/heads, refs/tags y HEAD apuntando a master
+    fn create_git_dir(git_dir: &String) -> Result<(), GitrError> {
+        if !Path::new(git_dir).is_dir() {
+            create_directory(git_dir)?;
+        }
+        for dir in ["objects", "refs", "refs/heads", "refs/tags"] {
+            let dir = format!("{}/{}", git_dir, dir);
+            if !Path::new(&dir).is_dir() {
+                create_directory(&dir)?;
+            }
+        }
+        write_file(
+            git_dir.clone() + "/HEAD",
+            "ref: refs/heads/master".to_string(),
+        )
+    }
+
+    /// Path del work tree (en un repo bare coincide con git_dir)
+    pub fn work_tree(&self) -> &str {
+        &self.work_tree
+    }
+
+    /// Path del directorio con objects, refs, HEAD e index
+    pub fn git_dir(&self) -> &str {
+        &self.git_dir
+    }
+
+    pub fn is_bare(&self) -> bool {
+        self.bare
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    pub fn objects(&self) -> &dyn ObjectDatabase {
+        self.objects.as_ref()
+    }
+
+    pub fn set_object_database(&mut self, objects: Arc<dyn ObjectDatabase>) {
+        self.objects = objects;
+    }
+
+    /// Devuelve el path de un archivo dentro de git_dir (ej: "refs/heads/master")
+    pub fn git_path(&self, path: &str) -> String {
+        format!("{}/{}", self.git_dir, path)
+    }
+
+    /// Devuelve el path de un archivo dentro del work tree
+    pub fn work_path(&self, path: &str) -> String {
+        format!("{}/{}", self.work_tree, path)
+    }
+}
+
+#[cfg(test)]
+mod repository_tests {
+    use super::*;
+
+    #[test]
+    fn test_init_and_open_repository() {
+        let path = "test_repository_init";
+        let _ = fs::remove_dir_all(path);
+        let repo = Repository::init(path).unwrap();
+        assert!(!repo.is_bare());
+        assert_eq!(repo.git_dir(), "test_repository_init/gitr");
+        assert_eq!(repo.git_path("HEAD"), "test_repository_init/gitr/HEAD");
+        let reopened = Repository::open(path).unwrap();
+        assert_eq!(reopened.work_tree(), path);
+        assert!(Repository::init(path).is_err());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_init_bare_repository() {
+        let path = "test_repository_bare/repos/x";
+        let _ = fs::remove_dir_all("test_repository_bare");
+        let repo = Repository::init_bare(path).unwrap();
+        assert!(repo.is_bare());
+        assert_eq!(repo.git_dir(), path);
+        assert!(Repository::open(path).unwrap().is_bare());
+        fs::remove_dir_all("test_repository_bare").unwrap();
+    }
+
+    #[test]
+    fn test_open_missing_repository() {
+        assert_eq!(
+            Repository::open("no_existe_este_repo").unwrap_err(),
+            GitrError::NoRepository
+        );
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse("[user]\n\temail = test@gmail.com\n\tname = test\n");
+        assert_eq!(config.user_name, Some("test".to_string()));
+        assert_eq!(config.user_email, Some("test@gmail.com".to_string()));
+    }
+}
@@ -0,0 +1,315 @@
+use std::path::Path;
+
+use crate::file_manager;
+use crate::gitr_errors::GitrError;
+use crate::repository::Repository;
+
+/// Remote de las refs remotas (refs/remotes/<remote>/<branch>) que usa @{upstream}
+pub const UPSTREAM_REMOTE: &str = "daemon";
+
+/// Largo minimo de un hash abreviado
+pub const MIN_ABBREV: usize = 4;
+
+/// Resuelve una revision al hash del objeto al que apunta, como git rev-parse.
+///
+/// La base puede ser HEAD (o @), una ref (refs/..., tag, branch o branch remota),
+/// un hash completo o abreviado, o <branch>@{upstream} / @{u}. Despues se aplican
+/// los sufijos en orden: ~<n> (n-esimo ancestro por primer padre), ^<n> (n-esimo padre,
+/// ^0 es el commit mismo) y ^{<tipo>} (pela tags y commits hasta llegar al tipo; ^{} pela tags).
+///
+/// On Error returns a NoCommitExisting si la revision no es valida o no existe,
+/// AmbiguousRevision si un hash abreviado coincide con mas de un objeto
+/// o NoUpstream si la branch no tiene branch remota.
+pub fn rev_parse(revision: &str, repo: &Repository) -> Result<String, GitrError> {
+    let invalida = || GitrError::NoCommitExisting(revision.to_string());
+    let fin_base = revision.find(['~', '^']).unwrap_or(revision.len());
+    let (base, mut sufijos) = revision.split_at(fin_base);
+    let mut hash = resolver_base(base, revision, repo)?;
+    while let Some(operador) = sufijos.chars().next() {
+        sufijos = &sufijos[1..];
+        if operador == '^' && sufijos.starts_with('{') {
+            let cierre = sufijos.find('}').ok_or_else(invalida)?;
+            hash = match &sufijos[1..cierre] {
+                "" => pelar_tags(&hash, repo)?,
+                tipo @ ("commit" | "tree" | "blob" | "tag") => peel(&hash, tipo, repo)?,
+                _ => return Err(invalida()),
+            };
+            sufijos = &sufijos[cierre + 1..];
+            continue;
+        }
+        let digitos = sufijos.len() - sufijos.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let n = match digitos {
+            0 => 1,
+            _ => sufijos[..digitos].parse::<usize>().map_err(|_| invalida())?,
+        };
+        sufijos = &sufijos[digitos..];
+        hash = peel(&hash, "commit", repo)?;
+        if operador == '~' {
+            for _ in 0..n {
+                hash = padre(&hash, 1, repo)?.ok_or_else(invalida)?;
+            }
+        } else if n > 0 {
+            hash = padre(&hash, n, repo)?.ok_or_else(invalida)?;
+        }
+    }
+    Ok(hash)
+}
+
+/// Resuelve una revision y la pela hasta un commit (un tag anotado apunta a su commit)
+pub fn rev_parse_commit(revision: &str, repo: &Repository) -> Result<String, GitrError> {
+    peel(&rev_parse(revision, repo)?, "commit", repo)
+}
+
+/// Resuelve una revision y la pela hasta un tree (un commit apunta a su tree)
+pub fn rev_parse_tree(revision: &str, repo: &Repository) -> Result<String, GitrError> {
+    peel(&rev_parse(revision, repo)?, "tree", repo)
+}
+
+/// Sigue tags y commits desde hash hasta llegar a un objeto del tipo pedido.
+/// On Error returns a NoCommitExisting si no se puede llegar a ese tipo
+pub fn peel(hash: &str, tipo: &str, repo: &Repository) -> Result<String, GitrError> {
+    let mut hash = hash.to_string();
+    loop {
+        let (tipo_actual, contenido) = leer_objeto(&hash, repo)?;
+        if tipo_actual == tipo {
+            return Ok(hash);
+        }
+        let siguiente = match tipo_actual.as_str() {
+            "tag" => campo(&contenido, "object").into_iter().next(),
+            "commit" if tipo == "tree" => campo(&contenido, "tree").into_iter().next(),
+            _ => None,
+        };
+        hash = match siguiente {
+            Some(siguiente) => siguiente,
+            None => return Err(GitrError::NoCommitExisting(format!("{}^{{{}}}", hash, tipo))),
+        };
+    }
+}
+
+// ^{}: sigue los tags anotados hasta el primer objeto que no sea tag
+fn pelar_tags(hash: &str, repo: &Repository) -> Result<String, GitrError> {
+    let mut hash = hash.to_string();
+    loop {
+        let (tipo, contenido) = leer_objeto(&hash, repo)?;
+        match (tipo.as_str(), campo(&contenido, "object").into_iter().next()) {
+            ("tag", Some(objeto)) => hash = objeto,
+            _ => return Ok(hash),
+        }
+    }
+}
+
+// n-esimo padre del commit (desde 1), None si no lo tiene
+fn padre(commit: &str, n: usize, repo: &Repository) -> Result<Option<String>, GitrError> {
+    let (_, contenido) = leer_objeto(commit, repo)?;
+    Ok(campo(&contenido, "parent").into_iter().nth(n - 1))
+}
+
+// valores de las lineas "<nombre> <valor>" del header de un commit o tag
+fn campo(contenido: &str, nombre: &str) -> Vec<String> {
+    contenido
+        .lines()
+        .take_while(|linea| !linea.is_empty())
+        .filter_map(|linea| linea.strip_prefix(nombre)?.strip_prefix(' '))
+        .map(|valor| valor.to_string())
+        .collect()
+}
+
+// (tipo, contenido) de un objeto del repo
+fn leer_objeto(hash: &str, repo: &Repository) -> Result<(String, String), GitrError> {
+    let data = repo.objects().read(hash)?;
+    let data = String::from_utf8_lossy(&data);
+    match data.split_once('\0') {
+        Some((header, contenido)) => {
+            let tipo = header.split(' ').next().unwrap_or_default();
+            Ok((tipo.to_string(), contenido.to_string()))
+        }
+        None => Err(GitrError::FileReadError(hash.to_string())),
+    }
+}
+
+// HEAD, una ref, un hash (completo o abreviado) o <branch>@{upstream}
+fn resolver_base(base: &str, revision: &str, repo: &Repository) -> Result<String, GitrError> {
+    if let Some((nombre, upstream)) = base.split_once("@{") {
+        if upstream != "u}" && upstream != "upstream}" {
+            return Err(GitrError::NoCommitExisting(revision.to_string()));
+        }
+        return resolver_upstream(nombre, repo);
+    }
+    if base == "HEAD" || base == "@" {
+        return file_manager::get_current_commit(repo);
+    }
+    if let Some(hash) = resolver_ref(base, repo) {
+        return Ok(hash);
+    }
+    resolver_hash(base, revision, repo)
+}
+
+// la branch remota que sigue a la branch (la actual si nombre es vacio o HEAD)
+fn resolver_upstream(nombre: &str, repo: &Repository) -> Result<String, GitrError> {
+    let branch = match nombre {
+        "" | "HEAD" | "@" => {
+            let head = file_manager::get_head(repo)?;
+            match head.strip_prefix("refs/heads/") {
+                Some(branch) => branch.to_string(),
+                None => return Err(GitrError::NoUpstream(head)),
+            }
+        }
+        nombre => nombre.to_string(),
+    };
+    let upstream = format!("refs/remotes/{}/{}", UPSTREAM_REMOTE, branch);
+    leer_ref(&upstream, repo).ok_or(GitrError::NoUpstream(branch))
+}
+
+// busca el nombre en el mismo orden que git: refs/<nombre>, tags, branches y remotes
+fn resolver_ref(nombre: &str, repo: &Repository) -> Option<String> {
+    if nombre.is_empty() || nombre.split('/').any(|parte| parte.is_empty() || parte == "..") {
+        return None;
+    }
+    let mut candidatos = vec![];
+    if nombre.starts_with("refs/") {
+        candidatos.push(nombre.to_string());
+    }
+    candidatos.push(format!("refs/{}", nombre));
+    candidatos.push(format!("refs/tags/{}", nombre));
+    candidatos.push(format!("refs/heads/{}", nombre));
+    candidatos.push(format!("refs/remotes/{}", nombre));
+    candidatos.push(format!("refs/remotes/{}/HEAD", nombre));
+    candidatos.iter().find_map(|candidato| leer_ref(candidato, repo))
+}
+
+// hash al que apunta la ref, siguiendo las refs simbolicas ("ref: refs/...")
+fn leer_ref(referencia: &str, repo: &Repository) -> Option<String> {
+    let path = repo.git_path(referencia);
+    if !Path::new(&path).is_file() {
+        return None;
+    }
+    let contenido = file_manager::read_file(path).ok()?;
+    let contenido = contenido.trim();
+    match contenido.strip_prefix("ref: ") {
+        Some(destino) if destino != referencia => leer_ref(destino, repo),
+        Some(_) => None,
+        None if es_hex(contenido) && contenido.len() == 40 => Some(contenido.to_string()),
+        None => None,
+    }
+}
+
+// un hash completo que exista o un prefijo de al menos MIN_ABBREV que identifique un solo objeto
+fn resolver_hash(prefijo: &str, revision: &str, repo: &Repository) -> Result<String, GitrError> {
+    let prefijo = prefijo.to_lowercase();
+    if prefijo.len() < MIN_ABBREV || prefijo.len() > 40 || !es_hex(&prefijo) {
+        return Err(GitrError::NoCommitExisting(revision.to_string()));
+    }
+    if prefijo.len() == 40 {
+        return match repo.objects().contains(&prefijo) {
+            true => Ok(prefijo),
+            false => Err(GitrError::NoCommitExisting(revision.to_string())),
+        };
+    }
+    let mut candidatos = repo
+        .objects()
+        .iter()
+        .filter(|hash| hash.starts_with(&prefijo))
+        .collect::<Vec<String>>();
+    candidatos.sort();
+    candidatos.dedup();
+    match candidatos.len() {
+        0 => Err(GitrError::NoCommitExisting(revision.to_string())),
+        1 => Ok(candidatos.remove(0)),
+        _ => Err(GitrError::AmbiguousRevision(prefijo, candidatos)),
+    }
+}
+
+fn es_hex(texto: &str) -> bool {
+    texto.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_database::hash_object_data;
+
+    fn guardar(repo: &Repository, tipo: &str, contenido: &str) -> String {
+        let data = format!("{} {}\0{}", tipo, contenido.len(), contenido);
+        repo.objects().write(data.as_bytes()).unwrap()
+    }
+
+    fn commit(repo: &Repository, tree: &str, padres: &[&str], mensaje: &str) -> String {
+        let mut contenido = format!("tree {}\n", tree);
+        for padre in padres {
+            contenido.push_str(&format!("parent {}\n", padre));
+        }
+        contenido.push_str("author test <test@gmail.com> 0 -0300\n");
+        contenido.push_str("committer test <test@gmail.com> 0 -0300\n\n");
+        contenido.push_str(mensaje);
+        guardar(repo, "commit", &contenido)
+    }
+
+    #[test]
+    fn test01_sufijos_de_ancestros_padres_y_tipos() {
+        let repo = Repository::in_memory();
+        let tree = guardar(&repo, "tree", "");
+        let primero = commit(&repo, &tree, &[], "primero\n");
+        let segundo = commit(&repo, &tree, &[&primero], "segundo\n");
+        let lateral = commit(&repo, &tree, &[&primero], "lateral\n");
+        let merge = commit(&repo, &tree, &[&segundo, &lateral], "merge\n");
+        let tag = guardar(
+            &repo,
+            "tag",
+            &format!("object {}\ntype commit\ntag v1\ntagger test <t@t> 0 -0300\n\nv1\n", merge),
+        );
+
+        assert_eq!(rev_parse(&merge, &repo).unwrap(), merge);
+        assert_eq!(rev_parse(&format!("{}~1", merge), &repo).unwrap(), segundo);
+        assert_eq!(rev_parse(&format!("{}~2", merge), &repo).unwrap(), primero);
+        assert_eq!(rev_parse(&format!("{}^", merge), &repo).unwrap(), segundo);
+        assert_eq!(rev_parse(&format!("{}^2", merge), &repo).unwrap(), lateral);
+        assert_eq!(rev_parse(&format!("{}^2~", merge), &repo).unwrap(), primero);
+        assert_eq!(rev_parse(&format!("{}^0", tag), &repo).unwrap(), merge);
+        assert_eq!(rev_parse(&format!("{}^{{}}", tag), &repo).unwrap(), merge);
+        assert_eq!(rev_parse(&format!("{}^{{tree}}", tag), &repo).unwrap(), tree);
+        assert_eq!(rev_parse_tree(&primero, &repo).unwrap(), tree);
+        assert_eq!(rev_parse_commit(&tag, &repo).unwrap(), merge);
+
+        assert!(rev_parse(&format!("{}^3", merge), &repo).is_err());
+        assert!(rev_parse(&format!("{}~3", merge), &repo).is_err());
+        assert!(rev_parse(&format!("{}^{{blob}}", merge), &repo).is_err());
+        assert!(rev_parse(&format!("{}^{{otro}}", merge), &repo).is_err());
+        assert!(rev_parse(&format!("{}~", tree), &repo).is_err());
+    }
+
+    #[test]
+    fn test02_hash_abreviado_unico_o_ambiguo() {
+        let repo = Repository::in_memory();
+        let hash = guardar(&repo, "blob", "contenido\n");
+        assert_eq!(rev_parse(&hash[..7], &repo).unwrap(), hash);
+        assert_eq!(rev_parse(&hash[..7].to_uppercase(), &repo).unwrap(), hash);
+        assert!(matches!(
+            rev_parse(&hash[..MIN_ABBREV - 1], &repo),
+            Err(GitrError::NoCommitExisting(_))
+        ));
+        assert!(matches!(
+            rev_parse("zzzzzzz", &repo),
+            Err(GitrError::NoCommitExisting(_))
+        ));
+
+        // busca dos blobs cuyos hashes compartan los primeros MIN_ABBREV digitos
+        let mut prefijos = std::collections::HashMap::new();
+        let (a, b) = (0..)
+            .find_map(|i| {
+                let data = format!("blob {}\0{}", i.to_string().len(), i);
+                let hash = hash_object_data(data.as_bytes());
+                let anterior = prefijos.insert(hash[..MIN_ABBREV].to_string(), i);
+                anterior.map(|anterior: i32| (anterior.to_string(), i.to_string()))
+            })
+            .unwrap();
+        let a = guardar(&repo, "blob", &a);
+        let b = guardar(&repo, "blob", &b);
+        match rev_parse(&a[..MIN_ABBREV], &repo) {
+            Err(GitrError::AmbiguousRevision(_, candidatos)) => assert_eq!(candidatos.len(), 2),
+            otro => panic!("se esperaba AmbiguousRevision: {:?}", otro),
+        }
+        let comun = a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count();
+        assert_eq!(rev_parse(&a[..comun + 1], &repo).unwrap(), a);
+        assert_eq!(rev_parse(&b[..comun + 1], &repo).unwrap(), b);
+    }
+}
@@ -21,7 +21,6 @@ use crate::file_manager;
 use crate::file_manager::contar_archivos_y_directorios;
 use crate::git_transport::pack_file::create_packfile;
 use crate::git_transport::pack_file::prepare_contents;
-use crate::git_transport::pack_file::PackFile;
 
 use crate::git_transport::ref_discovery;
 use crate::gitr_errors::GitrError;
@@ -29,6 +28,7 @@ use crate::logger::log_error;
 
 use crate::objects::commit::Commit;
 use crate::objects::pull_request::PullRequest;
+use crate::repository::Repository;
 
 
 /// Pone en fucionamiento el Servidor Gitr en la direccion de socket provista. Maneja cada cliente de manera concurrente.
@@ -179,17 +179,17 @@ committer cliente <test> 1702329287 -0300
 commit branch
 
 */
-fn build_json_from_commit(commit_hash: String, commit_raw_data:String, ruta_repo_server: String) -> Result<String, GitrError>{
+fn build_json_from_commit(commit_hash: String, commit_raw_data:String, repo: &Repository) -> Result<String, GitrError>{
     let commit_vec = commit_raw_data.split('\n').collect::<Vec<&str>>();
     
     let tree = commit_vec[0].split(' ').collect::<Vec<&str>>()[2];
-    let date = file_manager::get_commit_date(commit_hash.clone(), ruta_repo_server.clone())?;
-    let author = file_manager::get_commit_author(commit_hash.clone(), ruta_repo_server.clone())?;
-    let author_mail = file_manager::get_commit_author_mail(commit_hash.clone(), ruta_repo_server.clone())?;
-    let message = file_manager::get_commit_message(commit_hash.clone(), ruta_repo_server.clone())?;
+    let date = file_manager::get_commit_date(commit_hash.clone(), repo)?;
+    let author = file_manager::get_commit_author(commit_hash.clone(), repo)?;
+    let author_mail = file_manager::get_commit_author_mail(commit_hash.clone(), repo)?;
+    let message = file_manager::get_commit_message(commit_hash.clone(), repo)?;
     let message = message.trim_end();
-    let commiter = file_manager::get_commit_commiter(commit_hash.clone(), ruta_repo_server.clone())?;
-    let commiter_mail = file_manager::get_commit_commiter_mail(commit_hash.clone(), ruta_repo_server.clone())?;
+    let commiter = file_manager::get_commit_commiter(commit_hash.clone(), repo)?;
+    let commiter_mail = file_manager::get_commit_commiter_mail(commit_hash.clone(), repo)?;
 
 
     let json_message = vec![
@@ -270,7 +270,15 @@ fn handler_get_request(ruta: &str, mut stream: &TcpStream) -> std::io::Result<St
             if last_dentry == "commits" {
                 let pr = PullRequest::from_string(response_body.clone()).unwrap();
                 let branch_name = pr.get_branch_name();
-                let commits = match command_utils::branch_commits_list(branch_name.to_string(), ruta_repo_server.clone()) {
+                let repo = match Repository::open(&ruta_repo_server) {
+                    Ok(repo) => repo,
+                    Err(_) => {
+                        println!("Error al abrir el repositorio");
+                        stream.write_all("HTTP/1.1 422 Validation failed\r\n\r\n".as_bytes())?;
+                        return Ok("".to_string());
+                    }
+                };
+                let commits = match command_utils::branch_commits_list(branch_name.to_string(), &repo) {
                     Ok(commits) => commits,
                     Err(_) => {
                         println!("Error al obtener commits");
@@ -284,7 +292,7 @@ fn handler_get_request(ruta: &str, mut stream: &TcpStream) -> std::io::Result<St
                 response_body = String::new();
                 response_body.push('[');
                 for commit in commits {
-                    let commit_data = match file_manager::read_object(&commit, ruta_repo_server.clone(), false) {
+                    let commit_data = match file_manager::read_object(&commit, &repo) {
                         Ok(commit_data) => commit_data,
                         Err(_) => {
                             println!("Error al obtener commit data");
@@ -293,7 +301,7 @@ fn handler_get_request(ruta: &str, mut stream: &TcpStream) -> std::io::Result<St
                         }
                     };
 
-                    let json_message = match build_json_from_commit(commit.clone(), commit_data.clone(), ruta_repo_server.clone()) {
+                    let json_message = match build_json_from_commit(commit.clone(), commit_data.clone(), &repo) {
                         Ok(json_message) => json_message,
                         Err(e) => {
                             println!("Error al obtener json message = {:?}",e);
@@ -432,8 +440,16 @@ fn handle_put_request(request: &str, mut stream: TcpStream) -> std::io::Result<(
     let branch_name = pr.get_branch_name();
     
     let ruta_repo_server = ruta_full.split('/').collect::<Vec<&str>>()[..=2].join("/");
+    let repo = match Repository::open(&ruta_repo_server) {
+        Ok(repo) => repo,
+        Err(e) => {
+            println!("Error al abrir el repositorio: {:?}",e);
+            stream.write_all("HTTP/1.1 422 Validation failed\r\n\r\n".as_bytes())?;
+            return Ok(());
+        }
+    };
 
-    let (hubo_conflict, _ , archivos_conflict) = match commands_fn::merge_(master_name.clone(), branch_name.clone(), ruta_repo_server.clone()) {
+    let (hubo_conflict, _ , archivos_conflict) = match commands_fn::merge_(master_name.clone(), branch_name.clone(), &command_utils::OpcionesMerge::default(), &repo) {
         Ok((hubo_conflict, a,archivos_conflict)) => (hubo_conflict,a, archivos_conflict),
         Err(e) => {
             println!("Error al hacer merge: {:?}",e);
@@ -442,7 +458,7 @@ fn handle_put_request(request: &str, mut stream: TcpStream) -> std::io::Result<(
         }
     };
 
-    let commit_hash = match file_manager::get_current_commit(ruta_repo_server.clone()) {
+    let commit_hash = match file_manager::get_current_commit(&repo) {
         Ok(commit_hash) => commit_hash,
         Err(e) => {
             println!("Error al obtener el hash del commit: {:?}",e);
@@ -453,7 +469,7 @@ fn handle_put_request(request: &str, mut stream: TcpStream) -> std::io::Result<(
 
 
     
-    let commit_data = file_manager::read_object(&commit_hash, ruta_repo_server.clone(), false);
+    let commit_data = file_manager::read_object(&commit_hash, &repo);
     let commit = Commit::new_commit_from_data(commit_data.unwrap()).unwrap();
     
     let es_tipo_merge = commit.parents.len() > 1;
@@ -508,7 +524,7 @@ fn handle_put_request(request: &str, mut stream: TcpStream) -> std::io::Result<(
                 return Err(Error::new(std::io::ErrorKind::Other,"Error al cerrar PR (aux)"));
             }
         };
-        let merge_commit_hash = match file_manager::get_current_commit(cliente.clone()) {
+        let merge_commit_hash = match file_manager::open_current_repo(cliente.clone()).and_then(|repo| file_manager::get_current_commit(&repo)) {
             Ok(merge_commit_hash) => merge_commit_hash,
             Err(e) => {
                 stream.write_all("HTTP/1.1 422 Error al obtener el hash del commit\r\n\r\n".as_bytes())?;
@@ -531,7 +547,7 @@ fn check_branches_exist(pull_request: &PullRequest, ruta: &str, stream: &mut Tcp
     let branch_name = pull_request.get_branch_name();
     let base_name = pull_request.get_base_name();
     let ruta_repo_server = ruta.split('/').collect::<Vec<&str>>()[..=2].join("/");
-    let branches = match file_manager::get_branches(ruta_repo_server.clone()) {
+    let branches = match Repository::open(&ruta_repo_server).and_then(|repo| file_manager::get_branches(&repo)) {
         Ok(branches) => branches,
         Err(e) => {
             println!("Error al obtener branches: {:?}",e);
@@ -637,19 +653,19 @@ fn handle_pkt_line(request: String, mut stream: TcpStream) -> std::io::Result<()
     let elems = split_n_validate_elems(&request)?;
     let direc = elems[2].split_once(':').unwrap_or(("",elems[2])).1;
     let r_path = format!("server{direc}/repos/{}",elems[1]);
-    create_dirs(&r_path)?;
+    let repo = create_dirs(&r_path)?;
 
     // ########## REFERENCE DISCOVERY ##########
-    (refs_string, guardados_id) = ref_discovery::ref_discovery(&r_path)?;
+    (refs_string, guardados_id) = ref_discovery::ref_discovery(&repo)?;
     stream.write_all(refs_string.as_bytes())?;
 
     // ########## ELECCION DE COMANDO ##########
     match elems[0] {
         "git-upload-pack" => {
-            gitr_upload_pack(&mut stream, guardados_id, r_path)?;
+            gitr_upload_pack(&mut stream, guardados_id, &repo)?;
         } // Mandar al cliente
         "git-receive-pack" => {
-            gitr_receive_pack(&mut stream, r_path)?;
+            gitr_receive_pack(&mut stream, &repo)?;
         } // Recibir del Cliente
         _ => {
             stream.write_all("Error: comando git no reconocido".as_bytes())?;
@@ -668,19 +684,19 @@ fn handle_pkt_line(request: String, mut stream: TcpStream) -> std::io::Result<()
 /// # Recibe
 /// * stream: TcpStream ya conectado con el Gitr cliente
 /// * guardados_id: HashSet con los ids de los objetos guardados en el servidor
-/// * r_path: String con la ruta del repositorio del servidor
+/// * repo: &Repository bare del servidor
 /// # Devuelve
 /// Err(std::Error) si no se pudo establecer bien la conexion o algun proceso interno tambien da error.
 fn gitr_upload_pack(
     stream: &mut TcpStream,
     guardados_id: HashSet<String>,
-    r_path: String,
+    repo: &Repository,
 ) -> std::io::Result<()> {
     // ##########  PACKFILE NEGOTIATION ##########
     let (wants_id, haves_id) = packfile_negotiation(stream, guardados_id)?;
     // ########## PACKFILE DATA ##########
     if !wants_id.is_empty() {
-        snd_packfile(stream, wants_id, haves_id, r_path)?;
+        snd_packfile(stream, wants_id, haves_id, repo)?;
     }
 
     Ok(())
@@ -690,10 +706,10 @@ fn gitr_upload_pack(
 /// Incluye el Reference Update y el recibe el packfile de ser necesario.
 /// # Recibe
 /// * stream: TcpStream ya conectado con el Gitr cliente
-/// * r_path: String con la ruta del repositorio del servidor
+/// * repo: &Repository bare del servidor
 /// # Devuelve
 /// Err(std::Error) si no se pudo establecer bien la conexion o algun proceso interno tambien da error.
-fn gitr_receive_pack(stream: &mut TcpStream, r_path: String) -> std::io::Result<()> {
+fn gitr_receive_pack(stream: &mut TcpStream, repo: &Repository) -> std::io::Result<()> {
     // ##########  REFERENCE UPDATE ##########
     let mut buffer = [0; 1024];
 
@@ -705,10 +721,10 @@ fn gitr_receive_pack(stream: &mut TcpStream, r_path: String) -> std::io::Result<
         }
         // ########## *PACKFILE DATA ##########
         if pkt_needed(old.clone(), new.clone()) {
-            let (ids, content) = rcv_packfile_bruno(stream)?;
-            update_contents(ids, content, r_path.clone())?;
+            let pack = rcv_packfile_bruno(stream)?;
+            update_contents(&pack, repo)?;
         }
-        update_refs(old, new, names, r_path)?;
+        update_refs(old, new, names, repo)?;
 
         return Ok(());
     }
@@ -718,27 +734,19 @@ fn gitr_receive_pack(stream: &mut TcpStream, r_path: String) -> std::io::Result<
     ))
 }
 
-/// Actualiza los contenidos de los objetos en el servidor, creando o modificando lo que sea necesario.
+/// Guarda los objetos recibidos en el servidor: el packfile queda tal cual en
+/// objects/pack, junto con su indice (.idx).
 /// # Recibe
-/// * ids: Vec<String> con los ids de los objetos a actualizar
-/// * content: Vec<Vec<u8>> con los nuevos contenidos de los objetos a actualizar
-/// * r_path: String con la ruta del repositorio del servidor
+/// * pack: &[u8] con el packfile completo recibido del cliente
+/// * repo: &Repository bare del servidor
 /// # Devuelve
-/// Err(std::Error) si la longitud de los ids no se corresponde con la de los contenidos o si algun proceso interno tambien da error.
-fn update_contents(ids: Vec<String>, content: Vec<Vec<u8>>, r_path: String) -> std::io::Result<()> {
-    if ids.len() != content.len() {
-        return Err(Error::new(
-            std::io::ErrorKind::Other,
-            "Error: no coinciden los ids con los contenidos",
-        ));
-    }
-    for (i, id) in ids.into_iter().enumerate() {
-        let dir_path = format!("{}/objects/{}", r_path.clone(), id.split_at(2).0);
-        let _ = fs::create_dir(dir_path.clone());
-        let mut archivo = File::create(&format!("{}/{}", dir_path, id.split_at(2).1))?;
-        archivo.write_all(&content[i])?;
+/// Err(std::Error) si el packfile es invalido (checksum incorrecto, deltas sin base, etc)
+/// o si algun proceso interno tambien da error.
+fn update_contents(pack: &[u8], repo: &Repository) -> std::io::Result<()> {
+    match repo.objects().write_pack(pack) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::new(std::io::ErrorKind::InvalidData, e.to_string())),
     }
-    Ok(())
 }
 
 /// Envia el packfile al cliente.
@@ -746,7 +754,7 @@ fn update_contents(ids: Vec<String>, content: Vec<Vec<u8>>, r_path: String) -> s
 /// * stream: TcpStream ya conectado con el Gitr cliente
 /// * wants_id: Vec<String> con los ids de los commits o tags que el cliente quiere
 /// * haves_id: Vec<String> con los ids de los objetos que el cliente tiene
-/// * r_path: String con la ruta del repositorio del servidor
+/// * repo: &Repository bare del servidor
 /// # Devuelve
 /// Err(std::Error) si no se pudo preparar bien el packfile, si no se pudo obtener la data de alguno
 /// de los objetos o si algun proceso interno tambien da error.
@@ -754,16 +762,16 @@ fn snd_packfile(
     stream: &mut TcpStream,
     wants_id: Vec<String>,
     haves_id: Vec<String>,
-    r_path: String,
+    repo: &Repository,
 ) -> std::io::Result<()> {
     let mut contents: Vec<Vec<u8>> = vec![];
     let all_commits =
-        Commit::get_parents(wants_id.clone(), haves_id.clone(), r_path.clone()).unwrap_or(wants_id);
+        Commit::get_parents(wants_id.clone(), haves_id.clone(), repo).unwrap_or(wants_id);
     let wants_id: Vec<String> =
-        Commit::get_objects_from_commits(all_commits.clone(), haves_id, r_path.clone())
+        Commit::get_objects_from_commits(all_commits.clone(), haves_id, repo)
             .unwrap_or_default();
     for id in wants_id.clone() {
-        match file_manager::get_object_bytes(id, r_path.clone()) {
+        match file_manager::get_object_bytes(id, repo) {
             Ok(obj) => contents.push(obj),
             Err(_) => {
                 return Err(Error::new(
@@ -834,34 +842,16 @@ fn packfile_negotiation(
     Ok((wants_id, haves_id))
 }
 
-/// Recibe el packfile del cliente y lo descomprime.
+/// Recibe el packfile del cliente.
 /// # Recibe
 /// * stream: TcpStream ya conectado con el Gitr cliente
 /// # Devuelve
-/// Una tupla con:
-/// * hashes: Vec<String> con los ids de los objetos recibidos
-/// * contents: Vec<Vec<u8>> con los contenidos de los objetos recibidos
-/// O un Error si algun proceso interno tambien da error.
-fn rcv_packfile_bruno(stream: &mut TcpStream) -> std::io::Result<(Vec<String>, Vec<Vec<u8>>)> {
+/// Los bytes del packfile recibido (header, objetos y checksum)
+/// O un Error si no se pudo leer del stream.
+fn rcv_packfile_bruno(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
     let mut buffer = Vec::new();
     let _ = stream.read_to_end(&mut buffer)?;
-    let pack_file_struct = PackFile::new_from_server_packfile(&mut buffer);
-    let pk_file = match pack_file_struct {
-        Ok(pack_file) => pack_file,
-        _ => {
-            return Err(Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Error: no se pudo crear el packfile",
-            ))
-        }
-    };
-    let mut hashes: Vec<String> = Vec::new();
-    let mut contents: Vec<Vec<u8>> = Vec::new();
-    for object in pk_file.objects.iter() {
-        hashes.push(object.get_hash());
-        contents.push(object.get_data());
-    }
-    Ok((hashes, contents))
+    Ok(buffer)
 }
 
 /// Verifica si es necesario enviar el packfile al cliente.
@@ -892,7 +882,7 @@ fn pkt_needed(old: Vec<String>, new: Vec<String>) -> bool {
 /// * old: Vec<String> con los ids de los objetos que el servidor tiene.
 /// * new: Vec<String> con los ids de los objetos que el cliente quiere mandar.
 /// * names: Vec<String> con los nombres de las referencias que el cliente quiere mandar.
-/// * r_path: String con la ruta del repositorio del servidor
+/// * repo: &Repository bare del servidor
 /// # Devuelve
 /// Err(std::Error) si no se pudo crear o borrar alguna referencia, si el nombre de alguna referencia
 /// no es correcto o si algun proceso interno tambien da error.
@@ -900,11 +890,11 @@ fn update_refs(
     old: Vec<String>,
     new: Vec<String>,
     names: Vec<String>,
-    r_path: String,
+    repo: &Repository,
 ) -> std::io::Result<()> {
     let nul_obj = "0000000000000000000000000000000000000000";
     for i in 0..old.len() {
-        let path = r_path.clone() + "/" + &names[i];
+        let path = repo.git_path(&names[i]);
         if old[i] == nul_obj && new[i] != nul_obj {
             // crear referencia
             let mut new_file = File::create(&path)?;
@@ -1099,47 +1089,28 @@ fn split_n_validate_elems(pkt_line: &str) -> std::io::Result<Vec<&str>> {
     ))
 }
 
-/// Crea los directorios y archivos necesarios para el repositorio del servidor.
+/// Crea (o abre, si ya existe) el repositorio bare del servidor junto con su carpeta de pulls.
 /// # Recibe
 /// * r_path: &str con la ruta del repositorio del servidor
 /// # Devuelve
-/// Err(std::io::Error) si algun proceso interno tambien da error o el repositorio ya existe.
-fn create_dirs(r_path: &str) -> std::io::Result<()> {
-    let p_str = r_path.to_string();
-    if Path::new(&p_str).exists() {
-        return Ok(())
+/// El Repository abierto o Err(std::io::Error) si algun proceso interno da error.
+fn create_dirs(r_path: &str) -> std::io::Result<Repository> {
+    let repo = match Repository::init_bare(r_path) {
+        Ok(repo) => repo,
+        Err(e) => return Err(Error::new(std::io::ErrorKind::Other, e.to_string())),
+    };
+    let pulls = repo.git_path("pulls");
+    if !Path::new(&pulls).exists() {
+        fs::create_dir(pulls)?;
     }
-    fs::create_dir_all(p_str.clone())?;
-
-    write_file(
-        p_str.clone() + "/HEAD",
-        "ref: refs/heads/master".to_string(),
-    )?;
-    fs::create_dir(p_str.clone() + "/refs")?;
-    fs::create_dir(p_str.clone() + "/refs/heads")?;
-    fs::create_dir(p_str.clone() + "/refs/tags")?;
-    fs::create_dir(p_str.clone() + "/objects")?;
-    fs::create_dir(p_str.clone() + "/pulls")?;
-    Ok(())
-}
-
-/// Escribe un archivo con el texto provisto.
-/// # Recibe
-/// * path: String con la ruta del archivo a crear
-/// * text: String con el texto a escribir en el archivo
-/// # Devuelve
-/// Err(std::io::Error) si algun proceso interno tambien da error.
-fn write_file(path: String, text: String) -> std::io::Result<()> {
-    let mut archivo = File::create(path)?;
-    archivo.write_all(text.as_bytes())?;
-    Ok(())
+    Ok(repo)
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::git_transport::pack_file;
+    use crate::git_transport::pack_index::PackIndex;
 
     #[test]
     #[serial_test::serial]
@@ -1210,7 +1181,7 @@ mod tests {
     #[serial_test::serial]
     fn test06_update_refs() {
         let r_path = "remote_repo";
-        let _ = create_dirs(r_path);
+        let repo = create_dirs(r_path).unwrap();
         assert!(fs::metadata(format!("{}/refs/heads/debug", r_path)).is_err());
         assert!(fs::metadata(format!("{}/refs/heads/master", r_path)).is_err());
         // caso de creacion de archivo
@@ -1226,7 +1197,7 @@ mod tests {
             "refs/heads/debug".to_string(),
             "refs/heads/master".to_string(),
         ];
-        update_refs(old.clone(), new.clone(), names, r_path.to_string()).unwrap();
+        update_refs(old.clone(), new.clone(), names, &repo).unwrap();
         assert!(pkt_needed(old, new));
         assert!(fs::metadata(format!("{}/refs/heads/debug", r_path)).is_ok());
         assert!(fs::metadata(format!("{}/refs/heads/master", r_path)).is_ok());
@@ -1252,7 +1223,7 @@ mod tests {
             "refs/heads/debug".to_string(),
             "refs/heads/master".to_string(),
         ];
-        update_refs(old.clone(), new.clone(), names, r_path.to_string()).unwrap();
+        update_refs(old.clone(), new.clone(), names, &repo).unwrap();
         assert!(pkt_needed(old, new));
         assert!(fs::metadata(format!("{}/refs/heads/debug", r_path)).is_ok());
         assert!(fs::metadata(format!("{}/refs/heads/master", r_path)).is_ok());
@@ -1277,7 +1248,7 @@ mod tests {
             "refs/heads/debug".to_string(),
             "refs/heads/master".to_string(),
         ];
-        update_refs(old.clone(), new.clone(), names, r_path.to_string()).unwrap();
+        update_refs(old.clone(), new.clone(), names, &repo).unwrap();
         assert!(!pkt_needed(old, new));
         assert!(fs::metadata(format!("{}/refs/heads/debug", r_path)).is_err());
         assert!(fs::metadata(format!("{}/refs/heads/master", r_path)).is_err());
@@ -1286,33 +1257,40 @@ mod tests {
     #[test]
     #[serial_test::serial]
     fn test07_update_contents_n_get_object() {
-        let r_path = "remote_repo";
-        let _ = create_dirs(r_path);
-        let ids = vec![
-            "74730d410fcb6603ace96f1dc55ea6196122532d".to_string(),
-            "5a3f6be755bbb7deae50065988cbfa1ffa9ab68a".to_string(),
-        ];
-        let content: Vec<Vec<u8>> = vec![
-            pack_file::code("Hola mundo".to_string().as_bytes()).unwrap(),
-            pack_file::code("Chau mundo".to_string().as_bytes()).unwrap(),
-        ];
-        update_contents(ids, content, r_path.to_string()).unwrap();
+        let repo = create_dirs("remote_repo").unwrap();
+        let pack = create_packfile(prepare_contents(vec![
+            b"blob 10\0Hola mundo".to_vec(),
+            b"blob 10\0Chau mundo".to_vec(),
+        ]))
+        .unwrap();
+        update_contents(&pack, &repo).unwrap();
         assert_eq!(
             file_manager::get_object(
-                "74730d410fcb6603ace96f1dc55ea6196122532d".to_string(),
-                r_path.to_string()
+                "37d8675fc0a706d4ea62f38b41469bf3fa9c5ed9".to_string(),
+                &repo
             )
             .unwrap(),
-            "Hola mundo"
+            "blob 10\0Hola mundo"
         );
         assert_eq!(
             file_manager::get_object(
-                "5a3f6be755bbb7deae50065988cbfa1ffa9ab68a".to_string(),
-                r_path.to_string()
+                "06ae662f3a48ae0354f4eaec7a03008a63b2dc4b".to_string(),
+                &repo
             )
             .unwrap(),
-            "Chau mundo"
+            "blob 10\0Chau mundo"
         );
+        // el pack queda guardado con su indice, sin objetos sueltos
+        let checksum = PackIndex::from_pack(&pack).unwrap().pack_checksum();
+        let pack_name = repo.git_path(&format!("objects/pack/pack-{}", checksum));
+        assert!(Path::new(&format!("{}.pack", pack_name)).exists());
+        assert!(Path::new(&format!("{}.idx", pack_name)).exists());
+        assert!(!Path::new(&repo.git_path("objects/37")).exists());
+
+        let mut corrupto = pack.clone();
+        let ultimo = corrupto.len() - 1;
+        corrupto[ultimo] ^= 0xff;
+        assert!(update_contents(&corrupto, &repo).is_err());
     }
 }
 
@@ -1339,7 +1317,7 @@ mod http_tests{
         let cliente = "cliente".to_string();
         let flags = vec!["repo_tests_http".to_string()];
         commands_fn::init(flags, cliente.clone()).unwrap();
-        let _ = write_file(
+        let _ = file_manager::write_file(
             (cliente.clone() + "/gitrconfig").to_string(),
             "[user]\n\tname = test\n\temail = test@gmail.com".to_string(),
         );
@@ -1577,8 +1555,8 @@ mod http_tests{
         println!("OUTPUT TEST 06{:?}", output);
 
         assert!(output.contains("HTTP/1.1 200 application/json\r\n\r\n"));
-        assert!(output.contains("\"author\":{\"name\":\"cliente\",\"email\":\"<test>\""));
-        assert!(output.contains(r#""committer":{"name":"cliente","email":"<test>""#));
+        assert!(output.contains("\"author\":{\"name\":\"test\",\"email\":\"<test@gmail.com>\""));
+        assert!(output.contains(r#""committer":{"name":"test","email":"<test@gmail.com>""#));
         assert!(output.contains(r#""message":"commit branch""#));
         assert!(output.contains(r#""tree":{"sha":"7e3f1eda8d09c76b01845520767ff1da6d51d470"}""#));
         assert!(output.contains(r#""message":"commit base""#));
@@ -1617,7 +1595,7 @@ mod merge_pr_tests{
         let cliente = "cliente".to_string();
         let flags = vec!["repo_tests_http".to_string()];
         commands_fn::init(flags, cliente.clone()).unwrap();
-        let _ = write_file(
+        let _ = file_manager::write_file(
             (cliente.clone() + "/gitrconfig").to_string(),
             "[user]\n\tname = test\n\temail = test@gmail.com".to_string(),
         );
@@ -1749,10 +1727,11 @@ mod merge_pr_tests{
 
         assert!(output.contains("HTTP/1.1 200 OK\r\n\r\n"));
         let id = read_file("server9418/repos/server_test/refs/heads/master".to_string()).unwrap();
+        let repo = Repository::open("server9418/repos/server_test").unwrap();
         assert!(
-            (file_manager::get_object(id.clone(), "server9418/repos/server_test".to_string()).unwrap().contains("Merge branch 'branch'"))
+            (file_manager::get_object(id.clone(), &repo).unwrap().contains("Merge branch 'branch'"))
             &&
-            (file_manager::get_object(id.clone(), "server9418/repos/server_test".to_string()).unwrap().matches("parent").count() == 2)
+            (file_manager::get_object(id.clone(), &repo).unwrap().matches("parent").count() == 2)
         );
 
 
@@ -6,7 +6,10 @@ use gitr::commands::commands_fn;
 use gitr::file_manager;
 use gitr::file_manager::*;
 use gitr::gitr_errors::GitrError;
+use gitr::index::IndexEntry;
 use gitr::objects::blob::Blob;
+use gitr::objects::commit::Commit;
+use gitr::rev_parse::rev_parse;
 use serial_test::serial;
 
 /*********************
@@ -40,6 +43,33 @@ fn test_init_exists() {
     fs::remove_dir_all("cliente_init_2").unwrap();
 }
 
+#[test]
+#[serial]
+fn test_username_sale_del_gitrconfig_o_del_cliente() {
+    let cliente = "cliente_username".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_username".to_string()], cliente.clone()).unwrap();
+    write_file(
+        cliente.clone() + "/gitrconfig",
+        "[user]\n\temail = test@gmail.com\n\tname = otro nombre\n".to_string(),
+    )
+    .unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    assert_eq!(get_current_username(&repo), "otro nombre");
+    assert_eq!(get_user_mail_from_config(&repo).unwrap(), "test@gmail.com");
+
+    // sin name en el gitrconfig se usa el nombre del cliente, como antes
+    write_file(
+        cliente.clone() + "/gitrconfig",
+        "[user]\n\temail = test@gmail.com\n".to_string(),
+    )
+    .unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    assert_eq!(get_current_username(&repo), cliente);
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
 // /*********************
 //   ADD TESTS
 // *********************/
@@ -59,16 +89,17 @@ fn test_add() {
     );
     commands_fn::add(vec!["blob1".to_string()], cliente.clone()).unwrap();
     commands_fn::add(vec!["blob2".to_string()], cliente.clone()).unwrap();
-    let hash1 = Blob::new("Hello, im blob 1".to_string())
+    let hash1 = Blob::new("Hello, im blob 1".as_bytes().to_vec())
         .unwrap()
         .get_hash();
-    let hash2 = Blob::new("Hello, im blob 2".to_string())
+    let hash2 = Blob::new("Hello, im blob 2".as_bytes().to_vec())
         .unwrap()
         .get_hash();
     assert!(Path::new(&(cliente.clone() + "/test_add_blob/gitr/index")).is_file());
-    let index = file_manager::read_index(cliente.clone()).unwrap();
-    assert!(index.contains(&hash1));
-    assert!(index.contains(&hash2));
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let index = file_manager::read_index(&repo).unwrap();
+    assert_eq!(index.get("blob1").unwrap().hash, hash1);
+    assert_eq!(index.get("blob2").unwrap().hash, hash2);
     let hash1_dir = (cliente.clone() + "/test_add_blob/gitr/objects/").to_string() + &hash1[..2];
     let hash2_dir = (cliente.clone() + "/test_add_blob/gitr/objects/").to_string() + &hash2[..2];
     assert!(Path::new(&hash1_dir).is_dir());
@@ -99,16 +130,17 @@ fn test_rm() {
     );
     commands_fn::add(vec!["blob1".to_string()], cliente.clone()).unwrap();
     commands_fn::add(vec!["blob2".to_string()], cliente.clone()).unwrap();
-    let hash1 = Blob::new("Hello, im blob 1".to_string())
+    let hash1 = Blob::new("Hello, im blob 1".as_bytes().to_vec())
         .unwrap()
         .get_hash();
-    let hash2 = Blob::new("Hello, im blob 2".to_string())
+    let hash2 = Blob::new("Hello, im blob 2".as_bytes().to_vec())
         .unwrap()
         .get_hash();
     assert!(Path::new(&(cliente.clone() + "/test_rm_blob/gitr/index")).is_file());
-    let index = file_manager::read_index(cliente.clone()).unwrap();
-    assert!(index.contains(&hash1));
-    assert!(index.contains(&hash2));
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let index = file_manager::read_index(&repo).unwrap();
+    assert_eq!(index.get("blob1").unwrap().hash, hash1);
+    assert_eq!(index.get("blob2").unwrap().hash, hash2);
     let hash1_dir = (cliente.clone() + "/test_rm_blob/gitr/objects/").to_string() + &hash1[..2];
     let hash2_dir = (cliente.clone() + "/test_rm_blob/gitr/objects/").to_string() + &hash2[..2];
     assert!(Path::new(&hash1_dir).is_dir());
@@ -119,9 +151,9 @@ fn test_rm() {
     assert!(Path::new(&hash2_file).is_file());
     commands_fn::rm(vec!["blob1".to_string()], cliente.clone()).unwrap();
     commands_fn::rm(vec!["blob2".to_string()], cliente.clone()).unwrap();
-    let index = file_manager::read_index(cliente.clone()).unwrap();
-    assert!(!index.contains(&hash1));
-    assert!(!index.contains(&hash2));
+    let index = file_manager::read_index(&repo).unwrap();
+    assert!(index.get("blob1").is_none());
+    assert!(index.get("blob2").is_none());
     fs::remove_dir_all(cliente).unwrap();
 }
 
@@ -134,7 +166,8 @@ fn test_ls_files_returns_empty_after_init() {
     let cliente = "cliente_ls_files_1".to_string();
     fs::create_dir_all(Path::new(&cliente)).unwrap();
     commands_fn::init(vec!["test_ls_files_empty".to_string()], cliente.clone()).unwrap();
-    let res = command_utils::get_ls_files_cached(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = command_utils::get_ls_files_cached(&repo).unwrap();
     assert!(res.is_empty());
     fs::remove_dir_all(cliente.clone()).unwrap();
 }
@@ -155,8 +188,9 @@ fn test_ls_files_stage_after_adding_files() {
     );
     commands_fn::add(vec!["blob1".to_string()], cliente.clone()).unwrap();
     commands_fn::add(vec!["blob2".to_string()], cliente.clone()).unwrap();
-    let res = read_index(cliente.clone()).unwrap();
-    let correct_res = String::from("100644 016a41a6a35d50d311286359f1a7611948a9c529 0 cliente_ls_files_2/test_ls_files_stage/blob1\n100644 18d74b139e1549bb6a96b281e6ac3a0ec9e563e8 0 cliente_ls_files_2/test_ls_files_stage/blob2");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = command_utils::get_ls_files_stage(&repo).unwrap();
+    let correct_res = String::from("100644 016a41a6a35d50d311286359f1a7611948a9c529 0\tblob1\n100644 18d74b139e1549bb6a96b281e6ac3a0ec9e563e8 0\tblob2\n");
     fs::remove_dir_all(cliente.clone()).unwrap();
     assert_eq!(res, correct_res);
 }
@@ -194,7 +228,8 @@ fn test_tag_lightweight() {
     let res =
         file_manager::read_file(cliente.clone() + "/test_tag_lightweight/gitr/refs/tags/tag1")
             .unwrap();
-    let current_commit = file_manager::get_current_commit(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let current_commit = file_manager::get_current_commit(&repo).unwrap();
     assert_eq!(res, current_commit);
     fs::remove_dir_all(cliente.clone()).unwrap();
 }
@@ -228,7 +263,8 @@ fn test_tag_delete() {
     commands_fn::tag(vec!["tag1".to_string()], cliente.clone()).unwrap();
     let res =
         file_manager::read_file(cliente.clone() + "/test_tag_delete/gitr/refs/tags/tag1").unwrap();
-    let current_commit = file_manager::get_current_commit(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let current_commit = file_manager::get_current_commit(&repo).unwrap();
     assert_eq!(res, current_commit);
     commands_fn::tag(vec!["-d".to_string(), "tag1".to_string()], cliente.clone()).unwrap();
     let res = file_manager::read_file(cliente.clone() + "/test_tag_delete/gitr/refs/tags/tag1");
@@ -274,12 +310,8 @@ fn test_tag_annotated() {
     .unwrap();
     let res = file_manager::read_file(cliente.clone() + "/test_tag_annotated/gitr/refs/tags/tag1")
         .unwrap();
-    let object = file_manager::read_object(
-        &res,
-        file_manager::get_current_repo(cliente.clone()).unwrap(),
-        true,
-    )
-    .unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let object = file_manager::read_object(&res, &repo).unwrap();
     let object_type = object.split(' ').collect::<Vec<&str>>()[0];
     assert_eq!(object_type, "tag");
     fs::remove_dir_all(cliente.clone()).unwrap();
@@ -315,7 +347,8 @@ fn test_branch_newbranch() {
     )
     .unwrap();
     commands_fn::branch(vec!["branch1".to_string()], cliente.clone()).unwrap();
-    let res = print_branches(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = print_branches(&repo).unwrap();
     let correct_res = String::from("* \x1b[92mmaster\x1b[0m\nbranch1\n");
     assert_eq!(res, correct_res);
     fs::remove_dir_all(cliente.clone()).unwrap();
@@ -328,7 +361,8 @@ fn test_branch_no_commit() {
     fs::create_dir_all(Path::new(&cliente)).unwrap();
     commands_fn::init(vec!["test_branch_no_commit".to_string()], cliente.clone()).unwrap();
     let error = commands_fn::branch(vec!["branch1".to_string()], cliente.clone()).unwrap_err();
-    let res = print_branches(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = print_branches(&repo).unwrap();
     let correct_res = String::from("");
     assert_eq!(res, correct_res);
     assert!(matches!(error, GitrError::NoCommitExisting(_)));
@@ -398,7 +432,8 @@ fn test_branch_delete() {
     )
     .unwrap();
     commands_fn::branch(vec!["branch1".to_string()], cliente.clone()).unwrap();
-    let res = print_branches(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = print_branches(&repo).unwrap();
     let correct_res = String::from("* \x1b[92mmaster\x1b[0m\nbranch1\n");
     assert_eq!(res, correct_res);
     commands_fn::branch(
@@ -406,7 +441,7 @@ fn test_branch_delete() {
         cliente.clone(),
     )
     .unwrap();
-    let res = print_branches(cliente.clone()).unwrap();
+    let res = print_branches(&repo).unwrap();
     let correct_res = String::from("* \x1b[92mmaster\x1b[0m\n");
     assert_eq!(res, correct_res);
     fs::remove_dir_all(cliente.clone()).unwrap();
@@ -487,7 +522,8 @@ fn test_branch_move() {
         cliente.clone(),
     )
     .unwrap();
-    let res = print_branches(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = print_branches(&repo).unwrap();
     let correct_res = String::from("branch2\n* \x1b[92mmaster\x1b[0m\n");
     fs::remove_dir_all(cliente.clone()).unwrap();
     assert_eq!(res, correct_res);
@@ -507,7 +543,8 @@ fn test_hash_object() {
         "Hello, im blob 1".to_string(),
     );
     let correct_hash = "016a41a6a35d50d311286359f1a7611948a9c529";
-    let res = get_object_hash(cliente.clone(), &mut ("blob1").to_string(), false).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = get_object_hash(&repo, &mut ("blob1").to_string(), false).unwrap();
     fs::remove_dir_all(cliente.clone()).unwrap();
     assert_eq!(res, correct_hash);
 }
@@ -530,13 +567,14 @@ fn test_cat_file() {
         "Hello, im blob 2".to_string(),
     );
     commands_fn::add(vec!["blob1".to_string()], cliente.clone()).unwrap();
-    let hash1 = Blob::new("Hello, im blob 1".to_string())
+    let hash1 = Blob::new("Hello, im blob 1".as_bytes().to_vec())
         .unwrap()
         .get_hash();
-    let res = _cat_file(vec!["-p".to_string(), hash1.clone()], cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let res = _cat_file(vec!["-p".to_string(), hash1.clone()], &repo).unwrap();
     let correct_res = String::from("Hello, im blob 1");
     assert_eq!(res, correct_res);
-    let res = _cat_file(vec!["-t".to_string(), hash1], cliente.clone()).unwrap();
+    let res = _cat_file(vec!["-t".to_string(), hash1], &repo).unwrap();
     let correct_res = String::from("blob");
     assert_eq!(res, correct_res);
     fs::remove_dir_all(cliente.clone()).unwrap();
@@ -650,11 +688,11 @@ fn merge_con_archivos_test_1_conflict_trivial() {
         "\n",
         "linea agregada de branch\n",
         "\n",
-        "<<<<<<< HEAD\n",
+        "<<<<<<< ours\n",
         "linea de origin\n",
         "=======\n",
         "linea de branch\n",
-        ">>>>>>> BRANCH\n",
+        ">>>>>>> theirs\n",
         "\n",
         "\n",
         "linea agregada de origin\n",
@@ -763,7 +801,7 @@ fn merge_con_archivos_test_3_conflict_multilinea() {
         "\n",
         "\n",
         "\n",
-        "<<<<<<< HEAD\n",
+        "<<<<<<< ours\n",
         "linea de origin 1\n",
         "linea de origin 2\n",
         "linea de origin 3\n",
@@ -773,7 +811,7 @@ fn merge_con_archivos_test_3_conflict_multilinea() {
         "linea de branch 3\n",
         "linea de branch 4\n",
         "linea de branch 5\n",
-        ">>>>>>> BRANCH\n",
+        ">>>>>>> theirs\n",
         "\n",
         "\n",
         "chau\n",
@@ -882,7 +920,7 @@ fn merge_con_archivos_test_4_multiples_conflicts_multilinea() {
         "\n",
         "\n",
         "\n",
-        "<<<<<<< HEAD\n",
+        "<<<<<<< ours\n",
         "linea de origin 1\n",
         "linea de origin 2\n",
         "linea de origin 3\n",
@@ -890,15 +928,15 @@ fn merge_con_archivos_test_4_multiples_conflicts_multilinea() {
         "linea de branch 1\n",
         "linea de branch 2\n",
         "linea de branch 3\n",
-        ">>>>>>> BRANCH\n",
+        ">>>>>>> theirs\n",
         "linea igual para todos\n",
-        "<<<<<<< HEAD\n",
+        "<<<<<<< ours\n",
         "linea de origin 4\n",
         "linea de origin 5\n",
         "linea de origin 6\n",
         "=======\n",
         "linea de branch 4\n",
-        ">>>>>>> BRANCH\n",
+        ">>>>>>> theirs\n",
         "\n",
         "chau\n",
     ]
@@ -1000,20 +1038,20 @@ fn merge_con_archivos_test_5_ejemplo_de_codigo() {
     let archivo_esperado = vec![
         "fn main() {\n",
         "    let a = 1;\n",
-        "<<<<<<< HEAD\n",
+        "<<<<<<< ours\n",
         "    let origin_variable = 2;\n",
         "=======\n",
         "    let branch_variable = 2;\n",
-        ">>>>>>> BRANCH\n",
+        ">>>>>>> theirs\n",
         "\n",
         "    if a == b {\n",
         "        println!(\"iguales\");\n",
-        "<<<<<<< HEAD\n",
+        "<<<<<<< ours\n",
         "        let res = origin_function();\n",
         "=======\n",
         "        let res = branch_function();\n",
         "        println!(\"res: {}\", res);\n",
-        ">>>>>>> BRANCH\n",
+        ">>>>>>> theirs\n",
         "        let res = origin_function();\n",
         "    } else {\n",
         "        println!(\"distintos\");\n",
@@ -1029,6 +1067,618 @@ fn merge_con_archivos_test_5_ejemplo_de_codigo() {
     delete_repo("cliente/test".to_string());
 }
 
+// repo nuevo en cliente/nombre con un usuario configurado y config_extra agregado al
+// gitrconfig. Devuelve el path del work tree
+fn crear_repo(cliente: &String, nombre: &str, config_extra: &str) -> String {
+    let _ = fs::remove_dir_all(cliente);
+    fs::create_dir_all(Path::new(cliente)).unwrap();
+    commands_fn::init(vec![nombre.to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string() + config_extra,
+    );
+    cliente.clone() + "/" + nombre
+}
+
+// escribe los archivos (paths relativos al work tree), agrega todo y commitea
+fn commitear(cliente: &String, archivos: &[(&str, &str)], mensaje: &str) {
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    for (path, data) in archivos {
+        let path = repo.work_path(path);
+        fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+        write_file(path, data.to_string()).unwrap();
+    }
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), format!("\"{}\"", mensaje)],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_state_persistido_abort_y_commit() {
+    let cliente = "cliente_merge_state".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_state", "");
+    let archivo = work_tree.clone() + "/archivo";
+    commitear(&cliente, &[("archivo", "hola\nbase\nchau\n")], "base");
+    commands_fn::checkout(vec!["-b".to_string(), "branch".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("archivo", "hola\nbranch\nchau\n")], "branch");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("archivo", "hola\nmaster\nchau\n")], "master");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let master = get_current_commit(&repo).unwrap();
+    let branch = rev_parse("branch", &repo).unwrap();
+
+    // el merge con conflictos queda guardado en el repo
+    let (hubo_conflict, _, _) = commands_fn::merge(vec!["branch".to_string()], cliente.clone()).unwrap();
+    assert!(hubo_conflict);
+    assert_eq!(read_merge_head(&repo).unwrap(), Some(branch.clone()));
+    assert_eq!(read_file(repo.git_path("MERGE_MSG")).unwrap(), "Merge branch 'branch'");
+    assert_eq!(read_file(repo.git_path("ORIG_HEAD")).unwrap(), master);
+    assert!(get_status(&repo).unwrap().contains("You have unmerged paths."));
+    let res = commands_fn::merge(vec!["branch".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::MergeInProgress));
+
+    // merge --abort vuelve a ORIG_HEAD sin tocar los archivos sin trackear
+    let sin_trackear = work_tree.clone() + "/sin_trackear";
+    write_file(sin_trackear.clone(), "mio\n".to_string()).unwrap();
+    commands_fn::merge(vec!["--abort".to_string()], cliente.clone()).unwrap();
+    assert_eq!(read_merge_head(&repo).unwrap(), None);
+    assert_eq!(get_current_commit(&repo).unwrap(), master);
+    assert_eq!(read_file(archivo.clone()).unwrap(), "hola\nmaster\nchau\n");
+    assert_eq!(read_file(sin_trackear.clone()).unwrap(), "mio\n");
+    fs::remove_file(sin_trackear).unwrap();
+    assert!(!get_status(&repo).unwrap().contains("unmerged"));
+    let res = commands_fn::merge(vec!["--abort".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::NoMergeInProgress));
+
+    // el commit que resuelve el conflicto toma MERGE_HEAD como segundo padre
+    commands_fn::merge(vec!["branch".to_string()], cliente.clone()).unwrap();
+    write_file(archivo.clone(), "hola\nresuelto\nchau\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::merge(vec!["--continue".to_string()], cliente.clone()).unwrap();
+    assert_eq!(read_merge_head(&repo).unwrap(), None);
+    assert_eq!(rev_parse("HEAD^1", &repo).unwrap(), master);
+    assert_eq!(rev_parse("HEAD^2", &repo).unwrap(), branch);
+    let merge_commit = get_current_commit(&repo).unwrap();
+    assert!(get_commit_message(merge_commit, &repo).unwrap().contains("Merge branch 'branch'"));
+    let res = commands_fn::merge(vec!["--continue".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::NoMergeInProgress));
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_conflict_markers_y_stages() {
+    let cliente = "cliente_merge_stages".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_stages", "\n[merge]\n\tconflictstyle = diff3");
+    commitear(&cliente, &[("archivo", "hola\nbase\nchau\n")], "base");
+    commands_fn::checkout(vec!["-b".to_string(), "branch".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("archivo", "hola\nbranch\nchau\n"), ("nuevo", "de branch\n")], "branch");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("archivo", "hola\nmaster\nchau\n"), ("nuevo", "de master\n")], "master");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let hash_de = |revision: &str| rev_parse(revision, &repo).unwrap();
+    let blob_de = |revision: &str, path: &str| {
+        let commit = rev_parse(revision, &repo).unwrap();
+        get_commit_hashmap(commit, &repo).unwrap()[&repo.work_path(path)].clone()
+    };
+
+    let (hubo_conflict, _, _) = commands_fn::merge(vec!["branch".to_string()], cliente.clone()).unwrap();
+    assert!(hubo_conflict);
+    assert_eq!(
+        read_file(work_tree.clone() + "/archivo").unwrap(),
+        "hola\n<<<<<<< ours\nmaster\n||||||| base\nbase\n=======\nbranch\n>>>>>>> theirs\nchau\n"
+    );
+    // el archivo agregado en las dos branches queda con el conflicto en su path
+    assert_eq!(
+        read_file(work_tree.clone() + "/nuevo").unwrap(),
+        "<<<<<<< ours\nde master\n||||||| base\n=======\nde branch\n>>>>>>> theirs\n"
+    );
+    assert!(!Path::new(&(work_tree.clone() + "/nuevo_mergeado")).exists());
+
+    // los paths sin mergear quedan en el index con stages 1, 2 y 3
+    let unmerged = get_ls_files_unmerged(&repo).unwrap();
+    let esperado = format!(
+        "100644 {} 1\tarchivo\n100644 {} 2\tarchivo\n100644 {} 3\tarchivo\n100644 {} 2\tnuevo\n100644 {} 3\tnuevo\n",
+        blob_de("master~1", "archivo"),
+        blob_de("master", "archivo"),
+        blob_de("branch", "archivo"),
+        blob_de("master", "nuevo"),
+        blob_de("branch", "nuevo"),
+    );
+    assert_eq!(unmerged, esperado);
+    let status = get_status(&repo).unwrap();
+    assert!(status.contains("Unmerged paths:"));
+    assert!(status.contains("both modified:   archivo"));
+    assert!(status.contains("both added:      nuevo"));
+    assert!(!status.contains("modified   archivo"));
+    let res = commands_fn::commit(
+        vec!["-m".to_string(), "\"merge\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    );
+    assert_eq!(
+        res,
+        Err(GitrError::UnmergedPaths(vec!["archivo".to_string(), "nuevo".to_string()]))
+    );
+
+    // agregar los archivos resueltos saca los stages
+    write_file(work_tree.clone() + "/archivo", "hola\nresuelto\nchau\n".to_string()).unwrap();
+    write_file(work_tree.clone() + "/nuevo", "resuelto\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    assert_eq!(get_ls_files_unmerged(&repo).unwrap(), "");
+    assert!(get_status(&repo).unwrap().contains("All conflicts fixed but you are still merging."));
+    commands_fn::commit(vec![], "None".to_string(), cliente.clone()).unwrap();
+    assert_eq!(rev_parse("HEAD^2", &repo).unwrap(), hash_de("branch"));
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_de_archivos_agregados_y_borrados() {
+    let cliente = "cliente_merge_trees".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_trees", "");
+    let existe = |path: &str| Path::new(&(work_tree.clone() + "/" + path)).exists();
+    let borrar = |paths: &[&str]| {
+        for path in paths {
+            fs::remove_file(work_tree.clone() + "/" + path).unwrap();
+            commands_fn::rm(vec![path.to_string()], cliente.clone()).unwrap();
+        }
+    };
+    commitear(
+        &cliente,
+        &[
+            ("borrado_en_branch", "igual\n"),
+            ("borrado_en_master", "igual\n"),
+            ("borrado_en_los_dos", "igual\n"),
+            ("mod_del", "base\n"),
+            ("del_mod", "base\n"),
+        ],
+        "base",
+    );
+    commands_fn::checkout(
+        vec!["-b".to_string(), "branch".to_string()],
+        cliente.clone(),
+    )
+    .unwrap();
+    borrar(&["borrado_en_branch", "borrado_en_los_dos", "mod_del"]);
+    commitear(
+        &cliente,
+        &[
+            ("nuevo_en_branch", "nuevo\n"),
+            ("del_mod", "branch\n"),
+            ("dir/add_add", "branch\n"),
+        ],
+        "branch",
+    );
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    borrar(&["borrado_en_master", "borrado_en_los_dos", "del_mod"]);
+    commitear(
+        &cliente,
+        &[("mod_del", "master\n"), ("dir/add_add", "master\n")],
+        "master",
+    );
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+
+    let (hubo_conflict, _, archivos_conflict) =
+        commands_fn::merge(vec!["branch".to_string()], cliente.clone()).unwrap();
+    assert!(hubo_conflict);
+    assert_eq!(archivos_conflict, vec!["del_mod", "add_add", "mod_del"]);
+
+    // los cambios de un solo lado se aplican sin conflicto
+    assert!(!existe("borrado_en_branch"));
+    assert!(!existe("borrado_en_master"));
+    assert!(!existe("borrado_en_los_dos"));
+    assert_eq!(
+        read_file(work_tree.clone() + "/nuevo_en_branch").unwrap(),
+        "nuevo\n"
+    );
+
+    // modify/delete deja el archivo modificado y solo los stages de los lados que lo tienen
+    assert_eq!(
+        read_file(work_tree.clone() + "/mod_del").unwrap(),
+        "master\n"
+    );
+    assert_eq!(
+        read_file(work_tree.clone() + "/del_mod").unwrap(),
+        "branch\n"
+    );
+    let stages = |path: &str| -> Vec<String> {
+        get_ls_files_unmerged(&repo)
+            .unwrap()
+            .lines()
+            .filter(|linea| linea.ends_with(&format!("\t{}", path)))
+            .map(|linea| {
+                linea.split(' ').collect::<Vec<&str>>()[2]
+                    .split('\t')
+                    .next()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    };
+    assert_eq!(stages("mod_del"), vec!["1", "2"]);
+    assert_eq!(stages("del_mod"), vec!["1", "3"]);
+    assert_eq!(stages("dir/add_add"), vec!["2", "3"]);
+    let status = get_status(&repo).unwrap();
+    assert!(status.contains("deleted by them: mod_del"));
+    assert!(status.contains("deleted by us:   del_mod"));
+    assert!(status.contains("both added:      dir/add_add"));
+
+    // se resuelve borrando del_mod y quedandose con mod_del
+    fs::remove_file(work_tree.clone() + "/del_mod").unwrap();
+    commands_fn::rm(vec!["del_mod".to_string()], cliente.clone()).unwrap();
+    write_file(work_tree.clone() + "/dir/add_add", "resuelto\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(vec![], "None".to_string(), cliente.clone()).unwrap();
+    let merge_commit = get_current_commit(&repo).unwrap();
+    let mut paths: Vec<String> = get_commit_hashmap(merge_commit, &repo)
+        .unwrap()
+        .keys()
+        .map(|path| path.replace(&(work_tree.clone() + "/"), ""))
+        .collect();
+    paths.sort();
+    assert_eq!(paths, vec!["dir/add_add", "mod_del", "nuevo_en_branch"]);
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_de_renombres_y_cambios_de_modo() {
+    use std::os::unix::fs::PermissionsExt;
+    let cliente = "cliente_merge_renombres".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_renombres", "");
+    let renombrar = |viejo: &str, nuevo: &str| {
+        let nuevo = work_tree.clone() + "/" + nuevo;
+        fs::create_dir_all(Path::new(&nuevo).parent().unwrap()).unwrap();
+        fs::rename(work_tree.clone() + "/" + viejo, nuevo).unwrap();
+        commands_fn::rm(vec![viejo.to_string()], cliente.clone()).unwrap();
+    };
+    let ejecutable = |path: &str| {
+        let path = work_tree.clone() + "/" + path;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    };
+    commitear(
+        &cliente,
+        &[
+            ("a", "uno\ndos\ntres\n"),
+            ("c", "cuatro\ncinco\nseis\n"),
+            ("x.sh", "echo x\n"),
+            ("y.sh", "echo y\n"),
+        ],
+        "base",
+    );
+    commands_fn::checkout(
+        vec!["-b".to_string(), "branch".to_string()],
+        cliente.clone(),
+    )
+    .unwrap();
+    renombrar("a", "dir/b");
+    ejecutable("x.sh");
+    ejecutable("y.sh");
+    commitear(&cliente, &[("c", "CUATRO\ncinco\nseis\n")], "branch");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    renombrar("c", "d");
+    commitear(
+        &cliente,
+        &[("a", "UNO\ndos\ntres\n"), ("y.sh", "echo master\n")],
+        "master",
+    );
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+
+    let (hubo_conflict, _, _) =
+        commands_fn::merge(vec!["branch".to_string()], cliente.clone()).unwrap();
+    assert!(!hubo_conflict);
+
+    // los cambios de cada lado se mergean en el path que le dio el otro
+    let existe = |path: &str| Path::new(&(work_tree.clone() + "/" + path)).exists();
+    assert!(!existe("a"));
+    assert!(!existe("c"));
+    assert_eq!(
+        read_file(work_tree.clone() + "/dir/b").unwrap(),
+        "UNO\ndos\ntres\n"
+    );
+    assert_eq!(
+        read_file(work_tree.clone() + "/d").unwrap(),
+        "CUATRO\ncinco\nseis\n"
+    );
+
+    // el cambio de modo de branch se toma aunque master haya cambiado el contenido
+    assert_eq!(
+        read_file(work_tree.clone() + "/y.sh").unwrap(),
+        "echo master\n"
+    );
+    for path in ["x.sh", "y.sh"] {
+        let modo = fs::metadata(work_tree.clone() + "/" + path)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_ne!(modo & 0o111, 0);
+    }
+    let tree = get_main_tree(get_current_commit(&repo).unwrap(), &repo).unwrap();
+    let modos: Vec<(u32, String)> = index_de_tree(&tree, &repo)
+        .unwrap()
+        .entries()
+        .iter()
+        .map(|e| (e.mode, e.path.clone()))
+        .collect();
+    assert_eq!(
+        modos,
+        vec![
+            (0o100644, "d".to_string()),
+            (0o100644, "dir/b".to_string()),
+            (0o100755, "x.sh".to_string()),
+            (0o100755, "y.sh".to_string())
+        ]
+    );
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_no_pisa_cambios_locales() {
+    let cliente = "cliente_merge_locales".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_locales", "");
+    commitear(&cliente, &[("archivo", "base\n"), ("otro", "base\n")], "base");
+    commands_fn::checkout(
+        vec!["-b".to_string(), "branch".to_string()],
+        cliente.clone(),
+    )
+    .unwrap();
+    commitear(&cliente, &[("archivo", "branch\n"), ("nuevo", "branch\n")], "branch");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("otro", "master\n")], "master");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let master = get_current_commit(&repo).unwrap();
+    let merge = || commands_fn::merge(vec!["branch".to_string()], cliente.clone());
+
+    // un cambio local en un archivo que el merge reescribe
+    write_file(work_tree.clone() + "/archivo", "local\n".to_string()).unwrap();
+    let res = merge();
+    assert_eq!(
+        res,
+        Err(GitrError::MergeLocalChanges(vec!["archivo".to_string()]))
+    );
+    assert_eq!(
+        read_file(work_tree.clone() + "/archivo").unwrap(),
+        "local\n"
+    );
+    assert_eq!(read_merge_head(&repo).unwrap(), None);
+    assert_eq!(get_current_commit(&repo).unwrap(), master);
+
+    // un archivo sin trackear que el merge agregaria
+    write_file(work_tree.clone() + "/archivo", "base\n".to_string()).unwrap();
+    write_file(work_tree.clone() + "/nuevo", "mio\n".to_string()).unwrap();
+    let res = merge();
+    assert_eq!(
+        res,
+        Err(GitrError::MergeLocalChanges(vec!["nuevo".to_string()]))
+    );
+    assert_eq!(read_file(work_tree.clone() + "/nuevo").unwrap(), "mio\n");
+
+    // los cambios locales en archivos que el merge no toca no lo frenan
+    fs::remove_file(work_tree.clone() + "/nuevo").unwrap();
+    write_file(work_tree.clone() + "/otro", "local\n".to_string()).unwrap();
+    let (hubo_conflict, _, _) = merge().unwrap();
+    assert!(!hubo_conflict);
+    assert_eq!(
+        read_file(work_tree.clone() + "/archivo").unwrap(),
+        "branch\n"
+    );
+    assert_eq!(read_file(work_tree.clone() + "/otro").unwrap(), "local\n");
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_rebase_de_una_branch_con_dos_commits() {
+    let cliente = "cliente_rebase".to_string();
+    let work_tree = crear_repo(&cliente, "test_rebase", "");
+    commitear(
+        &cliente,
+        &[("a", "base\n"), ("b", "base\n"), ("c", "base\n")],
+        "base",
+    );
+    commands_fn::checkout(
+        vec!["-b".to_string(), "branch".to_string()],
+        cliente.clone(),
+    )
+    .unwrap();
+    commitear(&cliente, &[("a", "branch\n")], "branch 1");
+    commitear(&cliente, &[("b", "branch\n")], "branch 2");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("c", "master\n")], "master");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let master = get_current_commit(&repo).unwrap();
+
+    // el segundo commit se rebasea sobre el primero, que ya tiene el cambio de master en c
+    commands_fn::checkout(vec!["branch".to_string()], cliente.clone()).unwrap();
+    commands_fn::rebase(vec!["master".to_string()], cliente.clone()).unwrap();
+    for (path, data) in [("a", "branch\n"), ("b", "branch\n"), ("c", "master\n")] {
+        assert_eq!(read_file(work_tree.clone() + "/" + path).unwrap(), data);
+    }
+    let segundo = get_current_commit(&repo).unwrap();
+    let primero = get_parent_commit(segundo.clone(), &repo).unwrap();
+    assert_eq!(
+        get_parent_commit(primero[0].clone(), &repo).unwrap(),
+        vec![master]
+    );
+    assert!(get_commit_message(primero[0].clone(), &repo)
+        .unwrap()
+        .contains("branch 1"));
+    assert!(get_commit_message(segundo, &repo)
+        .unwrap()
+        .contains("branch 2"));
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_base_de_merges_cruzados() {
+    let cliente = "cliente_merge_base".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_base", "");
+    let merge = |revision: &str| {
+        let (hubo_conflict, _, _) =
+            commands_fn::merge(vec![revision.to_string()], cliente.clone()).unwrap();
+        assert!(!hubo_conflict);
+    };
+    write_file(work_tree.clone() + "/g", "base\n".to_string()).unwrap();
+    commitear(&cliente, &[("f", "base\n")], "base");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+
+    // cada lado mergea el commit del otro: quedan dos merge bases
+    commands_fn::checkout(vec!["-b".to_string(), "x".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("g", "x\n")], "x1");
+    let x1 = get_current_commit(&repo).unwrap();
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("f", "m\n")], "m1");
+    let m1 = get_current_commit(&repo).unwrap();
+    merge("x");
+    commands_fn::checkout(vec!["x".to_string()], cliente.clone()).unwrap();
+    merge(&m1);
+    commitear(&cliente, &[("f", "fx\n")], "x3");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("g", "gm\n")], "m3");
+
+    let master = rev_parse("master", &repo).unwrap();
+    let x = rev_parse("x", &repo).unwrap();
+    let mut bases = merge_bases(&master, &x, &repo).unwrap();
+    bases.sort();
+    let mut esperadas = vec![m1.clone(), x1.clone()];
+    esperadas.sort();
+    assert_eq!(bases, esperadas);
+    assert_eq!(merge_bases(&master, &m1, &repo).unwrap(), vec![m1.clone()]);
+    let mut todas: Vec<String> = get_merge_base("master", "x", true, &repo)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    todas.sort();
+    assert_eq!(todas, esperadas);
+    let una = get_merge_base("master", "x", false, &repo).unwrap();
+    assert!(esperadas.contains(&una.trim_end().to_string()));
+    assert_eq!(una.lines().count(), 1);
+    assert_eq!(
+        get_merge_base("master", &m1, false, &repo).unwrap(),
+        m1.clone() + "\n"
+    );
+    assert!(commands_fn::merge_base(vec!["--all".to_string()], cliente.clone()).is_err());
+
+    // con cualquiera de las dos bases sola habria conflicto, con la base virtual no
+    merge("x");
+    assert_eq!(read_file(work_tree.clone() + "/f").unwrap(), "fx\n");
+    assert_eq!(read_file(work_tree.clone() + "/g").unwrap(), "gm\n");
+    let parents = get_parent_commit(get_current_commit(&repo).unwrap(), &repo).unwrap();
+    assert_eq!(parents, vec![master, x]);
+
+    // un commit sin padres no tiene historia en comun con master
+    let tree = get_main_tree(get_current_commit(&repo).unwrap(), &repo).unwrap();
+    let huerfano = Commit::new(
+        tree,
+        vec!["None".to_string()],
+        "test".to_string(),
+        "test".to_string(),
+        "huerfano".to_string(),
+        &repo,
+    )
+    .unwrap();
+    huerfano.save(&repo).unwrap();
+    write_file(repo.git_path("refs/heads/huerfana"), huerfano.get_hash()).unwrap();
+    let master = get_current_commit(&repo).unwrap();
+    let no_relacionadas =
+        GitrError::UnrelatedHistories("master".to_string(), "huerfana".to_string());
+    assert_eq!(
+        get_merge_base("master", "huerfana", false, &repo).unwrap_err(),
+        no_relacionadas
+    );
+    assert_eq!(
+        commands_fn::merge(vec!["huerfana".to_string()], cliente.clone()).unwrap_err(),
+        no_relacionadas
+    );
+    assert_eq!(
+        commands_fn::rebase(vec!["huerfana".to_string()], cliente.clone()).unwrap_err(),
+        no_relacionadas
+    );
+    assert_eq!(get_current_commit(&repo).unwrap(), master);
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_merge_no_ff_ff_only_squash_y_estrategias() {
+    let cliente = "cliente_merge_opciones".to_string();
+    let work_tree = crear_repo(&cliente, "test_merge_opciones", "");
+    let merge = |flags: &[&str]| {
+        let flags = flags.iter().map(|flag| flag.to_string()).collect();
+        commands_fn::merge(flags, cliente.clone())
+    };
+    let checkout = |branch: &str| {
+        commands_fn::checkout(vec![branch.to_string()], cliente.clone()).unwrap();
+    };
+    let leer = |path: &str| read_file(work_tree.clone() + "/" + path).unwrap();
+    commitear(&cliente, &[("f", "base\n")], "base");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let padres = || get_parent_commit(get_current_commit(&repo).unwrap(), &repo).unwrap();
+
+    // --no-ff crea el commit de merge aunque se pueda hacer fast-forward
+    commands_fn::checkout(vec!["-b".to_string(), "b".to_string()], cliente.clone()).unwrap();
+    commitear(&cliente, &[("f", "b\n")], "b");
+    checkout("master");
+    let master = get_current_commit(&repo).unwrap();
+    assert!(merge(&["--no-ff", "b"]).is_ok());
+    assert_eq!(padres(), vec![master, rev_parse("b", &repo).unwrap()]);
+    assert_eq!(leer("f"), "b\n");
+
+    // --ff-only falla si las historias divergieron
+    checkout("b");
+    commitear(&cliente, &[("f", "b2\n")], "b2");
+    checkout("master");
+    commitear(&cliente, &[("g", "m\n")], "m");
+    let master = get_current_commit(&repo).unwrap();
+    assert_eq!(
+        merge(&["--ff-only", "b"]),
+        Err(GitrError::NonFastForward("b".to_string()))
+    );
+    assert_eq!(get_current_commit(&repo).unwrap(), master);
+
+    // --squash deja el resultado en el index sin mover HEAD ni dejar un merge en curso
+    assert_eq!(merge(&["--squash", "b"]).unwrap().0, false);
+    assert_eq!(get_current_commit(&repo).unwrap(), master);
+    assert_eq!(read_merge_head(&repo).unwrap(), None);
+    assert_eq!(leer("f"), "b2\n");
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"squash\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    assert_eq!(padres(), vec![master]);
+
+    // -X ours / -X theirs resuelven los conflictos de contenido
+    checkout("b");
+    commitear(&cliente, &[("f", "theirs\n")], "theirs");
+    checkout("master");
+    commitear(&cliente, &[("f", "ours\n")], "ours");
+    assert_eq!(merge(&["-X", "ours", "b"]).unwrap().0, false);
+    assert_eq!(leer("f"), "ours\n");
+    assert_eq!(padres().len(), 2);
+    checkout("b");
+    commitear(&cliente, &[("f", "theirs2\n")], "theirs2");
+    checkout("master");
+    commitear(&cliente, &[("f", "ours2\n")], "ours2");
+    assert_eq!(merge(&["-Xtheirs", "b"]).unwrap().0, false);
+    assert_eq!(leer("f"), "theirs2\n");
+    assert_eq!(padres().len(), 2);
+
+    assert!(merge(&["--squash", "--no-ff", "b"]).is_err());
+    assert!(merge(&["-X", "patience", "b"]).is_err());
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
 // /*********************
 //   COMMIT TESTS
 // *********************/
@@ -1059,7 +1709,8 @@ fn test_commit() {
     .unwrap();
     let res =
         file_manager::read_file(cliente.clone() + "/test_commit/gitr/refs/heads/master").unwrap();
-    let current_commit = file_manager::get_current_commit(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let current_commit = file_manager::get_current_commit(&repo).unwrap();
     assert_eq!(res, current_commit);
     fs::remove_dir_all(cliente.clone()).unwrap();
 }
@@ -1108,6 +1759,176 @@ fn test_checkout() {
     fs::remove_dir_all(cliente.clone()).unwrap();
 }
 
+#[test]
+#[serial]
+fn test_checkout_no_pisa_cambios_locales() {
+    let cliente = "cliente_checkout_seguro".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_checkout_seguro".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_checkout_seguro";
+    let escribir = |path: &str, data: &str| {
+        write_file(work_tree.clone() + "/" + path, data.to_string()).unwrap()
+    };
+    let leer = |path: &str| read_file(work_tree.clone() + "/" + path);
+    let commitear = |mensaje: &str| {
+        commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+        commands_fn::commit(
+            vec!["-m".to_string(), format!("\"{}\"", mensaje)],
+            "None".to_string(),
+            cliente.clone(),
+        )
+        .unwrap();
+    };
+    escribir("archivo", "master\n");
+    escribir("comun", "comun\n");
+    commitear("master");
+    commands_fn::branch(vec!["otra".to_string()], cliente.clone()).unwrap();
+    commands_fn::checkout(vec!["otra".to_string()], cliente.clone()).unwrap();
+    escribir("archivo", "otra\n");
+    fs::create_dir_all(work_tree.clone() + "/dir").unwrap();
+    escribir("dir/solo_otra", "otra\n");
+    commitear("otra");
+
+    // los archivos que no cambian entre branches conservan sus cambios locales
+    escribir("comun", "cambio local\n");
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    assert_eq!(leer("archivo").unwrap(), "master\n");
+    assert_eq!(leer("comun").unwrap(), "cambio local\n");
+    assert!(!Path::new(&(work_tree.clone() + "/dir")).exists());
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    assert!(diff_worktree(&OpcionesDiff::default(), &repo).unwrap().contains("+cambio local\n"));
+    assert_eq!(diff_cached(&OpcionesDiff::default(), &repo).unwrap(), "");
+
+    // un cambio local en un archivo que cambia entre branches frena el checkout
+    escribir("archivo", "sin commitear\n");
+    let res = commands_fn::checkout(vec!["otra".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::CheckoutConflict(vec!["archivo".to_string()])));
+    assert_eq!(leer("archivo").unwrap(), "sin commitear\n");
+    assert_eq!(get_head(&repo).unwrap(), "refs/heads/master");
+
+    // igual que un archivo sin trackear que el destino trae
+    commands_fn::restore(vec!["archivo".to_string()], cliente.clone()).unwrap();
+    fs::create_dir_all(work_tree.clone() + "/dir").unwrap();
+    escribir("dir/solo_otra", "sin trackear\n");
+    let res = commands_fn::checkout(vec!["otra".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::CheckoutConflict(vec!["dir/solo_otra".to_string()])));
+    fs::remove_dir_all(work_tree.clone() + "/dir").unwrap();
+    commands_fn::checkout(vec!["otra".to_string()], cliente.clone()).unwrap();
+    assert_eq!(leer("archivo").unwrap(), "otra\n");
+    assert_eq!(leer("dir/solo_otra").unwrap(), "otra\n");
+    assert_eq!(leer("comun").unwrap(), "cambio local\n");
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_checkout_detached_head() {
+    let cliente = "cliente_detached".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_detached".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_detached";
+    let escribir = |path: &str, data: &str| {
+        write_file(work_tree.clone() + "/" + path, data.to_string()).unwrap()
+    };
+    let leer = |path: &str| read_file(work_tree.clone() + "/" + path).unwrap();
+    let commitear = |mensaje: &str| {
+        commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+        commands_fn::commit(
+            vec!["-m".to_string(), format!("\"{}\"", mensaje)],
+            "None".to_string(),
+            cliente.clone(),
+        )
+        .unwrap();
+    };
+    escribir("archivo", "primero\n");
+    commitear("primero");
+    commands_fn::tag(vec!["v1".to_string()], cliente.clone()).unwrap();
+    escribir("archivo", "segundo\n");
+    commitear("segundo");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let primero = rev_parse("v1", &repo).unwrap();
+    let segundo = rev_parse("master", &repo).unwrap();
+
+    // checkout de un tag deja HEAD apuntando al commit
+    commands_fn::checkout(vec!["v1".to_string()], cliente.clone()).unwrap();
+    assert_eq!(read_file(repo.git_path("HEAD")).unwrap(), primero);
+    assert_eq!(get_head(&repo).unwrap(), "HEAD");
+    assert_eq!(get_current_branch(&repo).unwrap(), None);
+    assert_eq!(leer("archivo"), "primero\n");
+    let status = status_print_current_branch(&repo).unwrap();
+    assert!(status.contains(&format!("HEAD detached at {}", &primero[..7])));
+    assert!(print_branches(&repo).unwrap().contains(&format!("(HEAD detached at {})", &primero[..7])));
+
+    // commitear en detached mueve HEAD pero no master
+    escribir("archivo", "perdido\n");
+    commitear("perdido");
+    let perdido = get_current_commit(&repo).unwrap();
+    assert_ne!(perdido, primero);
+    assert_eq!(rev_parse("HEAD~1", &repo).unwrap(), primero);
+    assert_eq!(rev_parse("master", &repo).unwrap(), segundo);
+    commands_fn::log(vec![], cliente.clone()).unwrap();
+    assert_eq!(
+        commits_sin_referencia(&perdido, &segundo, &repo).unwrap(),
+        vec![perdido.clone()]
+    );
+
+    // volver a una branch deja HEAD en la ref
+    commands_fn::checkout(vec!["master".to_string()], cliente.clone()).unwrap();
+    assert_eq!(read_file(repo.git_path("HEAD")).unwrap(), "ref: refs/heads/master");
+    assert_eq!(leer("archivo"), "segundo\n");
+
+    // checkout -b desde un commit detached
+    commands_fn::checkout(vec![perdido[..7].to_string()], cliente.clone()).unwrap();
+    commands_fn::checkout(vec!["-b".to_string(), "rescate".to_string()], cliente.clone()).unwrap();
+    assert_eq!(get_current_branch(&repo).unwrap(), Some("rescate".to_string()));
+    assert_eq!(rev_parse("rescate", &repo).unwrap(), perdido);
+
+    let res = commands_fn::checkout(vec!["no_existe".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::BranchNonExistsError("no_existe".to_string())));
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_checkout_binary_file_round_trips() {
+    let cliente = "cliente_checkout_binario".to_string();
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_checkout".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let binario: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff, 0xfe, 0x80];
+    write_file_bytes(cliente.clone() + "/test_checkout/imagen.png", &binario).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"commit 1\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    let hash = Blob::new(binario.clone()).unwrap().get_hash();
+    assert_eq!(hash, "e6bf6df4979b51d8367b166aeb686d5095c03528");
+    commands_fn::branch(vec!["branch1".to_string()], cliente.clone()).unwrap();
+    commands_fn::checkout(vec!["branch1".to_string()], cliente.clone()).unwrap();
+    let res = fs::read(cliente.clone() + "/test_checkout/imagen.png").unwrap();
+    assert_eq!(res, binario);
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let blob_data = read_file_data_from_blob_hash(hash, &repo).unwrap();
+    assert_eq!(blob_data, binario);
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
 // /*********************
 //   STATUS TESTS
 // *********************/
@@ -1129,8 +1950,9 @@ fn test_status_new_file_untracked() {
         (cliente.clone() + "/test_status_new_file_added/blob1").to_string(),
         "Hello, im blob 1".to_string(),
     );
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
     let (not_staged, untracked_files, hayindex) =
-        get_untracked_notstaged_files(cliente.clone()).unwrap();
+        get_untracked_notstaged_files(&repo).unwrap();
 
     assert!(untracked_files.contains(&(cliente.clone() + "/test_status_new_file_added/blob1")));
     assert!(!not_staged.contains(&"blob1".to_string()));
@@ -1157,9 +1979,10 @@ fn test_status_new_file_added() {
         "Hello, im blob 1".to_string(),
     );
     commands_fn::add(vec!["blob1".to_string()], cliente.clone()).unwrap();
-    let (not_staged, _, _) = get_untracked_notstaged_files(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let (not_staged, _, _) = get_untracked_notstaged_files(&repo).unwrap();
     let (new_files, modified_files) =
-        get_tobe_commited_files(&not_staged, cliente.clone()).unwrap();
+        get_tobe_commited_files(&not_staged, &repo).unwrap();
     assert!(new_files.contains(&(cliente.clone() + "/test_status_new_file_added/blob1")));
     assert!(!modified_files.contains(&"blob1".to_string()));
     fs::remove_dir_all(cliente.clone()).unwrap();
@@ -1195,14 +2018,65 @@ fn test_status_new_file_modified() {
         "Hello, im blob 1 modified".to_string(),
     );
     commands_fn::add(vec!["blob1".to_string()], cliente.clone()).unwrap();
-    let (not_staged, _, _) = get_untracked_notstaged_files(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let (not_staged, _, _) = get_untracked_notstaged_files(&repo).unwrap();
     let (new_files, modified_files) =
-        get_tobe_commited_files(&not_staged, cliente.clone()).unwrap();
+        get_tobe_commited_files(&not_staged, &repo).unwrap();
     assert!(!new_files.contains(&(cliente.clone() + "/test_status_new_file_modified/blob1")));
     assert!(modified_files.contains(&(cliente.clone() + "/test_status_new_file_modified/blob1")));
     fs::remove_dir_all(cliente.clone()).unwrap();
 }
 
+#[test]
+#[serial]
+fn test_status_no_rehashea_archivos_sin_cambios() {
+    let cliente = "cliente_status_stat_cache".to_string();
+    let repo_path = cliente.clone() + "/test_status_stat_cache";
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_status_stat_cache".to_string()], cliente.clone()).unwrap();
+    // mtime en el pasado para que ningun archivo quede racy respecto del index
+    let antes = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+    for file in 0..10 {
+        let contenido = format!("contenido {}\n", file);
+        let file_path = format!("{}/file{}", repo_path, file);
+        fs::write(&file_path, contenido).unwrap();
+        let archivo = fs::File::options().write(true).open(&file_path).unwrap();
+        archivo.set_modified(antes).unwrap();
+    }
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let working_dir = get_working_dir_hashmap(&repo).unwrap();
+    assert_eq!(working_dir.len(), 11); // los 10 archivos y el gitrignore
+
+    // despues del refresh la entrada conserva su stat data y el stat cache la acepta
+    let file_path = repo_path.clone() + "/file3";
+    let mut index = read_index(&repo).unwrap();
+    let entry = index.get("file3").unwrap().clone();
+    assert!(entry.stat_matches(&file_path, gitr::index::file_mtime(&repo.git_path("index"))));
+
+    // si se rehasheara el archivo apareceria su hash real y no el del index
+    let hash_falso = "0123456789abcdef0123456789abcdef01234567".to_string();
+    index.get_mut("file3").unwrap().hash = hash_falso.clone();
+    write_index(&index, &repo).unwrap();
+    let working_dir = get_working_dir_hashmap(&repo).unwrap();
+    assert_eq!(working_dir[&file_path], hash_falso);
+    let refrescada = read_index(&repo).unwrap().get("file3").unwrap().clone();
+    assert_eq!(
+        refrescada,
+        IndexEntry {
+            hash: hash_falso,
+            ..entry
+        }
+    );
+
+    let modificado = repo_path.clone() + "/file7";
+    fs::write(&modificado, "otro contenido").unwrap();
+    let (not_staged, _, _) = get_untracked_notstaged_files(&repo).unwrap();
+    assert_eq!(not_staged, vec![modificado]);
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
 // /*********************
 //   LS-TREE TESTS
 // *********************/
@@ -1225,17 +2099,624 @@ fn test_ls_tree_sin_flags_se_comporta_como_cat_file() {
     )
     .unwrap();
 
-    let current_commit = file_manager::get_current_commit(cliente.clone()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let current_commit = file_manager::get_current_commit(&repo).unwrap();
 
-    let commit =
-        file_manager::read_object(&current_commit, cliente.clone() + "/test/gitr/", false).unwrap();
+    let commit = file_manager::read_object(&current_commit, &repo).unwrap();
 
     let _tree_hash = commit.split(" ").collect::<Vec<&str>>()[2].to_string();
     let tree_hash = _tree_hash.split("\n").collect::<Vec<&str>>()[0].to_string();
     let res =
-        command_utils::_ls_tree(vec![tree_hash.clone()], "".to_string(), cliente.clone()).unwrap();
+        command_utils::_ls_tree(vec![tree_hash.clone()], "".to_string(), &repo).unwrap();
 
-    let cat_file = _cat_file(vec!["-p".to_string(), tree_hash], cliente.clone()).unwrap();
+    let cat_file = _cat_file(vec!["-p".to_string(), tree_hash], &repo).unwrap();
 
     assert_eq!(res, cat_file);
 }
+
+// /*********************
+//   GC TESTS
+// *********************/
+fn commit_archivo(cliente: &String, contenido: &str, mensaje: &str) {
+    write_file(cliente.clone() + "/test_gc/archivo", contenido.to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), mensaje.to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+}
+
+#[test]
+#[serial]
+fn test_gc_empaqueta_alcanzables_y_borra_inalcanzables() {
+    let cliente = "cliente_gc".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_gc".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let base = "linea de un archivo bastante largo para que convenga el delta\n".repeat(20);
+    commit_archivo(&cliente, &base, "\"commit 1\"");
+    commit_archivo(&cliente, &(base.clone() + "una linea mas\n"), "\"commit 2\"");
+    write_file(cliente.clone() + "/test_gc/suelto", "nadie me usa".to_string()).unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let suelto = get_object_hash(&repo, &mut "suelto".to_string(), true).unwrap();
+    let objetos = get_all_objects_hashes(&repo).unwrap();
+
+    // con el tiempo de gracia por defecto el objeto inalcanzable se conserva
+    let stats = gc_(&repo, Some(GC_PRUNE_GRACE)).unwrap();
+    assert_eq!(stats.packs_after, 1);
+    assert_eq!(stats.packed, objetos.len() - 1);
+    assert_eq!(stats.loose_after, 1);
+    assert_eq!(stats.pruned, 0);
+
+    // repetir el gc no cambia el pack
+    let stats = gc_(&repo, None).unwrap();
+    assert_eq!((stats.packs_before, stats.packs_after), (1, 1));
+
+    let stats = gc_(&repo, Some(std::time::Duration::ZERO)).unwrap();
+    assert_eq!(stats.loose_after, 0);
+    assert_eq!(stats.pruned, 1);
+
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let mut restantes = get_all_objects_hashes(&repo).unwrap();
+    let mut esperados: Vec<String> = objetos.into_iter().filter(|h| *h != suelto).collect();
+    restantes.sort();
+    esperados.sort();
+    assert_eq!(restantes, esperados);
+    for hash in restantes {
+        assert!(file_manager::get_object_bytes(hash, &repo).is_ok());
+    }
+    let master = file_manager::get_commit("master".to_string(), &repo).unwrap();
+    commands_fn::branch(vec!["otra".to_string()], cliente.clone()).unwrap();
+    commands_fn::checkout(vec!["otra".to_string()], cliente.clone()).unwrap();
+    assert_eq!(file_manager::get_current_commit(&repo).unwrap(), master);
+    assert_eq!(
+        file_manager::read_file(cliente.clone() + "/test_gc/archivo").unwrap(),
+        base + "una linea mas\n"
+    );
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+// /*********************
+//   FSCK TESTS
+// *********************/
+#[test]
+#[serial]
+fn test_fsck_detecta_corruptos_faltantes_colgados_y_refs_invalidas() {
+    let cliente = "cliente_fsck".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_fsck".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    write_file(cliente.clone() + "/test_fsck/blob1", "Hello, im blob 1".to_string()).unwrap();
+    write_file(cliente.clone() + "/test_fsck/blob2", "Hello, im blob 2".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"commit 1\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let report = fsck_(&repo).unwrap();
+    assert!(report.is_ok());
+    assert!(report.dangling.is_empty());
+    assert_eq!(report.checked, 4);
+    commands_fn::fsck(vec![], cliente.clone()).unwrap();
+
+    // blob que no usa nadie
+    write_file(cliente.clone() + "/test_fsck/suelto", "nadie me usa".to_string()).unwrap();
+    let suelto = get_object_hash(&repo, &mut "suelto".to_string(), true).unwrap();
+    // blob1 con contenido que no corresponde a su hash
+    let blob1 = "016a41a6a35d50d311286359f1a7611948a9c529";
+    let objects = cliente.clone() + "/test_fsck/gitr/objects/";
+    let otro = flate2compress2(b"blob 3\0abc".to_vec()).unwrap();
+    write_file_bytes(format!("{}{}/{}", objects, &blob1[..2], &blob1[2..]), &otro).unwrap();
+    // blob2 borrado
+    let blob2 = Blob::new(b"Hello, im blob 2".to_vec()).unwrap().get_hash();
+    fs::remove_file(format!("{}{}/{}", objects, &blob2[..2], &blob2[2..])).unwrap();
+    write_file(cliente.clone() + "/test_fsck/gitr/refs/heads/rota", "no es un hash".to_string()).unwrap();
+
+    let report = fsck_(&repo).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.corrupt.len(), 1);
+    assert!(report.corrupt[0].starts_with(blob1));
+    assert_eq!(report.missing, vec![format!("blob {}", blob2)]);
+    assert_eq!(report.dangling, vec![format!("blob {}", suelto)]);
+    assert_eq!(report.bad_refs.len(), 1);
+    assert!(report.bad_refs[0].starts_with("refs/heads/rota"));
+    assert!(matches!(
+        commands_fn::fsck(vec![], cliente.clone()),
+        Err(GitrError::FsckError(_, 3))
+    ));
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+// /*********************
+//   FILE MODE TESTS
+// *********************/
+#[test]
+#[serial]
+fn test_modos_ejecutable_y_symlink() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    let cliente = "cliente_modos".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_modos".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_modos";
+    write_file(work_tree.clone() + "/normal", "hola".to_string()).unwrap();
+    write_file(work_tree.clone() + "/script.sh", "echo hola".to_string()).unwrap();
+    fs::set_permissions(work_tree.clone() + "/script.sh", fs::Permissions::from_mode(0o755)).unwrap();
+    symlink("script.sh", work_tree.clone() + "/link").unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"modos\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let commit = get_current_commit(&repo).unwrap();
+    let tree = get_main_tree(commit.clone(), &repo).unwrap();
+    let link_blob = Blob::new(b"script.sh".to_vec()).unwrap().get_hash();
+    let ls_tree = _ls_tree(vec![tree], "".to_string(), &repo).unwrap();
+    assert!(ls_tree.contains(&format!("120000 blob {} link\n", link_blob)));
+    assert!(ls_tree.contains("100644 blob"));
+    assert!(ls_tree.contains("100755 blob"));
+    let modos: Vec<(u32, String)> = read_index(&repo)
+        .unwrap()
+        .entries()
+        .iter()
+        .map(|e| (e.mode, e.path.clone()))
+        .collect();
+    assert_eq!(
+        modos,
+        vec![
+            (0o120000, "link".to_string()),
+            (0o100644, "normal".to_string()),
+            (0o100755, "script.sh".to_string())
+        ]
+    );
+
+    // el checkout vuelve a crear el symlink y el bit de ejecucion
+    fs::remove_file(work_tree.clone() + "/link").unwrap();
+    fs::set_permissions(work_tree.clone() + "/script.sh", fs::Permissions::from_mode(0o644)).unwrap();
+    update_working_directory(commit, &repo).unwrap();
+    let link = fs::symlink_metadata(work_tree.clone() + "/link").unwrap();
+    assert!(link.file_type().is_symlink());
+    assert_eq!(
+        fs::read_link(work_tree.clone() + "/link").unwrap(),
+        Path::new("script.sh")
+    );
+    let script = fs::metadata(work_tree.clone() + "/script.sh").unwrap();
+    assert_ne!(script.permissions().mode() & 0o111, 0);
+    let normal = fs::metadata(work_tree.clone() + "/normal").unwrap();
+    assert_eq!(normal.permissions().mode() & 0o111, 0);
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+// /*********************
+//   DIFF TESTS
+// *********************/
+#[test]
+#[serial]
+fn test_diff_worktree_cached_y_commits() {
+    let cliente = "cliente_diff".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_diff".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_diff";
+    write_file(work_tree.clone() + "/archivo", "uno\ndos\ntres\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"primero\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let primero = get_current_commit(&repo).unwrap();
+
+    write_file(work_tree.clone() + "/archivo", "uno\nDOS\ntres\n".to_string()).unwrap();
+    let diff = diff_worktree(&OpcionesDiff::default(), &repo).unwrap();
+    assert!(diff.starts_with("diff --git a/archivo b/archivo\n"));
+    assert!(diff.contains("--- a/archivo\n+++ b/archivo\n@@ -1,3 +1,3 @@\n uno\n-dos\n+DOS\n tres\n"));
+    assert_eq!(diff_cached(&OpcionesDiff::default(), &repo).unwrap(), "");
+
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    write_file(work_tree.clone() + "/nuevo", "hola\n".to_string()).unwrap();
+    commands_fn::add(vec!["nuevo".to_string()], cliente.clone()).unwrap();
+    assert_eq!(diff_worktree(&OpcionesDiff::default(), &repo).unwrap(), "");
+    let sin_contexto = OpcionesDiff {
+        contexto: 0,
+        ..OpcionesDiff::default()
+    };
+    let cached = diff_cached(&sin_contexto, &repo).unwrap();
+    assert!(cached.contains("@@ -2 +2 @@\n-dos\n+DOS\n"));
+    assert!(cached.contains("new file mode 100644\n"));
+    assert!(cached.contains("--- /dev/null\n+++ b/nuevo\n@@ -0,0 +1 @@\n+hola\n"));
+
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"segundo\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    let segundo = get_current_commit(&repo).unwrap();
+    assert_eq!(diff_commits(&primero, "master", &sin_contexto, &repo).unwrap(), cached);
+    let al_reves = diff_commits(&segundo, &primero, &sin_contexto, &repo).unwrap();
+    assert!(al_reves.contains("deleted file mode 100644\n"));
+    assert!(al_reves.contains("--- a/nuevo\n+++ /dev/null\n@@ -1 +0,0 @@\n-hola\n"));
+    assert!(diff_commits("no_existe", &primero, &sin_contexto, &repo).is_err());
+    let por_palabras = OpcionesDiff {
+        formato: FormatoDiff::Palabras,
+        ..OpcionesDiff::default()
+    };
+    let palabras = diff_commits(&primero, &segundo, &por_palabras, &repo).unwrap();
+    assert!(palabras.contains("@@ -1,3 +1,3 @@\nuno\n[-dos-]{+DOS+}\ntres\n"));
+    assert!(palabras.contains("+++ b/nuevo\n@@ -0,0 +1 @@\n{+hola+}\n"));
+    let stat = OpcionesDiff {
+        formato: FormatoDiff::Stat,
+        ..OpcionesDiff::default()
+    };
+    assert_eq!(
+        diff_commits(&primero, &segundo, &stat, &repo).unwrap(),
+        " archivo | 2 +-\n nuevo   | 1 +\n 2 files changed, 2 insertions(+), 1 deletion(-)\n"
+    );
+    let numstat = OpcionesDiff {
+        formato: FormatoDiff::Numstat,
+        ..OpcionesDiff::default()
+    };
+    assert_eq!(
+        diff_commits(&segundo, &primero, &numstat, &repo).unwrap(),
+        "1\t1\tarchivo\n0\t1\tnuevo\n"
+    );
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_show_commit_tag_y_tree() {
+    let cliente = "cliente_show".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_show".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_show";
+    write_file(work_tree.clone() + "/archivo", "uno\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"primero\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    write_file(work_tree.clone() + "/archivo", "uno\ndos\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::commit(
+        vec!["-m".to_string(), "\"segundo\"".to_string()],
+        "None".to_string(),
+        cliente.clone(),
+    )
+    .unwrap();
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let segundo = get_current_commit(&repo).unwrap();
+    let opciones = OpcionesDiff::default();
+
+    let show = show_("HEAD", &opciones, &repo).unwrap();
+    assert!(show.starts_with(&format!("commit: {}\nAuthor: test\nDate: ", segundo)));
+    assert!(show.contains("\tsegundo\n"));
+    assert!(show.contains("\n\ndiff --git a/archivo b/archivo\n"));
+    assert!(show.ends_with("@@ -1 +1,2 @@\n uno\n+dos\n"));
+    assert_eq!(show_("master", &opciones, &repo).unwrap(), show);
+    let primero = get_parent_commit(segundo.clone(), &repo).unwrap()[0].clone();
+    assert!(show_(&primero, &opciones, &repo).unwrap().contains("new file mode 100644\n"));
+
+    commands_fn::tag(
+        vec!["-a".to_string(), "v1".to_string(), "-m".to_string(), "version".to_string()],
+        cliente.clone(),
+    )
+    .unwrap();
+    let tag = show_("v1", &opciones, &repo).unwrap();
+    assert!(tag.starts_with("tag v1\nTagger: test <test@gmail.com>\nDate: "));
+    assert!(tag.ends_with(&show));
+    commands_fn::tag(vec!["liviana".to_string()], cliente.clone()).unwrap();
+    assert_eq!(show_("liviana", &opciones, &repo).unwrap(), show);
+
+    let tree = get_main_tree(segundo.clone(), &repo).unwrap();
+    let blob = Blob::new("uno\ndos\n".as_bytes().to_vec()).unwrap().get_hash();
+    assert_eq!(
+        show_(&tree, &opciones, &repo).unwrap(),
+        format!("tree {}\n\n100644 blob {} archivo\n", tree, blob)
+    );
+    assert_eq!(show_(&blob, &opciones, &repo).unwrap(), "uno\ndos\n");
+    assert!(show_("no_existe", &opciones, &repo).is_err());
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_rev_parse_refs_y_comandos() {
+    let cliente = "cliente_rev_parse".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_rev_parse".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_rev_parse";
+    let mut commits = vec![];
+    for i in 0..3 {
+        write_file(work_tree.clone() + "/archivo", format!("version {}\n", i)).unwrap();
+        commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+        commands_fn::commit(
+            vec!["-m".to_string(), format!("\"commit {}\"", i)],
+            "None".to_string(),
+            cliente.clone(),
+        )
+        .unwrap();
+        let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+        commits.push(get_current_commit(&repo).unwrap());
+    }
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    commands_fn::tag(
+        vec!["-a".to_string(), "v1".to_string(), "-m".to_string(), "version".to_string()],
+        cliente.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(rev_parse("HEAD", &repo).unwrap(), commits[2]);
+    assert_eq!(rev_parse("@", &repo).unwrap(), commits[2]);
+    assert_eq!(rev_parse("master~2", &repo).unwrap(), commits[0]);
+    assert_eq!(rev_parse("HEAD^^", &repo).unwrap(), commits[0]);
+    assert_eq!(rev_parse("refs/heads/master^", &repo).unwrap(), commits[1]);
+    assert_eq!(rev_parse(&commits[1][..7], &repo).unwrap(), commits[1]);
+    assert_eq!(rev_parse("v1^{}", &repo).unwrap(), commits[2]);
+    assert_eq!(rev_parse("v1~1", &repo).unwrap(), commits[1]);
+    assert_eq!(
+        rev_parse("v1^{tree}", &repo).unwrap(),
+        get_main_tree(commits[2].clone(), &repo).unwrap()
+    );
+    assert!(matches!(rev_parse("HEAD~3", &repo), Err(GitrError::NoCommitExisting(_))));
+    assert!(matches!(rev_parse("@{u}", &repo), Err(GitrError::NoUpstream(_))));
+    write_file(repo.git_path("refs/remotes/daemon/master"), commits[0].clone()).unwrap();
+    assert_eq!(rev_parse("@{upstream}", &repo).unwrap(), commits[0]);
+    assert_eq!(rev_parse("master@{u}", &repo).unwrap(), commits[0]);
+
+    // los comandos que reciben revisiones las resuelven con rev_parse
+    let cat = _cat_file(vec!["-p".to_string(), "HEAD~1".to_string()], &repo).unwrap();
+    assert!(cat.contains("commit 1"));
+    let ls = _ls_tree(vec!["HEAD".to_string()], "".to_string(), &repo).unwrap();
+    assert!(ls.ends_with(" archivo\n"));
+    let diff = diff_commits("HEAD~2", "v1", &OpcionesDiff::default(), &repo).unwrap();
+    assert!(diff.contains("-version 0\n+version 2\n"));
+    assert_eq!(
+        branch_commits_list("HEAD~1".to_string(), &repo).unwrap(),
+        vec![commits[1].clone(), commits[0].clone()]
+    );
+    assert!(commands_fn::rev_parse(vec!["HEAD~1".to_string()], cliente.clone()).is_ok());
+    assert!(commands_fn::rev_parse(vec!["nada".to_string()], cliente.clone()).is_err());
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_reset_soft_mixed_hard_y_paths() {
+    let cliente = "cliente_reset".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_reset".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_reset";
+    let mut commits = vec![];
+    for i in 0..3 {
+        write_file(work_tree.clone() + "/archivo", format!("version {}\n", i)).unwrap();
+        commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+        commands_fn::commit(
+            vec!["-m".to_string(), format!("\"commit {}\"", i)],
+            "None".to_string(),
+            cliente.clone(),
+        )
+        .unwrap();
+        let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+        commits.push(get_current_commit(&repo).unwrap());
+    }
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let sin_contexto = OpcionesDiff {
+        contexto: 0,
+        ..OpcionesDiff::default()
+    };
+
+    // --soft: solo se mueve la branch, el index y el work tree quedan en la version 2
+    commands_fn::reset(vec!["--soft".to_string(), "HEAD~1".to_string()], cliente.clone()).unwrap();
+    assert_eq!(get_current_commit(&repo).unwrap(), commits[1]);
+    assert!(diff_cached(&sin_contexto, &repo).unwrap().contains("-version 1\n+version 2\n"));
+    assert_eq!(diff_worktree(&sin_contexto, &repo).unwrap(), "");
+
+    // --mixed (por defecto): el index vuelve al commit pero el work tree no
+    commands_fn::reset(vec!["HEAD~1".to_string()], cliente.clone()).unwrap();
+    assert_eq!(get_current_commit(&repo).unwrap(), commits[0]);
+    assert_eq!(diff_cached(&sin_contexto, &repo).unwrap(), "");
+    assert!(diff_worktree(&sin_contexto, &repo).unwrap().contains("-version 0\n+version 2\n"));
+
+    // --hard: tambien el work tree
+    commands_fn::reset(vec!["--hard".to_string(), commits[2].clone()], cliente.clone()).unwrap();
+    assert_eq!(get_current_commit(&repo).unwrap(), commits[2]);
+    assert_eq!(read_file(work_tree.clone() + "/archivo").unwrap(), "version 2\n");
+    assert_eq!(diff_worktree(&sin_contexto, &repo).unwrap(), "");
+
+    // reset -- <path>: saca del index lo agregado sin tocar la branch ni el work tree
+    write_file(work_tree.clone() + "/archivo", "otra\n".to_string()).unwrap();
+    write_file(work_tree.clone() + "/nuevo", "nuevo\n".to_string()).unwrap();
+    commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+    commands_fn::reset(vec!["--".to_string(), "nuevo".to_string()], cliente.clone()).unwrap();
+    let cached = diff_cached(&sin_contexto, &repo).unwrap();
+    assert!(cached.contains("-version 2\n+otra\n"));
+    assert!(!cached.contains("nuevo"));
+    commands_fn::reset(vec!["--".to_string(), ".".to_string()], cliente.clone()).unwrap();
+    assert_eq!(diff_cached(&sin_contexto, &repo).unwrap(), "");
+    assert_eq!(get_current_commit(&repo).unwrap(), commits[2]);
+    assert_eq!(read_file(work_tree.clone() + "/nuevo").unwrap(), "nuevo\n");
+    let invalido = vec!["--soft".to_string(), "--".to_string()];
+    assert!(commands_fn::reset(invalido, cliente.clone()).is_err());
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_reset_hard_mantiene_archivos_sin_trackear() {
+    let cliente = "cliente_reset_hard".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_reset_hard".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_reset_hard";
+    fs::create_dir_all(work_tree.clone() + "/dir").unwrap();
+    write_file(work_tree.clone() + "/dir/otro", "otro\n".to_string()).unwrap();
+    let mut commits = vec![];
+    for i in 0..2 {
+        write_file(work_tree.clone() + "/archivo", format!("version {}\n", i)).unwrap();
+        if i == 1 {
+            write_file(work_tree.clone() + "/dir/nuevo", "nuevo\n".to_string()).unwrap();
+        }
+        commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+        commands_fn::commit(
+            vec!["-m".to_string(), format!("\"commit {}\"", i)],
+            "None".to_string(),
+            cliente.clone(),
+        )
+        .unwrap();
+        let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+        commits.push(get_current_commit(&repo).unwrap());
+    }
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+
+    // un archivo agregado al index, uno modificado y dos sin trackear
+    write_file(work_tree.clone() + "/agregado", "agregado\n".to_string()).unwrap();
+    commands_fn::add(vec!["agregado".to_string()], cliente.clone()).unwrap();
+    write_file(work_tree.clone() + "/dir/otro", "cambio local\n".to_string()).unwrap();
+    write_file(work_tree.clone() + "/sin_trackear", "mio\n".to_string()).unwrap();
+    write_file(work_tree.clone() + "/dir/suelto", "mio\n".to_string()).unwrap();
+
+    commands_fn::reset(vec!["--hard".to_string(), commits[0].clone()], cliente.clone()).unwrap();
+    assert_eq!(get_current_commit(&repo).unwrap(), commits[0]);
+    assert_eq!(read_file(work_tree.clone() + "/archivo").unwrap(), "version 0\n");
+    assert_eq!(read_file(work_tree.clone() + "/dir/otro").unwrap(), "otro\n");
+    assert!(!Path::new(&(work_tree.clone() + "/dir/nuevo")).exists());
+    assert!(!Path::new(&(work_tree.clone() + "/agregado")).exists());
+    assert_eq!(read_file(work_tree.clone() + "/sin_trackear").unwrap(), "mio\n");
+    assert_eq!(read_file(work_tree.clone() + "/dir/suelto").unwrap(), "mio\n");
+    let index = read_index(&repo).unwrap();
+    let paths: Vec<&str> = index.entries().iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(paths, vec!["archivo", "dir/otro"]);
+    assert_eq!(diff_worktree(&OpcionesDiff::default(), &repo).unwrap(), "");
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_reset_que_falla_no_mueve_la_branch() {
+    let cliente = "cliente_reset_falla".to_string();
+    let work_tree = crear_repo(&cliente, "test_reset_falla", "");
+    commitear(&cliente, &[("archivo", "uno\n")], "uno");
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let primero = get_current_commit(&repo).unwrap();
+    let blob = read_index(&repo)
+        .unwrap()
+        .get("archivo")
+        .unwrap()
+        .hash
+        .clone();
+    commitear(&cliente, &[("archivo", "dos\n")], "dos");
+    let segundo = get_current_commit(&repo).unwrap();
+
+    // sin el blob no se puede rehacer el work tree
+    fs::remove_file(repo.git_path(&format!("objects/{}/{}", &blob[..2], &blob[2..]))).unwrap();
+    assert!(commands_fn::reset(vec!["--hard".to_string(), primero], cliente.clone()).is_err());
+    assert_eq!(get_current_commit(&repo).unwrap(), segundo);
+    assert_eq!(read_file(work_tree.clone() + "/archivo").unwrap(), "dos\n");
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}
+
+#[test]
+#[serial]
+fn test_restore_y_checkout_de_paths() {
+    let cliente = "cliente_restore".to_string();
+    let _ = fs::remove_dir_all(&cliente);
+    fs::create_dir_all(Path::new(&cliente)).unwrap();
+    commands_fn::init(vec!["test_restore".to_string()], cliente.clone()).unwrap();
+    let _ = write_file(
+        (cliente.clone() + "/gitrconfig").to_string(),
+        "[user]\n\tname = test\n\temail =test@gmail.com".to_string(),
+    );
+    let work_tree = cliente.clone() + "/test_restore";
+    fs::create_dir_all(work_tree.clone() + "/dir").unwrap();
+    for i in 0..2 {
+        write_file(work_tree.clone() + "/archivo", format!("version {}\n", i)).unwrap();
+        write_file(work_tree.clone() + "/dir/otro", format!("otro {}\n", i)).unwrap();
+        commands_fn::add(vec![".".to_string()], cliente.clone()).unwrap();
+        commands_fn::commit(
+            vec!["-m".to_string(), format!("\"commit {}\"", i)],
+            "None".to_string(),
+            cliente.clone(),
+        )
+        .unwrap();
+    }
+    let repo = file_manager::open_current_repo(cliente.clone()).unwrap();
+    let head = get_current_commit(&repo).unwrap();
+    let leer = |path: &str| read_file(work_tree.clone() + "/" + path).unwrap();
+
+    // desde el index: solo se toca el path pedido
+    write_file(work_tree.clone() + "/archivo", "cambio\n".to_string()).unwrap();
+    write_file(work_tree.clone() + "/dir/otro", "cambio\n".to_string()).unwrap();
+    commands_fn::restore(vec!["archivo".to_string()], cliente.clone()).unwrap();
+    assert_eq!(leer("archivo"), "version 1\n");
+    assert_eq!(leer("dir/otro"), "cambio\n");
+    commands_fn::checkout(vec!["--".to_string(), "dir".to_string()], cliente.clone()).unwrap();
+    assert_eq!(leer("dir/otro"), "otro 1\n");
+
+    // desde una revision: restore no toca el index, checkout si
+    let flags = vec!["--source".to_string(), "HEAD~1".to_string(), "archivo".to_string()];
+    commands_fn::restore(flags, cliente.clone()).unwrap();
+    assert_eq!(leer("archivo"), "version 0\n");
+    assert_eq!(diff_cached(&OpcionesDiff::default(), &repo).unwrap(), "");
+    let flags = vec!["HEAD~1".to_string(), "--".to_string(), "dir/otro".to_string()];
+    commands_fn::checkout(flags, cliente.clone()).unwrap();
+    assert_eq!(leer("dir/otro"), "otro 0\n");
+    let cached = diff_cached(&OpcionesDiff::default(), &repo).unwrap();
+    assert!(cached.contains("-otro 1\n+otro 0\n"));
+    assert!(!cached.contains("archivo"));
+    assert_eq!(get_current_commit(&repo).unwrap(), head);
+
+    let res = commands_fn::restore(vec!["no_existe".to_string()], cliente.clone());
+    assert_eq!(res, Err(GitrError::PathspecError("no_existe".to_string())));
+    assert!(commands_fn::restore(vec!["--source".to_string()], cliente.clone()).is_err());
+    fs::remove_dir_all(cliente.clone()).unwrap();
+}